@@ -0,0 +1,64 @@
+// 单帧处理吞吐量：加窗 + 实数FFT + 平均 + 单位换算
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use myalgorithm::{
+    AmplitudeScale, AveragingSettings, Calibration, SpectrumAverager, SpectrumEngine, SpectrumScaler, WindowFunction,
+    FFT_SIZES,
+};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+fn test_signal(n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE;
+            0.5 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin() + 0.01 * ((i * 7919 % 1000) as f32 / 500.0 - 1.0)
+        })
+        .collect()
+}
+
+fn bench_spectrum_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spectrum_frame");
+
+    for &fft_size in &FFT_SIZES {
+        let signal = test_signal(fft_size);
+        let mut engine = SpectrumEngine::new(fft_size, WindowFunction::Hann);
+        let mut averager = SpectrumAverager::new(engine.num_bins(), AveragingSettings::default());
+        let scaler = SpectrumScaler::new(AmplitudeScale::Dbfs, Calibration::default(), engine.window(), SAMPLE_RATE);
+        let mut magnitudes = vec![0.0; engine.num_bins()];
+        let mut scaled = vec![0.0; engine.num_bins()];
+        let frame_interval = fft_size as f32 / SAMPLE_RATE;
+
+        // 以每秒处理的样本数衡量吞吐量
+        group.throughput(Throughput::Elements(fft_size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(fft_size), &fft_size, |b, _| {
+            b.iter(|| {
+                engine.process(black_box(&signal), &mut magnitudes);
+                averager.process(&magnitudes, frame_interval);
+                scaler.scale_into(&magnitudes, &mut scaled);
+                black_box(&scaled);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_fft_only(c: &mut Criterion) {
+    let mut group = c.benchmark_group("real_fft");
+
+    for &fft_size in &FFT_SIZES {
+        let signal = test_signal(fft_size);
+        let mut engine = SpectrumEngine::new(fft_size, WindowFunction::Hann);
+        let mut magnitudes = vec![0.0; engine.num_bins()];
+
+        group.throughput(Throughput::Elements(fft_size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(fft_size), &fft_size, |b, _| {
+            b.iter(|| engine.process(black_box(&signal), &mut magnitudes))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_spectrum_frame, bench_fft_only);
+criterion_main!(benches);
@@ -0,0 +1,322 @@
+// 频谱平均：在线性幅度域上对逐帧的FFT模值做平均、峰值保持和最小值保持，
+// 平均之后再换算成显示单位，保证读数仍然是校准过的
+
+// 平均方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AveragingMode {
+    // 最近N帧幅度的算术平均
+    #[default]
+    Linear,
+    // 功率的指数平均，按时间常数衰减
+    Exponential,
+    // 最近N帧功率的平均再开方
+    RmsPower,
+}
+
+impl AveragingMode {
+    pub const ALL: [AveragingMode; 3] = [
+        AveragingMode::Linear,
+        AveragingMode::Exponential,
+        AveragingMode::RmsPower,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AveragingMode::Linear => "Linear",
+            AveragingMode::Exponential => "Exponential",
+            AveragingMode::RmsPower => "RMS",
+        }
+    }
+}
+
+// 峰值保持的回落方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeakHold {
+    // 一直保持，直到复位
+    Infinite,
+    // 按给定速率回落
+    Decay { db_per_second: f32 },
+}
+
+impl Default for PeakHold {
+    fn default() -> Self {
+        PeakHold::Decay { db_per_second: 20.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AveragingSettings {
+    pub mode: AveragingMode,
+    // Linear/RmsPower 使用的帧数，0 表示平均复位以来的全部帧
+    pub frames: usize,
+    // Exponential 使用的时间常数
+    pub time_constant_ms: f32,
+    pub peak_hold: PeakHold,
+}
+
+impl Default for AveragingSettings {
+    fn default() -> Self {
+        Self {
+            mode: AveragingMode::default(),
+            frames: 8,
+            time_constant_ms: 250.0,
+            peak_hold: PeakHold::default(),
+        }
+    }
+}
+
+// Linear/RmsPower 历史最多保存的值的个数（约 16 MB），帧数按频点数限制在这个范围内
+const MAX_HISTORY_VALUES: usize = 1 << 22;
+
+// num_bins 个频点时 Linear/RmsPower 最多平均的帧数
+pub fn max_average_frames(num_bins: usize) -> usize {
+    (MAX_HISTORY_VALUES / num_bins.max(1)).max(1)
+}
+
+pub struct SpectrumAverager {
+    settings: AveragingSettings,
+    // 最近N帧的历史（环形存放），线性或功率值
+    history: Vec<Vec<f32>>,
+    history_pos: usize,
+    history_len: usize,
+    // 历史的累加和，用f64避免长时间运行的误差积累
+    sum: Vec<f64>,
+    average: Vec<f32>,
+    max_hold: Vec<f32>,
+    min_hold: Vec<f32>,
+    frames_seen: u64,
+}
+
+impl SpectrumAverager {
+    // 帧数超过 max_average_frames 时按上限分配历史，平均全部帧时只保留累加和
+    pub fn new(num_bins: usize, settings: AveragingSettings) -> Self {
+        let frames = match settings.frames {
+            0 => 0,
+            frames => frames.min(max_average_frames(num_bins)),
+        };
+        Self {
+            settings,
+            history: vec![vec![0.0; num_bins]; frames],
+            history_pos: 0,
+            history_len: 0,
+            sum: vec![0.0; num_bins],
+            average: vec![0.0; num_bins],
+            max_hold: vec![0.0; num_bins],
+            min_hold: vec![f32::INFINITY; num_bins],
+            frames_seen: 0,
+        }
+    }
+
+    pub fn settings(&self) -> &AveragingSettings {
+        &self.settings
+    }
+
+    pub fn num_bins(&self) -> usize {
+        self.sum.len()
+    }
+
+    // 修改设置，影响平均结果的改动会清空历史
+    pub fn set_settings(&mut self, settings: AveragingSettings) {
+        if settings == self.settings {
+            return;
+        }
+        let restart = settings.mode != self.settings.mode || settings.frames != self.settings.frames;
+        self.settings = settings;
+        if restart {
+            *self = Self::new(self.num_bins(), settings);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.num_bins(), self.settings);
+    }
+
+    pub fn frames_seen(&self) -> u64 {
+        self.frames_seen
+    }
+
+    // 送入一帧FFT模值，frame_interval为两帧之间的时间（秒）
+    pub fn process(&mut self, magnitudes: &[f32], frame_interval: f32) {
+        debug_assert_eq!(magnitudes.len(), self.num_bins());
+        self.update_average(magnitudes, frame_interval);
+        self.update_holds(magnitudes, frame_interval);
+        self.frames_seen += 1;
+    }
+
+    fn update_average(&mut self, magnitudes: &[f32], frame_interval: f32) {
+        match self.settings.mode {
+            AveragingMode::Exponential => {
+                let tau = (self.settings.time_constant_ms / 1000.0).max(1e-6);
+                // 第一帧直接作为初值
+                let alpha = if self.frames_seen == 0 {
+                    1.0
+                } else {
+                    1.0 - (-frame_interval / tau).exp()
+                };
+                for (avg, &m) in self.average.iter_mut().zip(magnitudes) {
+                    let power = avg.powi(2) + alpha * (m * m - avg.powi(2));
+                    *avg = power.max(0.0).sqrt();
+                }
+            }
+            AveragingMode::Linear | AveragingMode::RmsPower => {
+                let power = self.settings.mode == AveragingMode::RmsPower;
+                let capacity = self.history.len();
+                if capacity == 0 {
+                    for (sum, &m) in self.sum.iter_mut().zip(magnitudes) {
+                        *sum += if power { m as f64 * m as f64 } else { m as f64 };
+                    }
+                    self.history_len += 1;
+                } else {
+                    let slot = &mut self.history[self.history_pos];
+                    for ((old, sum), &m) in slot.iter_mut().zip(self.sum.iter_mut()).zip(magnitudes) {
+                        let value = if power { m * m } else { m };
+                        if self.history_len == capacity {
+                            *sum -= *old as f64;
+                        }
+                        *sum += value as f64;
+                        *old = value;
+                    }
+                    self.history_pos = (self.history_pos + 1) % capacity;
+                    self.history_len = (self.history_len + 1).min(capacity);
+                }
+
+                let count = self.history_len as f64;
+                for (avg, &sum) in self.average.iter_mut().zip(&self.sum) {
+                    let mean = (sum / count).max(0.0) as f32;
+                    *avg = if power { mean.sqrt() } else { mean };
+                }
+            }
+        }
+    }
+
+    fn update_holds(&mut self, magnitudes: &[f32], frame_interval: f32) {
+        let decay = match self.settings.peak_hold {
+            PeakHold::Infinite => 1.0,
+            PeakHold::Decay { db_per_second } => 10f32.powf(-db_per_second * frame_interval / 20.0),
+        };
+        for ((max, min), &m) in self.max_hold.iter_mut().zip(self.min_hold.iter_mut()).zip(magnitudes) {
+            *max = m.max(*max * decay);
+            *min = m.min(*min);
+        }
+    }
+
+    pub fn average(&self) -> &[f32] {
+        &self.average
+    }
+
+    pub fn max_hold(&self) -> &[f32] {
+        &self.max_hold
+    }
+
+    pub fn min_hold(&self) -> &[f32] {
+        &self.min_hold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mode: AveragingMode) -> AveragingSettings {
+        AveragingSettings {
+            mode,
+            frames: 4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_linear_and_rms_moving_average() {
+        let mut linear = SpectrumAverager::new(1, settings(AveragingMode::Linear));
+        let mut rms = SpectrumAverager::new(1, settings(AveragingMode::RmsPower));
+        for value in [1.0, 3.0, 1.0, 3.0, 1.0, 3.0] {
+            linear.process(&[value], 0.01);
+            rms.process(&[value], 0.01);
+        }
+        // 只保留最近4帧
+        assert!((linear.average()[0] - 2.0).abs() < 1e-6);
+        assert!((rms.average()[0] - 5f32.sqrt()).abs() < 1e-6);
+
+        linear.process(&[3.0], 0.01);
+        linear.process(&[3.0], 0.01);
+        linear.process(&[3.0], 0.01);
+        linear.process(&[3.0], 0.01);
+        assert!((linear.average()[0] - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_average_all_frames() {
+        let mut linear = SpectrumAverager::new(1, AveragingSettings { frames: 0, ..Default::default() });
+        let mut rms = SpectrumAverager::new(1, AveragingSettings { mode: AveragingMode::RmsPower, frames: 0, ..Default::default() });
+        assert!(linear.history.is_empty());
+        for value in (0..100).map(|i| if i < 10 { 3.0 } else { 1.0 }) {
+            linear.process(&[value], 0.01);
+            rms.process(&[value], 0.01);
+        }
+        // 最早的帧也计入平均
+        assert!((linear.average()[0] - 1.2).abs() < 1e-6);
+        assert!((rms.average()[0] - 1.8f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_history_capped() {
+        let bins = 65536 / 2 + 1;
+        let mut averager = SpectrumAverager::new(bins, AveragingSettings { frames: 1024, ..Default::default() });
+        assert_eq!(averager.history.len(), max_average_frames(bins));
+        assert!(averager.history.len() * bins <= MAX_HISTORY_VALUES);
+        // 超出上限时按上限的帧数做滑动平均
+        let frames = averager.history.len();
+        let mut input = vec![1.0; bins];
+        for _ in 0..frames {
+            averager.process(&input, 0.01);
+        }
+        input.fill(3.0);
+        for _ in 0..frames {
+            averager.process(&input, 0.01);
+        }
+        assert!((averager.average()[0] - 3.0).abs() < 1e-6);
+        assert_eq!(max_average_frames(1), MAX_HISTORY_VALUES);
+    }
+
+    #[test]
+    fn test_exponential_time_constant() {
+        let mut averager = SpectrumAverager::new(1, AveragingSettings {
+            mode: AveragingMode::Exponential,
+            time_constant_ms: 100.0,
+            ..Default::default()
+        });
+        averager.process(&[0.0], 0.001);
+        // 功率阶跃输入，经过一个时间常数后达到终值的 1-1/e
+        for _ in 0..100 {
+            averager.process(&[1.0], 0.001);
+        }
+        let power = averager.average()[0].powi(2);
+        assert!((power - (1.0 - (-1f32).exp())).abs() < 1e-3, "{}", power);
+    }
+
+    #[test]
+    fn test_peak_and_min_hold() {
+        let mut averager = SpectrumAverager::new(2, AveragingSettings {
+            peak_hold: PeakHold::Infinite,
+            ..Default::default()
+        });
+        averager.process(&[1.0, 0.5], 0.1);
+        averager.process(&[0.2, 0.8], 0.1);
+        assert_eq!(averager.max_hold(), &[1.0, 0.8]);
+        assert_eq!(averager.min_hold(), &[0.2, 0.5]);
+
+        // 每秒回落20dB，0.5秒后回落10dB
+        let mut averager = SpectrumAverager::new(1, AveragingSettings {
+            peak_hold: PeakHold::Decay { db_per_second: 20.0 },
+            ..Default::default()
+        });
+        averager.process(&[1.0], 0.5);
+        averager.process(&[0.0], 0.5);
+        let db = 20.0 * averager.max_hold()[0].log10();
+        assert!((db + 10.0).abs() < 1e-3, "{}", db);
+
+        averager.reset();
+        assert_eq!(averager.max_hold(), &[0.0]);
+        assert_eq!(averager.frames_seen(), 0);
+    }
+}
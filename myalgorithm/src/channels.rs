@@ -0,0 +1,197 @@
+// 多声道输入的拆分与组合
+// 设备回调给出的是交错排列的样本 (L R L R ...)，这里按声道模式拆成若干路单声道信号
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelMode {
+    // 只分析指定的一个声道（从0开始）
+    Single(usize),
+    // 所有声道取平均
+    #[default]
+    MonoSum,
+    // 前两个声道的和/差信号 M=(L+R)/2, S=(L-R)/2
+    MidSide,
+    // 每个声道独立分析
+    PerChannel,
+}
+
+impl ChannelMode {
+    pub fn name(&self) -> String {
+        match self {
+            ChannelMode::Single(channel) => format!("CH{}", channel + 1),
+            ChannelMode::MonoSum => "Mono".to_string(),
+            ChannelMode::MidSide => "Mid/Side".to_string(),
+            ChannelMode::PerChannel => "Per channel".to_string(),
+        }
+    }
+
+    // 在给定声道数下可选的全部模式
+    pub fn available(channels: usize) -> Vec<ChannelMode> {
+        let mut modes = vec![ChannelMode::MonoSum];
+        modes.extend((0..channels).map(ChannelMode::Single));
+        if channels >= 2 {
+            modes.push(ChannelMode::MidSide);
+            modes.push(ChannelMode::PerChannel);
+        }
+        modes
+    }
+
+    // 声道数不满足要求时退回到单声道求和
+    pub fn resolve(self, channels: usize) -> ChannelMode {
+        match self {
+            ChannelMode::Single(channel) if channel >= channels => ChannelMode::MonoSum,
+            ChannelMode::MidSide if channels < 2 => ChannelMode::MonoSum,
+            mode => mode,
+        }
+    }
+
+    // 输出的信号路数
+    pub fn output_count(&self, channels: usize) -> usize {
+        match self.resolve(channels) {
+            ChannelMode::Single(_) | ChannelMode::MonoSum => 1,
+            ChannelMode::MidSide => 2,
+            ChannelMode::PerChannel => channels,
+        }
+    }
+
+    // 各路输出的名称
+    pub fn output_names(&self, channels: usize) -> Vec<String> {
+        match self.resolve(channels) {
+            ChannelMode::Single(channel) => vec![format!("CH{}", channel + 1)],
+            ChannelMode::MonoSum => vec!["Mono".to_string()],
+            ChannelMode::MidSide => vec!["Mid".to_string(), "Side".to_string()],
+            ChannelMode::PerChannel => (0..channels).map(|c| format!("CH{}", c + 1)).collect(),
+        }
+    }
+}
+
+// 把交错样本按声道模式拆分成若干路输出
+// 输入不必按帧对齐，不完整的一帧会留到下一次处理
+pub struct ChannelSplitter {
+    channels: usize,
+    mode: ChannelMode,
+    outputs: Vec<Vec<f32>>,
+    partial: Vec<f32>,
+}
+
+impl ChannelSplitter {
+    pub fn new(channels: usize, mode: ChannelMode) -> Self {
+        let channels = channels.max(1);
+        let mode = mode.resolve(channels);
+        Self {
+            channels,
+            mode,
+            outputs: vec![Vec::new(); mode.output_count(channels)],
+            partial: Vec::with_capacity(channels),
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn mode(&self) -> ChannelMode {
+        self.mode
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    // 拆分一段交错样本，返回各路输出（每次调用会覆盖上一次的结果）
+    pub fn process(&mut self, interleaved: &[f32]) -> &[Vec<f32>] {
+        for output in &mut self.outputs {
+            output.clear();
+        }
+
+        let mut input = interleaved;
+        // 先补齐上次剩下的半帧
+        if !self.partial.is_empty() {
+            let take = (self.channels - self.partial.len()).min(input.len());
+            self.partial.extend_from_slice(&input[..take]);
+            input = &input[take..];
+            if self.partial.len() == self.channels {
+                push_frame(self.mode, &mut self.outputs, &self.partial);
+                self.partial.clear();
+            }
+        }
+
+        let mut frames = input.chunks_exact(self.channels);
+        for frame in &mut frames {
+            push_frame(self.mode, &mut self.outputs, frame);
+        }
+        self.partial.extend_from_slice(frames.remainder());
+
+        &self.outputs
+    }
+}
+
+// 处理完整的一帧（每个声道一个样本）
+fn push_frame(mode: ChannelMode, outputs: &mut [Vec<f32>], frame: &[f32]) {
+    match mode {
+        ChannelMode::Single(channel) => outputs[0].push(frame[channel]),
+        ChannelMode::MonoSum => {
+            let sum: f32 = frame.iter().sum();
+            outputs[0].push(sum / frame.len() as f32);
+        }
+        ChannelMode::MidSide => {
+            let (left, right) = (frame[0], frame[1]);
+            outputs[0].push((left + right) * 0.5);
+            outputs[1].push((left - right) * 0.5);
+        }
+        ChannelMode::PerChannel => {
+            for (output, &sample) in outputs.iter_mut().zip(frame) {
+                output.push(sample);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEREO: [f32; 8] = [1.0, 0.0, 0.5, 0.5, -1.0, 1.0, 0.25, -0.25];
+
+    #[test]
+    fn test_modes() {
+        let mut single = ChannelSplitter::new(2, ChannelMode::Single(1));
+        assert_eq!(single.process(&STEREO), &[vec![0.0, 0.5, 1.0, -0.25]]);
+
+        let mut mono = ChannelSplitter::new(2, ChannelMode::MonoSum);
+        assert_eq!(mono.process(&STEREO), &[vec![0.5, 0.5, 0.0, 0.0]]);
+
+        let mut mid_side = ChannelSplitter::new(2, ChannelMode::MidSide);
+        assert_eq!(
+            mid_side.process(&STEREO),
+            &[vec![0.5, 0.5, 0.0, 0.0], vec![0.5, 0.0, -1.0, 0.25]]
+        );
+
+        let mut per_channel = ChannelSplitter::new(2, ChannelMode::PerChannel);
+        assert_eq!(
+            per_channel.process(&STEREO),
+            &[vec![1.0, 0.5, -1.0, 0.25], vec![0.0, 0.5, 1.0, -0.25]]
+        );
+    }
+
+    #[test]
+    fn test_unaligned_chunks_keep_channel_order() {
+        let mut splitter = ChannelSplitter::new(2, ChannelMode::PerChannel);
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        for chunk in STEREO.chunks(3) {
+            let outputs = splitter.process(chunk);
+            left.extend_from_slice(&outputs[0]);
+            right.extend_from_slice(&outputs[1]);
+        }
+        assert_eq!(left, vec![1.0, 0.5, -1.0, 0.25]);
+        assert_eq!(right, vec![0.0, 0.5, 1.0, -0.25]);
+    }
+
+    #[test]
+    fn test_resolve_falls_back_for_mono_input() {
+        assert_eq!(ChannelMode::MidSide.resolve(1), ChannelMode::MonoSum);
+        assert_eq!(ChannelMode::Single(3).resolve(2), ChannelMode::MonoSum);
+        assert_eq!(ChannelMode::PerChannel.output_count(4), 4);
+        assert_eq!(ChannelMode::available(1), vec![ChannelMode::MonoSum, ChannelMode::Single(0)]);
+    }
+}
@@ -0,0 +1,382 @@
+// 失真分析：在一帧FFT模值上找出基波，累加各次谐波和其余频点的功率，
+// 得到 THD、THD+N、SINAD、SNR、ENOB 以及各次谐波的电平
+use crate::peaks::interpolate;
+use crate::{FrequencyAxis, PeakInterpolation, Window};
+
+// 正弦的主瓣向每侧最多延伸的频点数，平顶窗约为 5
+const MAX_LOBE_BINS: usize = 8;
+// 谐波的实际位置与基波整数倍之间允许的偏差（频点）
+const HARMONIC_SEARCH_BINS: usize = 2;
+const MIN_POWER: f64 = 1e-30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistortionSettings {
+    // 计入的最高谐波次数（从 2 次起）
+    pub max_harmonic: usize,
+    // 基波两侧不计入噪声的总宽度（Hz），比主瓣窄时只排除主瓣
+    pub notch_width_hz: f32,
+    // 测量频段，直流的泄漏总是排除；上限为 None 时到奈奎斯特频率
+    pub min_freq_hz: f32,
+    pub max_freq_hz: Option<f32>,
+    // 在给定频率附近找基波，None 时取频段内最高的峰
+    pub fundamental_hz: Option<f32>,
+    // 高于奈奎斯特频率的谐波按折叠后的位置计入
+    pub include_aliased: bool,
+}
+
+impl Default for DistortionSettings {
+    fn default() -> Self {
+        Self {
+            max_harmonic: 10,
+            notch_width_hz: 0.0,
+            min_freq_hz: 0.0,
+            max_freq_hz: None,
+            fundamental_hz: None,
+            include_aliased: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HarmonicLevel {
+    // 谐波次数，基波为 1
+    pub order: usize,
+    // 插值后的实际频率，混叠的谐波为折叠后的位置
+    pub frequency: f32,
+    // 正弦峰值幅度（满幅为1）
+    pub amplitude: f32,
+    // 相对基波（dBc）
+    pub level_dbc: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistortionResult {
+    pub fundamental_hz: f32,
+    // 基波的正弦峰值幅度（满幅为1）
+    pub fundamental_amplitude: f32,
+    // 找到的各次谐波，按次数排列
+    pub harmonics: Vec<HarmonicLevel>,
+    // 谐波总和、谐波加噪声相对基波的幅度比
+    pub thd: f32,
+    pub thd_n: f32,
+    // 基波与噪声加失真、与噪声（不含所计入的谐波）的功率比（dB）
+    pub sinad_db: f32,
+    pub snr_db: f32,
+    // 按 SINAD 折算的有效位数
+    pub enob: f32,
+}
+
+impl DistortionResult {
+    pub fn thd_db(&self) -> f32 {
+        ratio_db(self.thd)
+    }
+
+    pub fn thd_n_db(&self) -> f32 {
+        ratio_db(self.thd_n)
+    }
+
+    pub fn fundamental_dbfs(&self) -> f32 {
+        ratio_db(self.fundamental_amplitude)
+    }
+
+    // 把基波换算到满幅后的有效位数，输入不满幅时用于比较 ADC
+    pub fn enob_full_scale(&self) -> f32 {
+        self.enob - self.fundamental_dbfs() / 6.02
+    }
+}
+
+fn ratio_db(ratio: f32) -> f32 {
+    20.0 * ratio.max(1e-12).log10()
+}
+
+// 由一帧加窗FFT的模值（直流到奈奎斯特频率）测量失真；找不到基波时返回 None
+pub fn analyze_distortion(
+    magnitudes: &[f32],
+    window: &Window,
+    axis: &FrequencyAxis,
+    settings: &DistortionSettings,
+) -> Option<DistortionResult> {
+    let num_bins = magnitudes.len().min(axis.num_bins());
+    if num_bins < 8 {
+        return None;
+    }
+    // 单边功率，奈奎斯特频点没有镜像
+    let mut power: Vec<f64> = magnitudes[..num_bins].iter().map(|&m| (m as f64) * (m as f64)).collect();
+    power[num_bins - 1] *= 0.5;
+    // 主瓣功率换算成正弦峰值幅度：sum|X|² = A²/4·N·sum(w²)
+    let n = window.len() as f64;
+    let amplitude_factor = 2.0 / (n * window.coherent_gain() as f64 * (window.enbw_bins() as f64).sqrt());
+    let amplitude = |power: f64| (amplitude_factor * power.sqrt()) as f32;
+
+    // 测量频段，排除直流的主瓣
+    let first = (lobe(&power, 0).1 + 1).max(axis.hz_to_bin_clamped(settings.min_freq_hz));
+    let last = settings
+        .max_freq_hz
+        .map_or(num_bins - 1, |hz| axis.hz_to_bin_clamped(hz).min(num_bins - 1));
+    if first + 2 > last {
+        return None;
+    }
+    let mut excluded = vec![false; num_bins];
+    excluded[..first].fill(true);
+    excluded[last + 1..].fill(true);
+
+    // 基波取给定频率附近或频段内最高的频点
+    let (start, end) = match settings.fundamental_hz {
+        Some(hz) => {
+            let center = axis.hz_to_fractional_bin(hz).round() as usize;
+            (center.saturating_sub(MAX_LOBE_BINS).max(first), (center + MAX_LOBE_BINS).min(last))
+        }
+        None => (first, last),
+    };
+    let peak = (start..=end).max_by(|&a, &b| power[a].total_cmp(&power[b]))?;
+    let fundamental_power = claim(&power, &mut excluded, lobe(&power, peak));
+    if fundamental_power < MIN_POWER {
+        return None;
+    }
+    let fundamental_hz = refine(magnitudes, axis, peak);
+    let half_notch = settings.notch_width_hz.max(0.0) / 2.0;
+    claim(
+        &power,
+        &mut excluded,
+        (
+            axis.hz_to_bin_clamped(fundamental_hz - half_notch),
+            axis.hz_to_bin_clamped(fundamental_hz + half_notch).min(num_bins - 1),
+        ),
+    );
+
+    let mut harmonics = Vec::new();
+    let mut distortion_power = 0.0;
+    for order in 2..=settings.max_harmonic {
+        let mut freq = fundamental_hz * order as f32;
+        if freq > axis.nyquist() {
+            if !settings.include_aliased {
+                continue;
+            }
+            freq = fold(freq, axis.sample_rate());
+        }
+        // 在整数倍位置附近找实际的峰，已计入基波或其他谐波的频点不再计入
+        let center = axis.hz_to_fractional_bin(freq).round() as usize;
+        if center < first || center > last {
+            continue;
+        }
+        let start = center.saturating_sub(HARMONIC_SEARCH_BINS).max(first);
+        let end = (center + HARMONIC_SEARCH_BINS).min(last);
+        let Some(bin) = (start..=end)
+            .filter(|&k| !excluded[k])
+            .max_by(|&a, &b| power[a].total_cmp(&power[b]))
+        else {
+            continue;
+        };
+        let harmonic_power = claim(&power, &mut excluded, lobe(&power, bin));
+        distortion_power += harmonic_power;
+        harmonics.push(HarmonicLevel {
+            order,
+            frequency: refine(magnitudes, axis, bin),
+            amplitude: amplitude(harmonic_power),
+            level_dbc: (10.0 * (harmonic_power.max(MIN_POWER) / fundamental_power).log10()) as f32,
+        });
+    }
+
+    // 频段内剩下的频点都算作噪声；陷波内的噪声不计入
+    let noise_power: f64 = (first..=last).filter(|&k| !excluded[k]).map(|k| power[k]).sum();
+    let residual = (noise_power + distortion_power).max(MIN_POWER);
+    let sinad_db = (10.0 * (fundamental_power / residual).log10()) as f32;
+
+    Some(DistortionResult {
+        fundamental_hz,
+        fundamental_amplitude: amplitude(fundamental_power),
+        harmonics,
+        thd: (distortion_power / fundamental_power).sqrt() as f32,
+        thd_n: (residual / fundamental_power).sqrt() as f32,
+        sinad_db,
+        snr_db: (10.0 * (fundamental_power / noise_power.max(MIN_POWER)).log10()) as f32,
+        enob: (sinad_db - 1.76) / 6.02,
+    })
+}
+
+// 从峰顶向两侧走到功率不再下降为止，即正弦的主瓣
+fn lobe(power: &[f64], peak: usize) -> (usize, usize) {
+    let mut start = peak;
+    while start > 0 && peak - start < MAX_LOBE_BINS && power[start - 1] < power[start] {
+        start -= 1;
+    }
+    let mut end = peak;
+    while end + 1 < power.len() && end - peak < MAX_LOBE_BINS && power[end + 1] < power[end] {
+        end += 1;
+    }
+    (start, end)
+}
+
+// 累加范围内还没有计入的频点，并把它们标记为已计入
+fn claim(power: &[f64], excluded: &mut [bool], (start, end): (usize, usize)) -> f64 {
+    let mut sum = 0.0;
+    for k in start..=end {
+        if !excluded[k] {
+            excluded[k] = true;
+            sum += power[k];
+        }
+    }
+    sum
+}
+
+// 峰顶频点插值后的频率
+fn refine(magnitudes: &[f32], axis: &FrequencyAxis, bin: usize) -> f32 {
+    if bin == 0 || bin + 1 >= magnitudes.len() {
+        return axis.bin_to_hz(bin);
+    }
+    let db = [bin - 1, bin, bin + 1].map(|k| 20.0 * magnitudes[k].max(1e-20).log10());
+    let (offset, _) = interpolate(&db, 1, PeakInterpolation::Gaussian);
+    axis.fractional_bin_to_hz(bin as f32 + offset)
+}
+
+// 采样后高于奈奎斯特频率的分量折叠到 0..fs/2
+fn fold(freq: f32, sample_rate: f32) -> f32 {
+    let freq = freq % sample_rate;
+    if freq > sample_rate / 2.0 {
+        sample_rate - freq
+    } else {
+        freq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SpectrumEngine, WindowFunction};
+    use std::f64::consts::TAU;
+
+    const FS: f32 = 48000.0;
+    const N: usize = 8192;
+
+    // 多个正弦之和经 shape 处理（加噪声、量化等）后做失真分析
+    fn analyze(tones: &[(f32, f64)], shape: impl Fn(usize, f64) -> f64, settings: &DistortionSettings) -> Option<DistortionResult> {
+        let samples: Vec<f32> = (0..N)
+            .map(|i| {
+                let x = tones
+                    .iter()
+                    .map(|&(freq, amplitude)| amplitude * (TAU * freq as f64 * i as f64 / FS as f64).sin())
+                    .sum();
+                shape(i, x) as f32
+            })
+            .collect();
+        let mut engine = SpectrumEngine::new(N, WindowFunction::BlackmanHarris);
+        let mut magnitudes = vec![0.0; engine.num_bins()];
+        engine.process(&samples, &mut magnitudes);
+        analyze_distortion(&magnitudes, engine.window(), &FrequencyAxis::new(FS, N), settings)
+    }
+
+    fn db(ratio: f64) -> f32 {
+        (20.0 * ratio.log10()) as f32
+    }
+
+    // 可重复的均匀分布噪声，范围 -1..1
+    fn noise(seed: usize) -> f64 {
+        let mut x = (seed as u64).wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        x ^= x >> 33;
+        x = x.wrapping_mul(0xff51afd7ed558ccd);
+        x ^= x >> 33;
+        (x >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+
+    #[test]
+    fn test_harmonic_levels_and_thd() {
+        let tones = [(997.0, 0.5), (1994.0, 0.005), (2991.0, 0.0005)];
+        let result = analyze(&tones, |_, x| x, &DistortionSettings::default()).unwrap();
+        assert!((result.fundamental_hz - 997.0).abs() < 0.1, "{}", result.fundamental_hz);
+        assert!((result.fundamental_amplitude - 0.5).abs() < 0.005, "{}", result.fundamental_amplitude);
+
+        assert_eq!(result.harmonics[0].order, 2);
+        assert!((result.harmonics[0].frequency - 1994.0).abs() < 0.2);
+        assert!((result.harmonics[0].level_dbc + 40.0).abs() < 0.1, "{}", result.harmonics[0].level_dbc);
+        assert!((result.harmonics[0].amplitude - 0.005).abs() < 5e-5);
+        assert!((result.harmonics[1].level_dbc + 60.0).abs() < 0.1, "{}", result.harmonics[1].level_dbc);
+        // 其余谐波只有计算噪声
+        assert!(result.harmonics[2..].iter().all(|h| h.level_dbc < -120.0));
+        assert_eq!(result.harmonics.last().unwrap().order, 10);
+
+        let thd = (0.01f32 * 0.01 + 0.001 * 0.001).sqrt();
+        assert!((result.thd / thd - 1.0).abs() < 0.01, "{}", result.thd);
+        assert!(result.thd_n >= result.thd && result.thd_n < thd * 1.01);
+        assert!((result.sinad_db + result.thd_n_db()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_snr_with_white_noise() {
+        // 均匀分布噪声的有效值为 a/√3
+        let a = 0.01;
+        let expected = 10.0 * ((0.5f64 * 0.5 / 2.0) / (a * a / 3.0)).log10();
+        let result = analyze(&[(1234.5, 0.5)], |i, x| x + a * noise(i), &DistortionSettings::default()).unwrap();
+        assert!((result.snr_db - expected as f32).abs() < 0.5, "{} vs {}", result.snr_db, expected);
+        // 没有谐波时 SINAD 与 SNR 只差谐波位置上的噪声
+        assert!(result.sinad_db <= result.snr_db && result.snr_db - result.sinad_db < 0.1);
+        assert!((result.enob - (result.sinad_db - 1.76) / 6.02).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_enob_of_quantized_sine() {
+        // 12 位量化的接近满幅正弦
+        let step = 1.0 / 2048.0;
+        let result = analyze(&[(1001.3, 0.999)], |_, x| (x / step).round() * step, &DistortionSettings::default()).unwrap();
+        assert!((result.enob - 12.0).abs() < 0.2, "{}", result.enob);
+        assert!((result.enob_full_scale() - result.enob).abs() < 0.01);
+
+        let half = analyze(&[(1001.3, 0.5)], |_, x| (x / step).round() * step, &DistortionSettings::default()).unwrap();
+        assert!((half.enob - 11.0).abs() < 0.25, "{}", half.enob);
+        assert!((half.enob_full_scale() - 12.0).abs() < 0.25, "{}", half.enob_full_scale());
+    }
+
+    #[test]
+    fn test_aliased_harmonics() {
+        // 10 kHz 的 3 次谐波 30 kHz 在 48 kHz 采样下折叠到 18 kHz
+        let tones = [(10000.0, 0.5), (20000.0, 0.0005), (18000.0, 0.0015)];
+        let result = analyze(&tones, |_, x| x, &DistortionSettings::default()).unwrap();
+        let third = result.harmonics.iter().find(|h| h.order == 3).unwrap();
+        assert!((third.frequency - 18000.0).abs() < 0.5, "{}", third.frequency);
+        assert!((third.level_dbc - db(0.003)).abs() < 0.1, "{}", third.level_dbc);
+        assert!(result.snr_db > 80.0, "{}", result.snr_db);
+
+        // 不计混叠时 18 kHz 算作噪声
+        let settings = DistortionSettings { include_aliased: false, ..Default::default() };
+        let result = analyze(&tones, |_, x| x, &settings).unwrap();
+        assert_eq!(result.harmonics.iter().map(|h| h.order).collect::<Vec<_>>(), vec![2]);
+        assert!((result.snr_db + db(0.003)).abs() < 0.1, "{}", result.snr_db);
+        assert!((result.thd_db() - db(0.001)).abs() < 0.1, "{}", result.thd_db());
+    }
+
+    #[test]
+    fn test_notch_dc_and_band() {
+        // 基波旁 50 Hz 的杂散在陷波外算作噪声，陷波足够宽时被排除
+        let tones = [(1000.0, 0.5), (1050.0, 0.005)];
+        let result = analyze(&tones, |_, x| x, &DistortionSettings::default()).unwrap();
+        assert!((result.thd_n_db() + 40.0).abs() < 0.1, "{}", result.thd_n_db());
+        let settings = DistortionSettings { notch_width_hz: 200.0, ..Default::default() };
+        let result = analyze(&tones, |_, x| x, &settings).unwrap();
+        assert!(result.thd_n_db() < -100.0, "{}", result.thd_n_db());
+
+        // 直流偏置不算噪声
+        let result = analyze(&[(1000.0, 0.5)], |_, x| x + 0.3, &DistortionSettings::default()).unwrap();
+        assert!((result.fundamental_hz - 1000.0).abs() < 0.1);
+        assert!(result.snr_db > 80.0, "{}", result.snr_db);
+
+        // 频段外的噪声不计入
+        let tones = [(1000.0, 0.5), (15000.0, 0.005)];
+        let settings = DistortionSettings { max_freq_hz: Some(10000.0), ..Default::default() };
+        assert!(analyze(&tones, |_, x| x, &settings).unwrap().snr_db > 80.0);
+        assert!(analyze(&tones, |_, x| x, &DistortionSettings::default()).unwrap().snr_db < 41.0);
+    }
+
+    #[test]
+    fn test_given_fundamental_and_silence() {
+        // 指定频率时不取最高的峰
+        let tones = [(1000.0, 0.5), (3100.0, 0.1)];
+        let settings = DistortionSettings { fundamental_hz: Some(3100.0), ..Default::default() };
+        let result = analyze(&tones, |_, x| x, &settings).unwrap();
+        assert!((result.fundamental_hz - 3100.0).abs() < 0.1);
+        assert!((result.fundamental_amplitude - 0.1).abs() < 0.001);
+
+        assert!(analyze(&[], |_, x| x, &DistortionSettings::default()).is_none());
+        let axis = FrequencyAxis::new(FS, 8);
+        let window = Window::new(WindowFunction::Hann, 8);
+        assert!(analyze_distortion(&[1.0; 5], &window, &axis, &DistortionSettings::default()).is_none());
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use crate::{Window, WindowFunction};
+
+// 加窗 + 实数FFT + 取模的单帧处理
+// FFT计划、窗系数表和所有中间缓冲都在构造时分配，处理一帧不再分配堆内存
+pub struct SpectrumEngine {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Window,
+    input: Vec<f32>,
+    output: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl SpectrumEngine {
+    pub fn new(fft_size: usize, window: WindowFunction) -> Self {
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(fft_size);
+        Self {
+            input: fft.make_input_vec(),
+            output: fft.make_output_vec(),
+            scratch: fft.make_scratch_vec(),
+            window: Window::new(window, fft_size),
+            fft,
+        }
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.fft.len()
+    }
+
+    // 直流到奈奎斯特（含）的频点数
+    pub fn num_bins(&self) -> usize {
+        self.output.len()
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    // 只在窗类型或参数变化时重新生成系数表
+    pub fn set_window(&mut self, function: WindowFunction) {
+        if self.window.function() != function {
+            self.window = Window::new(function, self.fft_size());
+        }
+    }
+
+    // 最近一帧的复数频谱
+    pub fn spectrum(&self) -> &[Complex<f32>] {
+        &self.output
+    }
+
+    // 对一帧样本加窗并做FFT，把各频点的模写入 magnitudes
+    // samples 长度必须等于 fft_size，magnitudes 长度必须等于 num_bins
+    pub fn process(&mut self, samples: &[f32], magnitudes: &mut [f32]) {
+        assert_eq!(samples.len(), self.fft_size(), "frame length must equal the FFT size");
+        assert_eq!(magnitudes.len(), self.num_bins(), "output length must equal the bin count");
+
+        for ((dst, &x), &w) in self.input.iter_mut().zip(samples).zip(self.window.coefficients()) {
+            *dst = x * w;
+        }
+        self.fft
+            .process_with_scratch(&mut self.input, &mut self.output, &mut self.scratch)
+            .expect("FFT buffers are sized by the planner");
+        for (m, c) in magnitudes.iter_mut().zip(&self.output) {
+            *m = c.norm();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustfft::FftPlanner;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_matches_complex_fft() {
+        let n = 1024;
+        let signal: Vec<f32> = (0..n)
+            .map(|i| (2.0 * PI * 37.3 * i as f32 / n as f32).sin() + 0.25 * (i as f32 * 0.01).cos())
+            .collect();
+
+        let mut engine = SpectrumEngine::new(n, WindowFunction::BlackmanHarris);
+        let mut magnitudes = vec![0.0; engine.num_bins()];
+        engine.process(&signal, &mut magnitudes);
+
+        let window = Window::new(WindowFunction::BlackmanHarris, n);
+        let mut reference: Vec<Complex<f32>> = signal
+            .iter()
+            .zip(window.coefficients())
+            .map(|(&x, &w)| Complex::new(x * w, 0.0))
+            .collect();
+        FftPlanner::new().plan_fft_forward(n).process(&mut reference);
+
+        assert_eq!(magnitudes.len(), n / 2 + 1);
+        for (k, (&m, c)) in magnitudes.iter().zip(&reference).enumerate() {
+            assert!((m - c.norm()).abs() < 1e-3, "bin {}: {} vs {}", k, m, c.norm());
+        }
+    }
+}
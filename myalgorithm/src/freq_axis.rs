@@ -0,0 +1,161 @@
+use crate::AnalysisConfig;
+
+// 频率轴：FFT频点与频率(Hz)之间的换算
+// 长度为N的实信号FFT有 N/2+1 个有效频点，第k个频点对应 k*fs/N Hz，
+// 最后一个频点正好落在奈奎斯特频率 fs/2 上
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyAxis {
+    sample_rate: f32,
+    fft_size: usize,
+}
+
+impl FrequencyAxis {
+    pub fn new(sample_rate: f32, fft_size: usize) -> Self {
+        Self { sample_rate, fft_size }
+    }
+
+    pub fn from_config(config: &AnalysisConfig) -> Self {
+        Self::new(config.sample_rate, config.fft_size)
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    // 相邻频点之间的间隔（频率分辨率）
+    pub fn bin_width(&self) -> f32 {
+        self.sample_rate / self.fft_size as f32
+    }
+
+    pub fn nyquist(&self) -> f32 {
+        self.sample_rate / 2.0
+    }
+
+    // 从直流到奈奎斯特（含）的频点数
+    pub fn num_bins(&self) -> usize {
+        self.fft_size / 2 + 1
+    }
+
+    pub fn bin_to_hz(&self, bin: usize) -> f32 {
+        bin as f32 * self.bin_width()
+    }
+
+    // 插值得到的小数频点位置换算成频率
+    pub fn fractional_bin_to_hz(&self, bin: f32) -> f32 {
+        bin * self.bin_width()
+    }
+
+    pub fn hz_to_fractional_bin(&self, hz: f32) -> f32 {
+        hz / self.bin_width()
+    }
+
+    // 最接近给定频率的频点，超出 0..=奈奎斯特 范围时返回 None
+    pub fn hz_to_bin(&self, hz: f32) -> Option<usize> {
+        if !(0.0..=self.nyquist()).contains(&hz) {
+            return None;
+        }
+        let bin = self.hz_to_fractional_bin(hz).round() as usize;
+        Some(bin.min(self.num_bins() - 1))
+    }
+
+    // 与 hz_to_bin 相同，但超出范围的频率被钳位到首尾频点
+    pub fn hz_to_bin_clamped(&self, hz: f32) -> usize {
+        let bin = self.hz_to_fractional_bin(hz.max(0.0)).round() as usize;
+        bin.min(self.num_bins() - 1)
+    }
+
+    // 依次给出每个频点的频率
+    pub fn frequencies(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.num_bins()).map(move |bin| self.bin_to_hz(bin))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FFT_SIZES, SUPPORTED_SAMPLE_RATES};
+    use rustfft::{num_complex::Complex, FftPlanner};
+    use std::f32::consts::PI;
+
+    fn sine_peak_bin(freq: f32, axis: &FrequencyAxis, planner: &mut FftPlanner<f32>) -> usize {
+        let n = axis.fft_size();
+        let fft = planner.plan_fft_forward(n);
+        let mut buffer: Vec<Complex<f32>> = (0..n)
+            .map(|i| {
+                let t = i as f32 / axis.sample_rate();
+                let window = 0.5 * (1.0 - (2.0 * PI * i as f32 / n as f32).cos());
+                Complex::new((2.0 * PI * freq * t).sin() * window, 0.0)
+            })
+            .collect();
+        fft.process(&mut buffer);
+
+        buffer
+            .iter()
+            .take(axis.num_bins())
+            .enumerate()
+            .max_by(|a, b| a.1.norm().total_cmp(&b.1.norm()))
+            .map(|(i, _)| i)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_1khz_sine_lands_on_1khz_bin() {
+        let mut planner = FftPlanner::new();
+        for &rate in &SUPPORTED_SAMPLE_RATES {
+            for &fft_size in &FFT_SIZES {
+                let axis = FrequencyAxis::new(rate as f32, fft_size);
+                let peak = sine_peak_bin(1000.0, &axis, &mut planner);
+
+                assert_eq!(
+                    Some(peak),
+                    axis.hz_to_bin(1000.0),
+                    "rate {} fft {}",
+                    rate,
+                    fft_size
+                );
+                assert!(
+                    (axis.bin_to_hz(peak) - 1000.0).abs() <= axis.bin_width() / 2.0,
+                    "rate {} fft {}: peak at {} Hz",
+                    rate,
+                    fft_size,
+                    axis.bin_to_hz(peak)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bin_mapping() {
+        let axis = FrequencyAxis::new(48000.0, 4096);
+        assert_eq!(axis.bin_width(), 48000.0 / 4096.0);
+        assert_eq!(axis.num_bins(), 2049);
+        assert_eq!(axis.bin_to_hz(0), 0.0);
+        assert_eq!(axis.bin_to_hz(2048), 24000.0);
+        assert_eq!(axis.hz_to_bin(0.0), Some(0));
+        assert_eq!(axis.hz_to_bin(24000.0), Some(2048));
+        assert_eq!(axis.hz_to_bin(axis.bin_to_hz(100)), Some(100));
+        assert_eq!(axis.frequencies().count(), axis.num_bins());
+    }
+
+    #[test]
+    fn test_nyquist_handling() {
+        let axis = FrequencyAxis::new(44100.0, 1024);
+        assert_eq!(axis.nyquist(), 22050.0);
+        assert_eq!(axis.hz_to_bin(22050.1), None);
+        assert_eq!(axis.hz_to_bin(-1.0), None);
+        assert_eq!(axis.hz_to_bin_clamped(30000.0), 512);
+        assert_eq!(axis.hz_to_bin_clamped(-5.0), 0);
+    }
+
+    #[test]
+    fn test_fractional_bins() {
+        let axis = FrequencyAxis::new(48000.0, 1024);
+        let hz = axis.fractional_bin_to_hz(10.25);
+        assert!((hz - 10.25 * 46.875).abs() < 1e-3);
+        assert!((axis.hz_to_fractional_bin(hz) - 10.25).abs() < 1e-5);
+    }
+}
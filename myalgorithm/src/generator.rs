@@ -0,0 +1,266 @@
+// 测试信号发生器：用于自检（直接作为分析输入）和通过被测设备回放激励信号
+use std::f64::consts::TAU;
+
+// Paul Kellett 粉红噪声滤波器对单位方差白噪声的增益，用于归一化到目标有效值
+const PINK_GAIN: f32 = 3.0525;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Waveform {
+    Sine { freq: f32 },
+    // 多个等幅正弦，总峰值不超过设定电平
+    MultiTone { freqs: Vec<f32> },
+    Square { freq: f32 },
+    Sawtooth { freq: f32 },
+    WhiteNoise,
+    PinkNoise,
+    // 对数（指数）扫频，到达终止频率后从头开始
+    LogSweep { start: f32, end: f32, duration: f32 },
+    // 单样本脉冲序列，rate 为每秒脉冲数
+    Impulse { rate: f32 },
+}
+
+impl Waveform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Waveform::Sine { .. } => "Sine",
+            Waveform::MultiTone { .. } => "Multi-tone",
+            Waveform::Square { .. } => "Square",
+            Waveform::Sawtooth { .. } => "Sawtooth",
+            Waveform::WhiteNoise => "White noise",
+            Waveform::PinkNoise => "Pink noise",
+            Waveform::LogSweep { .. } => "Log sweep",
+            Waveform::Impulse { .. } => "Impulse",
+        }
+    }
+}
+
+// 电平按 dBFS 设定：周期信号为峰值（满幅正弦为 0 dBFS），噪声为与同电平正弦相同的有效值
+pub struct SignalGenerator {
+    waveform: Waveform,
+    sample_rate: f32,
+    amplitude: f32,
+    // 以周期为单位的相位 0..1，多音时每个频率一个
+    phases: Vec<f64>,
+    // 已生成的样本数（扫频和脉冲使用）
+    position: u64,
+    rng: u64,
+    pink: [f32; 7],
+}
+
+impl SignalGenerator {
+    pub fn new(waveform: Waveform, level_dbfs: f32, sample_rate: f32) -> Self {
+        let tones = match &waveform {
+            Waveform::MultiTone { freqs } => freqs.len().max(1),
+            _ => 1,
+        };
+        Self {
+            waveform,
+            sample_rate,
+            amplitude: 10f32.powf(level_dbfs / 20.0),
+            phases: vec![0.0; tones],
+            position: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+            pink: [0.0; 7],
+        }
+    }
+
+    pub fn waveform(&self) -> &Waveform {
+        &self.waveform
+    }
+
+    pub fn level_dbfs(&self) -> f32 {
+        20.0 * self.amplitude.log10()
+    }
+
+    pub fn set_level_dbfs(&mut self, level_dbfs: f32) {
+        self.amplitude = 10f32.powf(level_dbfs / 20.0);
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    // 生成一段单声道样本
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = self.next_sample();
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        let amplitude = self.amplitude;
+        let value = match &self.waveform {
+            Waveform::Sine { freq } => {
+                let phase = advance(&mut self.phases[0], *freq, self.sample_rate);
+                amplitude * (phase * TAU).sin() as f32
+            }
+            Waveform::MultiTone { freqs } => {
+                let scale = amplitude / freqs.len().max(1) as f32;
+                let mut sum = 0.0;
+                for (phase, &freq) in self.phases.iter_mut().zip(freqs) {
+                    sum += (advance(phase, freq, self.sample_rate) * TAU).sin() as f32;
+                }
+                scale * sum
+            }
+            Waveform::Square { freq } => {
+                let phase = advance(&mut self.phases[0], *freq, self.sample_rate);
+                if phase < 0.5 { amplitude } else { -amplitude }
+            }
+            Waveform::Sawtooth { freq } => {
+                let phase = advance(&mut self.phases[0], *freq, self.sample_rate);
+                amplitude * (2.0 * phase as f32 - 1.0)
+            }
+            Waveform::WhiteNoise => amplitude * std::f32::consts::FRAC_1_SQRT_2 * self.gaussian(),
+            Waveform::PinkNoise => {
+                let white = self.gaussian();
+                amplitude * std::f32::consts::FRAC_1_SQRT_2 * self.pink_filter(white) / PINK_GAIN
+            }
+            Waveform::LogSweep { start, end, duration } => {
+                // 指数扫频 f(t) = f1·(f2/f1)^(t/T)，相位为其积分
+                let period = (*duration as f64 * self.sample_rate as f64).max(1.0) as u64;
+                let t = (self.position % period) as f64 / self.sample_rate as f64;
+                let (f1, f2, big_t) = (*start as f64, *end as f64, *duration as f64);
+                let rate = (f2 / f1).ln();
+                // 起止频率相同时退化为固定频率的正弦，避免 0/0
+                let phase = if rate.abs() < 1e-9 {
+                    f1 * t
+                } else {
+                    f1 * big_t / rate * ((t * rate / big_t).exp() - 1.0)
+                };
+                amplitude * (phase * TAU).sin() as f32
+            }
+            Waveform::Impulse { rate } => {
+                let period = (self.sample_rate / rate.max(1e-3)).round().max(1.0) as u64;
+                if self.position.is_multiple_of(period) { amplitude } else { 0.0 }
+            }
+        };
+        self.position += 1;
+        value
+    }
+
+    // xorshift64* 伪随机数，经 Box-Muller 变换得到标准正态分布
+    fn uniform(&mut self) -> f32 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40;
+        (bits as f32 + 0.5) / (1u64 << 24) as f32
+    }
+
+    fn gaussian(&mut self) -> f32 {
+        let (u1, u2) = (self.uniform(), self.uniform());
+        (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+    }
+
+    // Paul Kellett 的粉红噪声滤波器，-3 dB/倍频程
+    fn pink_filter(&mut self, white: f32) -> f32 {
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        pink
+    }
+}
+
+// 返回当前相位并前进一个样本
+fn advance(phase: &mut f64, freq: f32, sample_rate: f32) -> f64 {
+    let current = *phase;
+    *phase = (*phase + freq as f64 / sample_rate as f64).fract();
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SpectrumEngine, WindowFunction};
+
+    const FS: f32 = 48000.0;
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    fn generate(waveform: Waveform, level_dbfs: f32, len: usize) -> Vec<f32> {
+        let mut generator = SignalGenerator::new(waveform, level_dbfs, FS);
+        let mut out = vec![0.0; len];
+        generator.fill(&mut out);
+        out
+    }
+
+    #[test]
+    fn test_levels() {
+        let sine = generate(Waveform::Sine { freq: 1000.0 }, -20.0, 48000);
+        let peak = sine.iter().fold(0f32, |m, x| m.max(x.abs()));
+        assert!((peak - 0.1).abs() < 1e-3, "{}", peak);
+        assert!((rms(&sine) - 0.1 / 2f32.sqrt()).abs() < 1e-4);
+
+        // 噪声的有效值与同电平正弦相同
+        for waveform in [Waveform::WhiteNoise, Waveform::PinkNoise] {
+            let noise = generate(waveform.clone(), -6.0, 480000);
+            let expected = 10f32.powf(-6.0 / 20.0) / 2f32.sqrt();
+            let db = 20.0 * (rms(&noise) / expected).log10();
+            assert!(db.abs() < 0.3, "{}: {} dB", waveform.name(), db);
+        }
+
+        let square = generate(Waveform::Square { freq: 100.0 }, 0.0, 4800);
+        assert!((rms(&square) - 1.0).abs() < 1e-3);
+        let multi = generate(Waveform::MultiTone { freqs: vec![100.0, 1000.0, 5000.0] }, 0.0, 48000);
+        assert!(multi.iter().all(|x| x.abs() <= 1.0));
+    }
+
+    #[test]
+    fn test_pink_noise_has_equal_power_per_octave() {
+        let n = 16384;
+        let mut engine = SpectrumEngine::new(n, WindowFunction::Hann);
+        let mut generator = SignalGenerator::new(Waveform::PinkNoise, -10.0, FS);
+        let mut frame = vec![0.0; n];
+        let mut magnitudes = vec![0.0; engine.num_bins()];
+        let mut power = vec![0.0f64; engine.num_bins()];
+        for _ in 0..32 {
+            generator.fill(&mut frame);
+            engine.process(&frame, &mut magnitudes);
+            for (p, m) in power.iter_mut().zip(&magnitudes) {
+                *p += (*m as f64).powi(2);
+            }
+        }
+        let bin_width = FS / n as f32;
+        let band = |low: f32| -> f64 {
+            let (a, b) = ((low / bin_width) as usize, (2.0 * low / bin_width) as usize);
+            power[a..b].iter().sum()
+        };
+        let reference = band(100.0);
+        for low in [400.0, 1600.0, 6400.0] {
+            let db = 10.0 * (band(low) / reference).log10();
+            assert!(db.abs() < 1.0, "{} Hz octave: {} dB", low, db);
+        }
+    }
+
+    #[test]
+    fn test_sweep_and_impulse() {
+        // 扫频起始处的周期约为 1/f1
+        let sweep = generate(Waveform::LogSweep { start: 100.0, end: 10000.0, duration: 1.0 }, 0.0, 48000);
+        let crossings: Vec<usize> = (1..sweep.len())
+            .filter(|&i| sweep[i - 1] < 0.0 && sweep[i] >= 0.0)
+            .collect();
+        let first_period = (crossings[1] - crossings[0]) as f32 / FS;
+        assert!((first_period - 0.01).abs() < 0.001, "{}", first_period);
+        // 对数扫频中每个倍频程用时相同，前半程结束于几何中点 1 kHz
+        let half = &crossings.iter().filter(|&&i| i > 24000).take(2).collect::<Vec<_>>();
+        let mid_freq = FS / (half[1] - half[0]) as f32;
+        assert!((mid_freq - 1000.0).abs() < 50.0, "{}", mid_freq);
+
+        // 起止频率相同时是固定频率的正弦，不产生 NaN
+        let flat = generate(Waveform::LogSweep { start: 1000.0, end: 1000.0, duration: 1.0 }, 0.0, 48000);
+        let sine = generate(Waveform::Sine { freq: 1000.0 }, 0.0, 48000);
+        assert!(flat.iter().all(|x| x.is_finite()));
+        assert!(flat.iter().zip(&sine).all(|(a, b)| (a - b).abs() < 1e-3));
+
+        let impulses = generate(Waveform::Impulse { rate: 10.0 }, -6.0, 48000);
+        assert_eq!(impulses.iter().filter(|&&x| x != 0.0).count(), 10);
+    }
+}
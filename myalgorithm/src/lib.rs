@@ -1,17 +1,124 @@
 use num_traits::{NumCast};
 
+pub mod averaging;
+pub mod channels;
+pub mod distortion;
+pub mod fft;
+pub mod freq_axis;
+pub mod generator;
+pub mod octave;
+pub mod peaks;
+pub mod scaling;
+pub mod stft;
+#[cfg(test)]
+mod test_util;
+pub mod weighting;
+pub mod window;
+
+pub use averaging::{max_average_frames, AveragingMode, AveragingSettings, PeakHold, SpectrumAverager};
+pub use channels::{ChannelMode, ChannelSplitter};
+pub use distortion::{analyze_distortion, DistortionResult, DistortionSettings, HarmonicLevel};
+pub use fft::SpectrumEngine;
+pub use freq_axis::FrequencyAxis;
+pub use generator::{SignalGenerator, Waveform};
+pub use octave::{octave_bands, BandLevels, OctaveBand, OctaveFilterBank, OctaveFraction, OctaveSettings, TimeWeighting};
+pub use peaks::{find_peaks, Peak, PeakInterpolation, PeakSettings};
+pub use scaling::{AmplitudeScale, Calibration, SpectrumScaler};
+pub use stft::StftBuffer;
+pub use weighting::{FrequencyWeighting, LevelMeter, LevelReading, WeightingCurve, WeightingFilter};
+pub use window::{Window, WindowFunction};
+
 // pub fn add(a: i32, b: i32) -> i32 {
 //     a + b
 // }
 
 
-pub const SAMPLE_RATE: f32 = 44100.0; 
-pub const MAX_FREQ: f32 = SAMPLE_RATE / 2.0; 
-pub const BUFFER_SZ: usize = 4096;
-pub const BUFFER_SZ_HALF: usize = BUFFER_SZ / 2;
+pub const DEFAULT_SAMPLE_RATE: f32 = 44100.0;
+pub const DEFAULT_FFT_SIZE: usize = 4096;
+// 打开音频设备时优先尝试的采样率
+pub const SUPPORTED_SAMPLE_RATES: [u32; 4] = [44100, 48000, 96000, 192000];
+// 支持的FFT长度（2的幂）
+pub const FFT_SIZES: [usize; 8] = [512, 1024, 2048, 4096, 8192, 16384, 32768, 65536];
+// 相邻分析帧的最大重叠比例（步进不小于FFT长度的1/16）
+pub const MAX_OVERLAP: f32 = 0.9375;
 
-pub fn get_freq(idx: usize) -> f32{
-    idx as f32 * SAMPLE_RATE / ((BUFFER_SZ/2) as f32) + 1.0
+// 分析参数：采样率取自实际打开的音频流，FFT长度与步进由用户选择
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalysisConfig {
+    pub sample_rate: f32,
+    pub fft_size: usize,
+    pub hop_size: usize,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            fft_size: DEFAULT_FFT_SIZE,
+            hop_size: DEFAULT_FFT_SIZE,
+        }
+    }
+}
+
+impl AnalysisConfig {
+    pub fn new(sample_rate: f32, fft_size: usize, hop_size: usize) -> Result<Self, String> {
+        let config = Self { sample_rate, fft_size, hop_size };
+        config.validate()?;
+        Ok(config)
+    }
+
+    // 音频流协商出实际采样率后更新
+    pub fn with_sample_rate(self, sample_rate: f32) -> Self {
+        Self { sample_rate, ..self }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.sample_rate.is_finite() || self.sample_rate <= 0.0 {
+            return Err(format!("Invalid sample rate: {}", self.sample_rate));
+        }
+        if !FFT_SIZES.contains(&self.fft_size) {
+            return Err(format!("Unsupported FFT size: {} (expected one of {:?})", self.fft_size, FFT_SIZES));
+        }
+        let min_hop = self.min_hop_size();
+        if self.hop_size < min_hop || self.hop_size > self.fft_size {
+            return Err(format!("Hop size must be in {}..={}, got {}", min_hop, self.fft_size, self.hop_size));
+        }
+        Ok(())
+    }
+
+    fn min_hop_size(&self) -> usize {
+        ((self.fft_size as f32 * (1.0 - MAX_OVERLAP)).round() as usize).max(1)
+    }
+
+    // 相邻帧的重叠比例，0表示不重叠
+    pub fn overlap(&self) -> f32 {
+        1.0 - self.hop_size as f32 / self.fft_size as f32
+    }
+
+    // 按重叠比例(0..=93.75%)设置步进
+    pub fn with_overlap(self, overlap: f32) -> Self {
+        let overlap = overlap.clamp(0.0, MAX_OVERLAP);
+        let hop_size = ((self.fft_size as f32 * (1.0 - overlap)).round() as usize)
+            .clamp(self.min_hop_size(), self.fft_size);
+        Self { hop_size, ..self }
+    }
+
+    // 每秒产生的分析帧数
+    pub fn frames_per_second(&self) -> f32 {
+        self.sample_rate / self.hop_size as f32
+    }
+
+    pub fn max_freq(&self) -> f32 {
+        self.sample_rate / 2.0
+    }
+
+    pub fn fft_size_half(&self) -> usize {
+        self.fft_size / 2
+    }
+
+    pub fn freq_axis(&self) -> FrequencyAxis {
+        FrequencyAxis::from_config(self)
+    }
 }
 
 pub fn get_normalized_db<T>(db: T) -> f32
@@ -27,7 +134,32 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_add() {
-        assert_eq!(add(2, 2), 4);
+    fn test_config_validation() {
+        assert!(AnalysisConfig::default().validate().is_ok());
+        assert!(AnalysisConfig::new(48000.0, 8192, 2048).is_ok());
+        assert!(AnalysisConfig::new(48000.0, 1000, 500).is_err());
+        assert!(AnalysisConfig::new(48000.0, 4096, 0).is_err());
+        assert!(AnalysisConfig::new(48000.0, 4096, 256).is_ok());
+        assert!(AnalysisConfig::new(48000.0, 4096, 255).is_err());
+        assert!(AnalysisConfig::new(48000.0, 4096, 8192).is_err());
+        assert!(AnalysisConfig::new(0.0, 4096, 4096).is_err());
+    }
+
+    #[test]
+    fn test_with_sample_rate() {
+        let config = AnalysisConfig::default().with_sample_rate(96000.0);
+        assert_eq!(config.sample_rate, 96000.0);
+        assert_eq!(config.fft_size, DEFAULT_FFT_SIZE);
+        assert_eq!(config.max_freq(), 48000.0);
+    }
+
+    #[test]
+    fn test_overlap() {
+        let config = AnalysisConfig::new(48000.0, 4096, 4096).unwrap();
+        assert_eq!(config.overlap(), 0.0);
+        assert_eq!(config.with_overlap(0.5).hop_size, 2048);
+        assert_eq!(config.with_overlap(0.75).frames_per_second(), 48000.0 / 1024.0);
+        assert_eq!(config.with_overlap(0.99).hop_size, 256);
+        assert!(config.with_overlap(MAX_OVERLAP).validate().is_ok());
     }
 }
@@ -0,0 +1,413 @@
+// 分数倍频程分析：按 IEC 61260 的十进制倍频程划分频带，每个频带用三节二阶节级联成 6 阶巴特沃斯带通，
+// 在时域上计算时间计权的电平和从复位起的等效连续电平（Leq）
+use realfft::num_complex::Complex64;
+use std::f64::consts::PI;
+
+// 十进制倍频程比 10^(3/10)
+const OCTAVE_RATIO: f64 = 1.995_262_314_968_879_5;
+const REFERENCE_HZ: f64 = 1000.0;
+// 带通原型的阶数，每阶对应一节二阶节
+const FILTER_ORDER: usize = 3;
+// 1/1、1/3 倍频程的标称中心频率取 R10 优先数
+const NOMINAL_THIRDS: [f32; 10] = [1.0, 1.25, 1.6, 2.0, 2.5, 3.15, 4.0, 5.0, 6.3, 8.0];
+const MIN_LEVEL: f64 = 1e-20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OctaveFraction {
+    Octave,
+    #[default]
+    Third,
+    Sixth,
+    Twelfth,
+    TwentyFourth,
+}
+
+impl OctaveFraction {
+    pub const ALL: [OctaveFraction; 5] = [
+        OctaveFraction::Octave,
+        OctaveFraction::Third,
+        OctaveFraction::Sixth,
+        OctaveFraction::Twelfth,
+        OctaveFraction::TwentyFourth,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OctaveFraction::Octave => "1/1 oct",
+            OctaveFraction::Third => "1/3 oct",
+            OctaveFraction::Sixth => "1/6 oct",
+            OctaveFraction::Twelfth => "1/12 oct",
+            OctaveFraction::TwentyFourth => "1/24 oct",
+        }
+    }
+
+    pub fn bands_per_octave(&self) -> u32 {
+        match self {
+            OctaveFraction::Octave => 1,
+            OctaveFraction::Third => 3,
+            OctaveFraction::Sixth => 6,
+            OctaveFraction::Twelfth => 12,
+            OctaveFraction::TwentyFourth => 24,
+        }
+    }
+}
+
+// 电平的时间计权：均方值按时间常数做指数平均
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeWeighting {
+    #[default]
+    Fast,
+    Slow,
+}
+
+impl TimeWeighting {
+    pub const ALL: [TimeWeighting; 2] = [TimeWeighting::Fast, TimeWeighting::Slow];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeWeighting::Fast => "Fast",
+            TimeWeighting::Slow => "Slow",
+        }
+    }
+
+    pub fn time_constant_secs(&self) -> f32 {
+        match self {
+            TimeWeighting::Fast => 0.125,
+            TimeWeighting::Slow => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OctaveSettings {
+    pub fraction: OctaveFraction,
+    // 包含这两个频率的频带以及其间的频带，上边缘超过奈奎斯特频率的频带不分析
+    pub min_freq_hz: f32,
+    pub max_freq_hz: f32,
+    pub time_weighting: TimeWeighting,
+}
+
+impl Default for OctaveSettings {
+    fn default() -> Self {
+        Self {
+            fraction: OctaveFraction::default(),
+            min_freq_hz: 20.0,
+            max_freq_hz: 20000.0,
+            time_weighting: TimeWeighting::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OctaveBand {
+    // 准确的中心频率与显示用的标称频率（Hz）
+    pub center: f32,
+    pub nominal: f32,
+    // 频带边缘，相邻频带首尾相接
+    pub lower: f32,
+    pub upper: f32,
+}
+
+// 在 min_hz..=max_hz 范围内的频带，按频率从低到高
+pub fn octave_bands(fraction: OctaveFraction, min_hz: f32, max_hz: f32, sample_rate: f32) -> Vec<OctaveBand> {
+    let b = fraction.bands_per_octave() as f64;
+    // 每倍频程频带数为偶数时中心频率偏开半个频带，1 kHz 落在两个频带的边缘
+    let offset = if fraction.bands_per_octave().is_multiple_of(2) { 0.5 } else { 0.0 };
+    let index = |hz: f32| b * (hz.max(1e-3) as f64 / REFERENCE_HZ).log(OCTAVE_RATIO) - offset;
+    let first = (index(min_hz) - 0.5).ceil() as i32;
+    let last = (index(max_hz) + 0.5).floor() as i32;
+    let half_band = OCTAVE_RATIO.powf(0.5 / b);
+
+    (first..=last)
+        .map(|x| {
+            let center = REFERENCE_HZ * OCTAVE_RATIO.powf((x as f64 + offset) / b);
+            let nominal = match fraction {
+                OctaveFraction::Octave | OctaveFraction::Third => {
+                    let third = x * 3 / fraction.bands_per_octave() as i32;
+                    NOMINAL_THIRDS[third.rem_euclid(10) as usize] * 10f32.powi(3 + third.div_euclid(10))
+                }
+                _ => round_significant(center, 3),
+            };
+            OctaveBand {
+                center: center as f32,
+                nominal,
+                lower: (center / half_band) as f32,
+                upper: (center * half_band) as f32,
+            }
+        })
+        .filter(|band| band.upper < sample_rate / 2.0)
+        .collect()
+}
+
+fn round_significant(value: f64, digits: i32) -> f32 {
+    let scale = 10f64.powi(digits - 1 - value.log10().floor() as i32);
+    ((value * scale).round() / scale) as f32
+}
+
+// 分子为 g(1 - z⁻²) 的带通二阶节，转置直接 II 型，用 f64 保证低频窄带的极点精度
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    gain: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.gain * x + self.z1;
+        self.z1 = self.z2 - self.a1 * y;
+        self.z2 = -self.gain * x - self.a2 * y;
+        y
+    }
+}
+
+// 双线性变换设计的巴特沃斯带通，边缘频率预畸变，通带中心增益为 1
+fn design_bandpass(lower: f64, upper: f64, sample_rate: f64) -> [Biquad; FILTER_ORDER] {
+    let warp = |hz: f64| 2.0 * sample_rate * (PI * hz / sample_rate).tan();
+    let (w1, w2) = (warp(lower), warp(upper));
+    let w0 = (w1 * w2).sqrt();
+    let bandwidth = w2 - w1;
+    // 模拟中心频率对应的数字角频率
+    let center = 2.0 * (w0 / (2.0 * sample_rate)).atan();
+    let unit = Complex64::from_polar(1.0, -center);
+
+    // 低通原型的极点经低通到带通变换各得两个极点，取上半平面的一个与其共轭组成一节
+    let mut sections = [Biquad { gain: 1.0, a1: 0.0, a2: 0.0, z1: 0.0, z2: 0.0 }; FILTER_ORDER];
+    let mut count = 0;
+    for k in 0..FILTER_ORDER {
+        let angle = PI * (2 * k + FILTER_ORDER + 1) as f64 / (2 * FILTER_ORDER) as f64;
+        let half = Complex64::from_polar(1.0, angle) * (bandwidth / 2.0);
+        let root = (half * half - w0 * w0).sqrt();
+        for s in [half + root, half - root] {
+            let z = (2.0 * sample_rate + s) / (2.0 * sample_rate - s);
+            if z.im <= 0.0 || count == FILTER_ORDER {
+                continue;
+            }
+            let (a1, a2) = (-2.0 * z.re, z.norm_sqr());
+            let response = (1.0 - unit * unit) / (1.0 + a1 * unit + a2 * unit * unit);
+            sections[count] = Biquad { gain: 1.0 / response.norm(), a1, a2, z1: 0.0, z2: 0.0 };
+            count += 1;
+        }
+    }
+    sections
+}
+
+// 一帧读出的各频带电平（dBFS，满幅正弦为 0 dB）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BandLevels {
+    pub bands: Vec<OctaveBand>,
+    // 时间计权的电平
+    pub level: Vec<f32>,
+    // 从复位起的等效连续电平
+    pub leq: Vec<f32>,
+    // Leq 的积分时长（秒）
+    pub duration: f32,
+}
+
+pub struct OctaveFilterBank {
+    settings: OctaveSettings,
+    sample_rate: f32,
+    bands: Vec<OctaveBand>,
+    filters: Vec<[Biquad; FILTER_ORDER]>,
+    // 时间计权的均方值及其每个样本的衰减系数
+    mean_square: Vec<f64>,
+    decay: f64,
+    // 从复位起的平方和与样本数
+    energy: Vec<f64>,
+    samples: u64,
+}
+
+impl OctaveFilterBank {
+    pub fn new(settings: OctaveSettings, sample_rate: f32) -> Self {
+        let bands = octave_bands(settings.fraction, settings.min_freq_hz, settings.max_freq_hz, sample_rate);
+        let filters = bands
+            .iter()
+            .map(|band| design_bandpass(band.lower as f64, band.upper as f64, sample_rate as f64))
+            .collect();
+        let time_constant = settings.time_weighting.time_constant_secs() as f64;
+        Self {
+            settings,
+            sample_rate,
+            filters,
+            mean_square: vec![0.0; bands.len()],
+            decay: (-1.0 / (time_constant * sample_rate as f64)).exp(),
+            energy: vec![0.0; bands.len()],
+            samples: 0,
+            bands,
+        }
+    }
+
+    pub fn settings(&self) -> &OctaveSettings {
+        &self.settings
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn bands(&self) -> &[OctaveBand] {
+        &self.bands
+    }
+
+    // 逐个样本滤波并累加，不分配内存
+    pub fn process(&mut self, samples: &[f32]) {
+        let weight = 1.0 - self.decay;
+        for ((filters, mean_square), energy) in self.filters.iter_mut().zip(&mut self.mean_square).zip(&mut self.energy) {
+            for &x in samples {
+                let y = filters.iter_mut().fold(x as f64, |y, section| section.process(y));
+                let square = y * y;
+                *mean_square += weight * (square - *mean_square);
+                *energy += square;
+            }
+        }
+        self.samples += samples.len() as u64;
+    }
+
+    // 重新开始 Leq 的积分，时间计权的电平不受影响
+    pub fn reset_leq(&mut self) {
+        self.energy.fill(0.0);
+        self.samples = 0;
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.samples as f32 / self.sample_rate
+    }
+
+    // 写入调用方的缓冲，频带数不变时不分配内存
+    pub fn read_levels(&self, out: &mut BandLevels) {
+        let to_db = |mean_square: f64| (10.0 * (2.0 * mean_square).max(MIN_LEVEL).log10()) as f32;
+        out.bands.clone_from(&self.bands);
+        out.level.clear();
+        out.level.extend(self.mean_square.iter().map(|&ms| to_db(ms)));
+        let count = self.samples.max(1) as f64;
+        out.leq.clear();
+        out.leq.extend(self.energy.iter().map(|&energy| to_db(energy / count)));
+        out.duration = self.duration_secs();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sine, FS};
+
+    // 先让滤波器稳定，再测 Leq
+    fn band_leq(settings: OctaveSettings, signal: &[f32]) -> BandLevels {
+        let mut bank = OctaveFilterBank::new(settings, FS);
+        bank.process(&signal[..signal.len() / 4]);
+        bank.reset_leq();
+        bank.process(&signal[signal.len() / 4..]);
+        let mut levels = BandLevels::default();
+        bank.read_levels(&mut levels);
+        levels
+    }
+
+    fn level_at(levels: &BandLevels, nominal: f32) -> f32 {
+        let index = levels.bands.iter().position(|b| b.nominal == nominal).unwrap();
+        levels.leq[index]
+    }
+
+    #[test]
+    fn test_band_centres_and_edges() {
+        let thirds = octave_bands(OctaveFraction::Third, 20.0, 20000.0, FS);
+        assert_eq!(thirds.len(), 31);
+        let nominal: Vec<f32> = thirds.iter().map(|b| b.nominal).collect();
+        assert_eq!(&nominal[..6], &[20.0, 25.0, 31.5, 40.0, 50.0, 63.0]);
+        assert_eq!(nominal[17], 1000.0);
+        assert_eq!(thirds[17].center, 1000.0);
+        assert_eq!(*nominal.last().unwrap(), 20000.0);
+        assert!(thirds.windows(2).all(|w| (w[0].upper / w[1].lower - 1.0).abs() < 1e-5));
+        assert!((thirds[17].upper - 1122.0).abs() < 0.1 && (thirds[17].lower - 891.3).abs() < 0.1);
+
+        let octaves: Vec<f32> = octave_bands(OctaveFraction::Octave, 20.0, 20000.0, FS).iter().map(|b| b.nominal).collect();
+        assert_eq!(octaves, vec![16.0, 31.5, 63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0]);
+
+        // 每倍频程频带数为偶数时 1 kHz 在频带边缘
+        let sixths = octave_bands(OctaveFraction::Sixth, 500.0, 2000.0, FS);
+        assert!(sixths.iter().any(|b| (b.upper - 1000.0).abs() < 1e-2));
+        assert_eq!(octave_bands(OctaveFraction::TwentyFourth, 1010.0, 1990.0, FS).len(), 24);
+
+        // 上边缘超过奈奎斯特频率的频带不分析
+        let low_rate = octave_bands(OctaveFraction::Third, 20.0, 20000.0, 16000.0);
+        assert_eq!(low_rate.last().unwrap().nominal, 6300.0);
+    }
+
+    #[test]
+    fn test_filter_response() {
+        let settings = OctaveSettings { min_freq_hz: 500.0, max_freq_hz: 2000.0, ..Default::default() };
+        // 中心频率上增益为 1，满幅一半的正弦为 -6.02 dBFS
+        let levels = band_leq(settings, &sine(1000.0, 0.5, 2.0));
+        assert!((level_at(&levels, 1000.0) + 6.02).abs() < 0.05, "{}", level_at(&levels, 1000.0));
+        // 相邻频带至少衰减 15 dB，一个倍频程外至少 40 dB
+        assert!(level_at(&levels, 1250.0) < -6.02 - 15.0);
+        assert!(level_at(&levels, 800.0) < -6.02 - 15.0);
+        assert!(level_at(&levels, 2000.0) < -6.02 - 40.0, "{}", level_at(&levels, 2000.0));
+        assert!(level_at(&levels, 500.0) < -6.02 - 40.0);
+
+        // 频带边缘处两个频带各 -3 dB
+        let edge = levels.bands[levels.bands.iter().position(|b| b.nominal == 1000.0).unwrap()].upper;
+        let levels = band_leq(settings, &sine(edge as f64, 1.0, 2.0));
+        assert!((level_at(&levels, 1000.0) + 3.01).abs() < 0.1, "{}", level_at(&levels, 1000.0));
+        assert!((level_at(&levels, 1250.0) + 3.01).abs() < 0.1, "{}", level_at(&levels, 1250.0));
+
+        // 低频的 1/24 倍频程带宽不到 1 Hz，f64 的二阶节仍然准确，只是需要几秒才能稳定
+        let narrow = OctaveSettings { fraction: OctaveFraction::TwentyFourth, min_freq_hz: 20.0, max_freq_hz: 21.5, ..settings };
+        let bands = octave_bands(narrow.fraction, narrow.min_freq_hz, narrow.max_freq_hz, FS);
+        let levels = band_leq(narrow, &sine(bands[1].center as f64, 0.5, 16.0));
+        assert!((levels.leq[1] + 6.02).abs() < 0.05, "{}", levels.leq[1]);
+        assert!(levels.leq[0] < -6.02 - 15.0 && levels.leq[2] < -6.02 - 15.0);
+    }
+
+    #[test]
+    fn test_white_noise_sums_to_total_power() {
+        // 可重复的均匀分布噪声，方差 1/3
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let noise: Vec<f32> = (0..(2.0 * FS) as usize)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 11) as f32 / (1u64 << 53) as f32 * 2.0 - 1.0
+            })
+            .collect();
+        let levels = band_leq(OctaveSettings::default(), &noise);
+        let total: f64 = levels.leq.iter().map(|&db| 10f64.powf(db as f64 / 10.0) / 2.0).sum();
+        // 白噪声在频带覆盖范围内的功率
+        let (lower, upper) = (levels.bands[0].lower, levels.bands.last().unwrap().upper);
+        let expected = (upper - lower) as f64 / (FS as f64 / 2.0) / 3.0;
+        assert!((10.0 * (total / expected).log10()).abs() < 0.3, "{} vs {}", total, expected);
+    }
+
+    #[test]
+    fn test_time_weighting_and_leq() {
+        let settings = OctaveSettings { min_freq_hz: 1000.0, max_freq_hz: 1000.0, ..Default::default() };
+        let burst = sine(1000.0, 1.0, 0.125);
+        let mut levels = BandLevels::default();
+        // 打开 125 ms 后均方值为稳态的 1 - exp(-0.125/τ)
+        for time_weighting in TimeWeighting::ALL {
+            let expected = 10.0 * (1.0 - (-0.125 / time_weighting.time_constant_secs()).exp()).log10();
+            let mut bank = OctaveFilterBank::new(OctaveSettings { time_weighting, ..settings }, FS);
+            assert_eq!(bank.bands().len(), 1);
+            bank.process(&burst);
+            bank.read_levels(&mut levels);
+            assert!((levels.level[0] - expected).abs() < 0.3, "{}: {}", time_weighting.name(), levels.level[0]);
+        }
+
+        // 1 秒正弦加 1 秒静音的 Leq 比正弦低 3 dB
+        let mut bank = OctaveFilterBank::new(settings, FS);
+        bank.process(&sine(1000.0, 0.5, 1.0));
+        bank.process(&vec![0.0; FS as usize]);
+        bank.read_levels(&mut levels);
+        assert!((levels.duration - 2.0).abs() < 1e-6);
+        assert!((levels.leq[0] + 9.03).abs() < 0.1, "{}", levels.leq[0]);
+        // Fast 计权每秒回落约 35 dB
+        assert!((levels.level[0] + 6.02 + 34.7).abs() < 1.0, "{}", levels.level[0]);
+
+        bank.reset_leq();
+        bank.read_levels(&mut levels);
+        assert_eq!(levels.duration, 0.0);
+        assert!(levels.leq[0] < -150.0);
+    }
+}
@@ -0,0 +1,285 @@
+// 寻峰：在一帧频谱上找出最高的若干个峰，并用相邻频点插值得到频点之间的频率和幅度
+use crate::FrequencyAxis;
+
+// 线性单位换算成 dB 时的下限
+const MIN_AMPLITUDE: f32 = 1e-20;
+
+// 峰顶位置的插值方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PeakInterpolation {
+    // 不插值，取频点中心
+    None,
+    // 三个频点的线性幅度拟合抛物线
+    Parabolic,
+    // 三个频点的幅度拟合高斯曲线，即对数幅度（dB）上的抛物线（QIFFT），两者的结果完全相同，
+    // 对 Hann、Blackman-Harris 等窗的偏差比线性抛物线小得多
+    #[default]
+    Gaussian,
+}
+
+impl PeakInterpolation {
+    pub const ALL: [PeakInterpolation; 3] = [
+        PeakInterpolation::None,
+        PeakInterpolation::Parabolic,
+        PeakInterpolation::Gaussian,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PeakInterpolation::None => "None",
+            PeakInterpolation::Parabolic => "Parabolic",
+            PeakInterpolation::Gaussian => "Gaussian (log)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeakSettings {
+    // 最多返回的峰数
+    pub max_peaks: usize,
+    pub interpolation: PeakInterpolation,
+    // 两个峰之间的最小间隔，较低的峰被舍弃
+    pub min_separation_hz: f32,
+    // 峰顶高出两侧较高的谷底的最小值（dB）
+    pub min_prominence_db: f32,
+}
+
+impl Default for PeakSettings {
+    fn default() -> Self {
+        Self {
+            max_peaks: 5,
+            interpolation: PeakInterpolation::default(),
+            min_separation_hz: 20.0,
+            min_prominence_db: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Peak {
+    // 峰顶所在的频点
+    pub bin: usize,
+    // 插值后的频率（Hz）和读数，读数单位与输入相同
+    pub frequency: f32,
+    pub level: f32,
+    // 突出度（dB）
+    pub prominence: f32,
+}
+
+// 在一帧读数中寻峰，按读数从高到低返回；is_db 为 false 时输入为线性幅度
+pub fn find_peaks(values: &[f32], axis: &FrequencyAxis, is_db: bool, settings: &PeakSettings) -> Vec<Peak> {
+    if values.len() < 3 || settings.max_peaks == 0 {
+        return Vec::new();
+    }
+    // 突出度和插值都在 dB 上计算
+    let db: Vec<f32> = if is_db {
+        values.to_vec()
+    } else {
+        values.iter().map(|&v| 20.0 * v.max(MIN_AMPLITUDE).log10()).collect()
+    };
+
+    // 局部极大值；平台取最左边的频点
+    let mut candidates: Vec<(usize, f32)> = (1..db.len() - 1)
+        .filter(|&i| db[i] > db[i - 1] && db[i] >= db[i + 1])
+        .map(|i| (i, prominence(&db, i)))
+        .filter(|&(_, prominence)| prominence >= settings.min_prominence_db)
+        .collect();
+    candidates.sort_by(|a, b| db[b.0].total_cmp(&db[a.0]));
+
+    // 从最高的峰开始，离已选的峰太近的跳过
+    let mut selected: Vec<(usize, f32)> = Vec::with_capacity(settings.max_peaks);
+    for (bin, prominence) in candidates {
+        let freq = axis.bin_to_hz(bin);
+        if selected
+            .iter()
+            .all(|&(other, _)| (axis.bin_to_hz(other) - freq).abs() >= settings.min_separation_hz)
+        {
+            selected.push((bin, prominence));
+            if selected.len() == settings.max_peaks {
+                break;
+            }
+        }
+    }
+
+    selected
+        .into_iter()
+        .map(|(bin, prominence)| {
+            let (offset, level_db) = interpolate(&db, bin, settings.interpolation);
+            Peak {
+                bin,
+                frequency: axis.fractional_bin_to_hz(bin as f32 + offset),
+                level: if is_db { level_db } else { 10f32.powf(level_db / 20.0) },
+                prominence,
+            }
+        })
+        .collect()
+}
+
+// 峰顶减去两侧较高的谷底：向两边找到更高的点或边界为止，其间的最小值为该侧的谷底
+fn prominence(db: &[f32], peak: usize) -> f32 {
+    let height = db[peak];
+    let base = |range: &mut dyn Iterator<Item = usize>| {
+        let mut lowest = height;
+        for i in range {
+            if db[i] > height {
+                break;
+            }
+            lowest = lowest.min(db[i]);
+        }
+        lowest
+    };
+    let left = base(&mut (0..peak).rev());
+    let right = base(&mut (peak + 1..db.len()));
+    height - left.max(right)
+}
+
+// 返回相对峰顶频点的小数偏移（-0.5..0.5 频点附近）和插值后的 dB 值
+pub(crate) fn interpolate(db: &[f32], bin: usize, method: PeakInterpolation) -> (f32, f32) {
+    let (a, b, c) = (db[bin - 1], db[bin], db[bin + 1]);
+    match method {
+        PeakInterpolation::None => (0.0, b),
+        PeakInterpolation::Parabolic => {
+            let [a, b, c] = [a, b, c].map(|v| 10f32.powf(v / 20.0));
+            let (offset, peak) = vertex(a, b, c);
+            (offset, 20.0 * peak.max(MIN_AMPLITUDE).log10())
+        }
+        // dB 与对数幅度成正比，直接在 dB 上求抛物线顶点
+        PeakInterpolation::Gaussian => vertex(a, b, c),
+    }
+}
+
+// 过三个等间隔点的抛物线的顶点
+fn vertex(a: f32, b: f32, c: f32) -> (f32, f32) {
+    let denominator = a - 2.0 * b + c;
+    if denominator >= 0.0 {
+        return (0.0, b);
+    }
+    let offset = (0.5 * (a - c) / denominator).clamp(-0.5, 0.5);
+    (offset, b - 0.25 * (a - c) * offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AmplitudeScale, Calibration, SpectrumEngine, SpectrumScaler, WindowFunction};
+    use std::f64::consts::TAU;
+
+    const FS: f32 = 48000.0;
+    const N: usize = 4096;
+
+    // 多个正弦之和的 dBFS 频谱
+    fn spectrum(tones: &[(f32, f32)], window: WindowFunction) -> (Vec<f32>, FrequencyAxis) {
+        let samples: Vec<f32> = (0..N)
+            .map(|i| {
+                tones
+                    .iter()
+                    .map(|&(freq, amplitude)| amplitude * (TAU * freq as f64 * i as f64 / FS as f64).sin() as f32)
+                    .sum()
+            })
+            .collect();
+        let mut engine = SpectrumEngine::new(N, window);
+        let mut magnitudes = vec![0.0; engine.num_bins()];
+        engine.process(&samples, &mut magnitudes);
+        let scaler = SpectrumScaler::new(AmplitudeScale::Dbfs, Calibration::default(), engine.window(), FS);
+        let mut db = vec![0.0; magnitudes.len()];
+        scaler.scale_into(&magnitudes, &mut db);
+        (db, FrequencyAxis::new(FS, N))
+    }
+
+    #[test]
+    fn test_interpolated_frequency_and_level() {
+        // 落在两个频点之间的正弦，频点间隔约 11.7 Hz
+        let bin_width = FS / N as f32;
+        let freq = 85.4 * bin_width;
+        let (db, axis) = spectrum(&[(freq, 0.5)], WindowFunction::Hann);
+        let expected_db = 20.0 * 0.5f32.log10();
+
+        let mut errors = Vec::new();
+        for interpolation in PeakInterpolation::ALL {
+            let settings = PeakSettings { max_peaks: 1, interpolation, ..Default::default() };
+            let peaks = find_peaks(&db, &axis, true, &settings);
+            assert_eq!(peaks.len(), 1);
+            let error = (peaks[0].frequency - freq).abs();
+            errors.push(error);
+            if interpolation != PeakInterpolation::None {
+                assert!(error < 0.1 * bin_width, "{}: {} Hz", interpolation.name(), error);
+            }
+        }
+        assert!(errors[0] > 0.3 * bin_width);
+
+        // 高斯插值对 Hann 窗几乎没有频率偏差，扇贝损失也大部分被补偿
+        let settings = PeakSettings { max_peaks: 1, ..Default::default() };
+        let peak = find_peaks(&db, &axis, true, &settings)[0];
+        assert!((peak.frequency - freq).abs() < 0.02 * bin_width, "{}", peak.frequency);
+        assert!((peak.level - expected_db).abs() < 0.3, "{}", peak.level);
+        let none = PeakSettings { interpolation: PeakInterpolation::None, ..settings };
+        assert!(find_peaks(&db, &axis, true, &none)[0].level < expected_db - 0.5);
+    }
+
+    #[test]
+    fn test_gaussian_beats_parabolic_for_wide_windows() {
+        let bin_width = FS / N as f32;
+        for offset in [0.1, 0.25, 0.4] {
+            let freq = (256.0 + offset) * bin_width;
+            let (db, axis) = spectrum(&[(freq, 0.25)], WindowFunction::BlackmanHarris);
+            let peak = |interpolation| {
+                let settings = PeakSettings { max_peaks: 1, interpolation, ..Default::default() };
+                find_peaks(&db, &axis, true, &settings)[0]
+            };
+            let gaussian = peak(PeakInterpolation::Gaussian);
+            let parabolic = peak(PeakInterpolation::Parabolic);
+            assert!((gaussian.frequency - freq).abs() < 0.01 * bin_width, "{}", gaussian.frequency);
+            assert!((gaussian.level - 20.0 * 0.25f32.log10()).abs() < 0.05, "{}", gaussian.level);
+            assert!((parabolic.frequency - freq).abs() > (gaussian.frequency - freq).abs());
+        }
+    }
+
+    #[test]
+    fn test_top_n_separation_and_prominence() {
+        let tones = [(440.0, 0.5), (1000.0, 0.1), (1100.0, 0.05), (5000.0, 0.01)];
+        let (db, axis) = spectrum(&tones, WindowFunction::BlackmanHarris);
+
+        // 按读数从高到低
+        let settings = PeakSettings { max_peaks: 4, min_separation_hz: 10.0, ..Default::default() };
+        let peaks = find_peaks(&db, &axis, true, &settings);
+        let freqs: Vec<f32> = peaks.iter().map(|p| p.frequency.round()).collect();
+        assert_eq!(freqs, vec![440.0, 1000.0, 1100.0, 5000.0]);
+        assert!(peaks.windows(2).all(|w| w[0].level >= w[1].level));
+
+        // 间隔 150 Hz 时 1100 Hz 被较高的 1000 Hz 挡掉
+        let wide = PeakSettings { max_peaks: 3, min_separation_hz: 150.0, ..settings };
+        let freqs: Vec<f32> = find_peaks(&db, &axis, true, &wide).iter().map(|p| p.frequency.round()).collect();
+        assert_eq!(freqs, vec![440.0, 1000.0, 5000.0]);
+
+        // 只取前两个
+        let top = PeakSettings { max_peaks: 2, ..settings };
+        assert_eq!(find_peaks(&db, &axis, true, &top).len(), 2);
+
+        // 1100 Hz 与 1000 Hz 之间的谷底较浅，突出度低于单独的 5000 Hz
+        let by_freq = |f: f32| peaks.iter().find(|p| p.frequency.round() == f).unwrap().prominence;
+        assert!(by_freq(1100.0) < by_freq(5000.0));
+        let strict = PeakSettings { max_peaks: 3, min_prominence_db: by_freq(1100.0) + 1.0, ..settings };
+        let freqs: Vec<f32> = find_peaks(&db, &axis, true, &strict).iter().map(|p| p.frequency.round()).collect();
+        assert_eq!(freqs, vec![440.0, 1000.0, 5000.0]);
+    }
+
+    #[test]
+    fn test_linear_input() {
+        let freq = 170.4 * FS / N as f32;
+        let (db, axis) = spectrum(&[(freq, 0.2)], WindowFunction::Hann);
+        let linear: Vec<f32> = db.iter().map(|v| 10f32.powf(v / 20.0)).collect();
+        let settings = PeakSettings { max_peaks: 1, ..Default::default() };
+        let from_db = find_peaks(&db, &axis, true, &settings)[0];
+        let from_linear = find_peaks(&linear, &axis, false, &settings)[0];
+        assert!((from_db.frequency - from_linear.frequency).abs() < 1e-3);
+        assert!((from_linear.level - 0.2).abs() < 0.008, "{}", from_linear.level);
+    }
+
+    #[test]
+    fn test_flat_and_short_input() {
+        let axis = FrequencyAxis::new(FS, N);
+        let settings = PeakSettings::default();
+        assert!(find_peaks(&[0.0; 2049], &axis, true, &settings).is_empty());
+        assert!(find_peaks(&[0.0, 1.0], &axis, true, &settings).is_empty());
+    }
+}
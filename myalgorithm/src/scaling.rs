@@ -0,0 +1,279 @@
+use crate::Window;
+
+// 0 dBu 对应的有效值电压
+pub const DBU_REFERENCE_VOLTS: f32 = 0.774_596_7;
+// 0 dB SPL 对应的声压（Pa）
+pub const SPL_REFERENCE_PASCALS: f32 = 20e-6;
+// 取对数前的下限，避免 log10(0)
+const MIN_LEVEL: f32 = 1e-20;
+
+// 幅度显示单位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AmplitudeScale {
+    // 以满幅正弦为 0 dB
+    #[default]
+    Dbfs,
+    // 以 1 Vrms 为 0 dB
+    Dbv,
+    // 以 0.7746 Vrms 为 0 dB
+    Dbu,
+    // 以 20 µPa 为 0 dB，需要传声器灵敏度
+    DbSpl,
+    // 正弦峰值幅度，满幅为 1.0
+    Linear,
+    // 功率谱密度，相对满幅正弦功率，单位 dB/Hz
+    Psd,
+}
+
+impl AmplitudeScale {
+    pub const ALL: [AmplitudeScale; 6] = [
+        AmplitudeScale::Dbfs,
+        AmplitudeScale::Dbv,
+        AmplitudeScale::Dbu,
+        AmplitudeScale::DbSpl,
+        AmplitudeScale::Linear,
+        AmplitudeScale::Psd,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AmplitudeScale::Dbfs => "dBFS",
+            AmplitudeScale::Dbv => "dBV",
+            AmplitudeScale::Dbu => "dBu",
+            AmplitudeScale::DbSpl => "dB SPL",
+            AmplitudeScale::Linear => "Linear",
+            AmplitudeScale::Psd => "PSD",
+        }
+    }
+
+    // 坐标轴上显示的单位
+    pub fn unit(&self) -> &'static str {
+        match self {
+            AmplitudeScale::Dbfs => "dBFS",
+            AmplitudeScale::Dbv => "dBV",
+            AmplitudeScale::Dbu => "dBu",
+            AmplitudeScale::DbSpl => "dB",
+            AmplitudeScale::Linear => "FS",
+            AmplitudeScale::Psd => "dBFS/Hz",
+        }
+    }
+
+    pub fn is_db(&self) -> bool {
+        !matches!(self, AmplitudeScale::Linear)
+    }
+}
+
+// 输入通道的校准参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    // 数字满幅(1.0)对应的峰值电压
+    pub volts_per_fs: f32,
+    // 传声器灵敏度 mV/Pa
+    pub mic_sensitivity_mv_per_pa: f32,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            volts_per_fs: 1.0,
+            mic_sensitivity_mv_per_pa: 10.0,
+        }
+    }
+}
+
+impl Calibration {
+    // 满幅正弦的有效值电压
+    pub fn full_scale_vrms(&self) -> f32 {
+        self.volts_per_fs / std::f32::consts::SQRT_2
+    }
+}
+
+// 把FFT频点的模换算成所选单位，换算系数在构造时一次算好
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrumScaler {
+    scale: AmplitudeScale,
+    calibration: Calibration,
+    // |X| -> 正弦峰值幅度（满幅为1）
+    amplitude_factor: f32,
+    // |X|² -> 单边功率谱密度（满幅正弦功率为1，单位1/Hz）
+    psd_factor: f32,
+    num_bins: usize,
+}
+
+impl SpectrumScaler {
+    pub fn new(scale: AmplitudeScale, calibration: Calibration, window: &Window, sample_rate: f32) -> Self {
+        let n = window.len() as f32;
+        let coherent_gain = window.coherent_gain();
+        // sum(w²) = N·CG²·ENBW
+        let sum_sq = n * coherent_gain * coherent_gain * window.enbw_bins();
+
+        Self {
+            scale,
+            calibration,
+            amplitude_factor: 2.0 / (n * coherent_gain),
+            // 单边PSD为 2|X|²/(fs·sum(w²))，再除以满幅正弦功率 0.5
+            psd_factor: 4.0 / (sample_rate * sum_sq),
+            num_bins: window.len() / 2 + 1,
+        }
+    }
+
+    pub fn scale(&self) -> AmplitudeScale {
+        self.scale
+    }
+
+    pub fn calibration(&self) -> &Calibration {
+        &self.calibration
+    }
+
+    // 直流和奈奎斯特频点在单边谱中没有镜像，不需要乘2
+    fn edge_factor(&self, bin: usize) -> f32 {
+        if bin == 0 || bin + 1 == self.num_bins {
+            0.5
+        } else {
+            1.0
+        }
+    }
+
+    // 频点对应的正弦峰值幅度（满幅为1）
+    pub fn peak_amplitude(&self, bin: usize, magnitude: f32) -> f32 {
+        magnitude * self.amplitude_factor * self.edge_factor(bin)
+    }
+
+    // 满幅正弦在当前单位下的读数
+    pub fn full_scale_level(&self) -> f32 {
+        self.amplitude_to_unit(1.0)
+    }
+
+    fn amplitude_to_unit(&self, amplitude: f32) -> f32 {
+        let vrms = amplitude * self.calibration.full_scale_vrms();
+        match self.scale {
+            AmplitudeScale::Dbfs => 20.0 * amplitude.max(MIN_LEVEL).log10(),
+            AmplitudeScale::Dbv => 20.0 * vrms.max(MIN_LEVEL).log10(),
+            AmplitudeScale::Dbu => 20.0 * (vrms / DBU_REFERENCE_VOLTS).max(MIN_LEVEL).log10(),
+            AmplitudeScale::DbSpl => {
+                let pascals = vrms / (self.calibration.mic_sensitivity_mv_per_pa * 1e-3);
+                20.0 * (pascals / SPL_REFERENCE_PASCALS).max(MIN_LEVEL).log10()
+            }
+            AmplitudeScale::Linear => amplitude,
+            AmplitudeScale::Psd => 10.0 * (amplitude * amplitude).max(MIN_LEVEL).log10(),
+        }
+    }
+
+    // 把第bin个频点的FFT模值换算成所选单位
+    pub fn scale_bin(&self, bin: usize, magnitude: f32) -> f32 {
+        match self.scale {
+            AmplitudeScale::Psd => {
+                let edge = self.edge_factor(bin);
+                let density = magnitude * magnitude * self.psd_factor * edge;
+                10.0 * density.max(MIN_LEVEL).log10()
+            }
+            _ => self.amplitude_to_unit(self.peak_amplitude(bin, magnitude)),
+        }
+    }
+
+    // 把一整帧FFT模值换算到调用方提供的缓冲，不分配内存
+    pub fn scale_into(&self, magnitudes: &[f32], out: &mut [f32]) {
+        for (bin, (dst, &magnitude)) in out.iter_mut().zip(magnitudes).enumerate() {
+            *dst = self.scale_bin(bin, magnitude);
+        }
+    }
+
+    // 界面默认的纵轴范围
+    pub fn display_range(&self) -> (f32, f32) {
+        match self.scale {
+            AmplitudeScale::Linear => (0.0, 1.1),
+            AmplitudeScale::Psd => (-200.0, -20.0),
+            _ => {
+                let top = self.full_scale_level();
+                (top - 140.0, top + 10.0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WindowFunction;
+    use rustfft::{num_complex::Complex, FftPlanner};
+    use std::f32::consts::PI;
+
+    const N: usize = 4096;
+    const FS: f32 = 48000.0;
+
+    fn magnitudes(signal: &[f32], window: &Window) -> Vec<f32> {
+        let fft = FftPlanner::new().plan_fft_forward(signal.len());
+        let mut buffer: Vec<Complex<f32>> = signal
+            .iter()
+            .zip(window.coefficients())
+            .map(|(&x, &w)| Complex::new(x * w, 0.0))
+            .collect();
+        fft.process(&mut buffer);
+        buffer.iter().take(N / 2 + 1).map(|c| c.norm()).collect()
+    }
+
+    fn sine(amplitude: f32, bin: usize) -> Vec<f32> {
+        (0..N)
+            .map(|i| amplitude * (2.0 * PI * bin as f32 * i as f32 / N as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_full_scale_sine_is_0_dbfs_for_every_window() {
+        let signal = sine(1.0, 100);
+        for function in WindowFunction::ALL {
+            let window = Window::new(function, N);
+            let scaler = SpectrumScaler::new(AmplitudeScale::Dbfs, Calibration::default(), &window, FS);
+            let mags = magnitudes(&signal, &window);
+            let level = scaler.scale_bin(100, mags[100]);
+            assert!(level.abs() < 0.01, "{}: {} dBFS", function.name(), level);
+        }
+    }
+
+    #[test]
+    fn test_calibrated_units() {
+        let window = Window::new(WindowFunction::Hann, N);
+        let mags = magnitudes(&sine(0.5, 200), &window);
+        let calibration = Calibration {
+            volts_per_fs: 2.0,
+            mic_sensitivity_mv_per_pa: 50.0,
+        };
+        let level = |scale| SpectrumScaler::new(scale, calibration, &window, FS).scale_bin(200, mags[200]);
+
+        // 0.5 FS × 2 V = 1 V峰值 = 0.7071 Vrms
+        let vrms: f32 = 1.0 / std::f32::consts::SQRT_2;
+        assert!((level(AmplitudeScale::Dbfs) - -6.0206).abs() < 0.01);
+        assert!((level(AmplitudeScale::Dbv) - 20.0 * vrms.log10()).abs() < 0.01);
+        assert!((level(AmplitudeScale::Dbu) - 20.0 * (vrms / DBU_REFERENCE_VOLTS).log10()).abs() < 0.01);
+        let spl = 20.0 * (vrms / 0.05 / SPL_REFERENCE_PASCALS).log10();
+        assert!((level(AmplitudeScale::DbSpl) - spl).abs() < 0.01);
+        assert!((level(AmplitudeScale::Linear) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_psd_of_white_noise_independent_of_window() {
+        // 确定性的均匀分布伪随机噪声，方差 1/3
+        let mut state: u32 = 12345;
+        let noise: Vec<f32> = (0..N)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state as f32 / u32::MAX as f32) * 2.0 - 1.0
+            })
+            .collect();
+        let variance: f32 = 1.0 / 3.0;
+        let expected = 10.0 * (variance / (FS / 2.0) / 0.5).log10();
+
+        for function in [WindowFunction::Rectangular, WindowFunction::Hann, WindowFunction::FlatTop] {
+            let window = Window::new(function, N);
+            let scaler = SpectrumScaler::new(AmplitudeScale::Psd, Calibration::default(), &window, FS);
+            let mags = magnitudes(&noise, &window);
+            // 在线性功率域上平均，再换回dB
+            let mean_power: f32 = (1..N / 2)
+                .map(|k| 10f32.powf(scaler.scale_bin(k, mags[k]) / 10.0))
+                .sum::<f32>()
+                / (N / 2 - 1) as f32;
+            let measured = 10.0 * mean_power.log10();
+            assert!((measured - expected).abs() < 0.5, "{}: {} vs {}", function.name(), measured, expected);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+// 短时傅里叶变换的滑动分析缓冲
+// 不论每次送入多少样本，只要累计满一个步进(hop)就产生一帧长度为fft_size的分析帧，
+// 相邻帧之间重叠 fft_size - hop 个样本
+#[derive(Debug, Clone)]
+pub struct StftBuffer {
+    fft_size: usize,
+    hop_size: usize,
+    pending: Vec<f32>,
+}
+
+impl StftBuffer {
+    pub fn new(fft_size: usize, hop_size: usize) -> Self {
+        assert!(hop_size > 0 && hop_size <= fft_size, "hop size must be in 1..=fft_size");
+        Self {
+            fft_size,
+            hop_size,
+            pending: Vec::with_capacity(fft_size * 2),
+        }
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    // 修改步进，已缓存的样本保留
+    pub fn set_hop_size(&mut self, hop_size: usize) {
+        assert!(hop_size > 0 && hop_size <= self.fft_size, "hop size must be in 1..=fft_size");
+        self.hop_size = hop_size;
+    }
+
+    pub fn overlap(&self) -> f32 {
+        1.0 - self.hop_size as f32 / self.fft_size as f32
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    // 送入新样本，每凑满一帧调用一次 on_frame，返回产生的帧数
+    pub fn push(&mut self, samples: &[f32], mut on_frame: impl FnMut(&[f32])) -> usize {
+        let mut frames = 0;
+        let mut input = samples;
+
+        while !input.is_empty() {
+            let needed = self.fft_size - self.pending.len();
+            let take = needed.min(input.len());
+            self.pending.extend_from_slice(&input[..take]);
+            input = &input[take..];
+
+            if self.pending.len() == self.fft_size {
+                on_frame(&self.pending);
+                frames += 1;
+                // 丢弃最旧的hop个样本，其余留给下一帧
+                self.pending.drain(..self.hop_size);
+            }
+        }
+
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_every_hop_regardless_of_chunk_size() {
+        let signal: Vec<f32> = (0..10_000).map(|i| i as f32).collect();
+
+        for chunk in [1, 7, 64, 441, 4096, 10_000] {
+            let mut stft = StftBuffer::new(1024, 256);
+            let mut starts = Vec::new();
+            for block in signal.chunks(chunk) {
+                stft.push(block, |frame| {
+                    assert_eq!(frame.len(), 1024);
+                    // 帧内样本连续
+                    assert_eq!(frame[1023] - frame[0], 1023.0);
+                    starts.push(frame[0] as usize);
+                });
+            }
+            // 第一帧需要完整的fft_size个样本，之后每hop个样本一帧
+            let expected: Vec<usize> = (0..).map(|k| k * 256).take_while(|s| s + 1024 <= 10_000).collect();
+            assert_eq!(starts, expected, "chunk {}", chunk);
+        }
+    }
+
+    #[test]
+    fn test_no_overlap_and_hop_change() {
+        let mut stft = StftBuffer::new(512, 512);
+        assert_eq!(stft.overlap(), 0.0);
+        assert_eq!(stft.push(&vec![0.0; 2048], |_| {}), 4);
+
+        stft.set_hop_size(32);
+        assert_eq!(stft.overlap(), 0.9375);
+        assert_eq!(stft.push(&vec![0.0; 512 + 15 * 32], |_| {}), 16);
+    }
+}
@@ -0,0 +1,11 @@
+// 单元测试共用的信号
+use std::f64::consts::TAU;
+
+pub(crate) const FS: f32 = 48000.0;
+
+// 采样率 FS 下的正弦
+pub(crate) fn sine(freq: f64, amplitude: f64, seconds: f64) -> Vec<f32> {
+    (0..(seconds * FS as f64) as usize)
+        .map(|i| (amplitude * (TAU * freq * i as f64 / FS as f64).sin()) as f32)
+        .collect()
+}
@@ -0,0 +1,562 @@
+// 频率计权：IEC 61672-1 的 A、C、Z，IEC 60651 的 B，ITU-R BS.468-4 噪声计权，以及从文件读入的自定义曲线；
+// 频谱显示时按频点叠加增益，时域电平测量用按同一条曲线以频率采样法设计的线性相位 FIR，经重叠保留法做快速卷积
+use std::sync::Arc;
+
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+
+use crate::TimeWeighting;
+
+// IEC 61672-1 解析式中的极点频率（Hz）
+const POLE_LOW: f64 = 20.598_997;
+const POLE_A2: f64 = 107.652_65;
+const POLE_A3: f64 = 737.862_23;
+const POLE_HIGH: f64 = 12_194.217;
+// IEC 60651 B 计权的中频极点
+const POLE_B: f64 = 158.489_32;
+// 各计权在 1 kHz 处归一化为 0 dB
+const REFERENCE_HZ: f64 = 1000.0;
+const MIN_GAIN: f64 = 1e-10;
+const MIN_LEVEL: f64 = 1e-20;
+// FIR 的长度约为 1/8 秒，不短于 1024 点
+const MIN_KERNEL_LEN: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FrequencyWeighting {
+    // 平直，不计权
+    #[default]
+    Z,
+    A,
+    B,
+    C,
+    Itu468,
+    Custom(WeightingCurve),
+}
+
+impl FrequencyWeighting {
+    pub const STANDARD: [FrequencyWeighting; 5] = [
+        FrequencyWeighting::Z,
+        FrequencyWeighting::A,
+        FrequencyWeighting::B,
+        FrequencyWeighting::C,
+        FrequencyWeighting::Itu468,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            FrequencyWeighting::Z => "Z",
+            FrequencyWeighting::A => "A",
+            FrequencyWeighting::B => "B",
+            FrequencyWeighting::C => "C",
+            FrequencyWeighting::Itu468 => "ITU-R 468",
+            FrequencyWeighting::Custom(curve) => curve.name(),
+        }
+    }
+
+    // 按名称（不区分大小写）取标准计权，如 "A"、"468"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_uppercase().as_str() {
+            "Z" => Some(FrequencyWeighting::Z),
+            "A" => Some(FrequencyWeighting::A),
+            "B" => Some(FrequencyWeighting::B),
+            "C" => Some(FrequencyWeighting::C),
+            "468" | "ITU-R 468" | "ITU468" => Some(FrequencyWeighting::Itu468),
+            _ => None,
+        }
+    }
+
+    pub fn is_flat(&self) -> bool {
+        matches!(self, FrequencyWeighting::Z)
+    }
+
+    // 幅度增益（线性），直流处 A、B、C、468 为 0
+    pub fn gain(&self, freq: f32) -> f32 {
+        match self {
+            FrequencyWeighting::Custom(curve) => 10f32.powf(curve.gain_db(freq) / 20.0),
+            _ => self.standard_gain(freq.max(0.0) as f64) as f32,
+        }
+    }
+
+    // 增益（dB），零点处限制在 -200 dB
+    pub fn gain_db(&self, freq: f32) -> f32 {
+        match self {
+            FrequencyWeighting::Custom(curve) => curve.gain_db(freq),
+            _ => (20.0 * self.standard_gain(freq.max(0.0) as f64).max(MIN_GAIN).log10()) as f32,
+        }
+    }
+
+    fn standard_gain(&self, freq: f64) -> f64 {
+        let response = match self {
+            FrequencyWeighting::A => a_response,
+            FrequencyWeighting::B => b_response,
+            FrequencyWeighting::C => c_response,
+            FrequencyWeighting::Itu468 => itu468_response,
+            FrequencyWeighting::Z | FrequencyWeighting::Custom(_) => return 1.0,
+        };
+        response(freq) / response(REFERENCE_HZ)
+    }
+}
+
+// 以下为未归一化的幅度响应，f 为 Hz
+fn c_response(f: f64) -> f64 {
+    let f2 = f * f;
+    POLE_HIGH * POLE_HIGH * f2 / ((f2 + POLE_LOW * POLE_LOW) * (f2 + POLE_HIGH * POLE_HIGH))
+}
+
+fn a_response(f: f64) -> f64 {
+    let f2 = f * f;
+    c_response(f) * f2 / ((f2 + POLE_A2 * POLE_A2) * (f2 + POLE_A3 * POLE_A3)).sqrt()
+}
+
+fn b_response(f: f64) -> f64 {
+    c_response(f) * f / (f * f + POLE_B * POLE_B).sqrt()
+}
+
+// ITU-R BS.468-4 附录给出的有理函数
+fn itu468_response(f: f64) -> f64 {
+    let h1 = -4.737_338_981_378_384e-24 * f.powi(6) + 2.043_828_333_606_125e-15 * f.powi(4)
+        - 1.363_894_795_463_638e-7 * f * f
+        + 1.0;
+    let h2 = 1.306_612_257_412_824e-19 * f.powi(5) - 2.118_150_887_518_656e-11 * f.powi(3)
+        + 5.559_488_023_498_642e-4 * f;
+    1.246_332_637_532_143e-4 * f / h1.hypot(h2)
+}
+
+// 用户曲线：若干 (频率 Hz, 增益 dB) 点，在对数频率上线性插值，两端之外保持端点的值
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightingCurve {
+    name: String,
+    points: Vec<(f32, f32)>,
+}
+
+impl WeightingCurve {
+    pub fn new(name: &str, mut points: Vec<(f32, f32)>) -> Result<Self, String> {
+        if points.is_empty() {
+            return Err("Weighting curve has no points".to_string());
+        }
+        if let Some(&(freq, gain)) = points.iter().find(|&&(f, g)| !(f.is_finite() && f > 0.0 && g.is_finite())) {
+            return Err(format!("Invalid weighting point: {} Hz, {} dB", freq, gain));
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some(pair) = points.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(format!("Duplicate frequency in weighting curve: {} Hz", pair[0].0));
+        }
+        Ok(Self { name: name.to_string(), points })
+    }
+
+    // 文本格式：每行 "频率 增益"，用空白、逗号或分号分隔，多出的列（如相位）忽略；
+    // 不以数字开头的行（表头、# 注释）跳过
+    pub fn parse(name: &str, text: &str) -> Result<Self, String> {
+        let mut points = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let mut fields = line
+                .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+                .filter(|field| !field.is_empty());
+            let Some(Ok(freq)) = fields.next().map(str::parse::<f32>) else {
+                continue;
+            };
+            let gain = fields
+                .next()
+                .and_then(|field| field.parse::<f32>().ok())
+                .ok_or_else(|| format!("Line {}: expected \"<frequency> <gain dB>\"", index + 1))?;
+            points.push((freq, gain));
+        }
+        Self::new(name, points)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    pub fn gain_db(&self, freq: f32) -> f32 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        if freq <= first.0 {
+            return first.1;
+        }
+        if freq >= last.0 {
+            return last.1;
+        }
+        let upper = self.points.partition_point(|&(f, _)| f <= freq);
+        let ((f0, g0), (f1, g1)) = (self.points[upper - 1], self.points[upper]);
+        let t = (freq / f0).ln() / (f1 / f0).ln();
+        g0 + (g1 - g0) * t
+    }
+}
+
+// 计权滤波器：长度为 L 的线性相位 FIR，每收满 L 个样本做一次 2L 点的重叠保留卷积；
+// FIR 本身的 L/2 点延迟在开头丢掉，输出与输入对齐但要等收满一块才交出；构造后处理不再分配内存
+pub struct WeightingFilter {
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    // 补零后的冲激响应的频谱，已含逆变换的 1/2L
+    response: Vec<Complex<f32>>,
+    // 前半为上一块输入，后半收集新样本
+    input: Vec<f32>,
+    filled: usize,
+    // 还要丢掉的开头的输出点数
+    skip: usize,
+    time: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    output: Vec<f32>,
+    forward_scratch: Vec<Complex<f32>>,
+    inverse_scratch: Vec<Complex<f32>>,
+}
+
+impl WeightingFilter {
+    pub fn new(weighting: &FrequencyWeighting, sample_rate: f32) -> Self {
+        let length = ((sample_rate / 8.0) as usize).next_power_of_two().max(MIN_KERNEL_LEN);
+        let mut planner = RealFftPlanner::<f32>::new();
+        let kernel = design_kernel(weighting, sample_rate, length, &mut planner);
+
+        let forward = planner.plan_fft_forward(2 * length);
+        let inverse = planner.plan_fft_inverse(2 * length);
+        let mut time = forward.make_input_vec();
+        time[..length].copy_from_slice(&kernel);
+        let mut response = forward.make_output_vec();
+        let mut forward_scratch = forward.make_scratch_vec();
+        forward
+            .process_with_scratch(&mut time, &mut response, &mut forward_scratch)
+            .expect("FFT buffers are sized by the planner");
+        let scale = 1.0 / (2 * length) as f32;
+        for value in response.iter_mut() {
+            *value *= scale;
+        }
+
+        Self {
+            response,
+            input: vec![0.0; 2 * length],
+            filled: 0,
+            skip: length / 2,
+            time,
+            spectrum: forward.make_output_vec(),
+            output: inverse.make_output_vec(),
+            forward_scratch,
+            inverse_scratch: inverse.make_scratch_vec(),
+            forward,
+            inverse,
+        }
+    }
+
+    // FIR 的长度，也是每次输出的块长
+    pub fn block_len(&self) -> usize {
+        self.input.len() / 2
+    }
+
+    // 送入任意长度的样本，每凑满一块就把滤波后的一块交给 sink
+    pub fn process(&mut self, samples: &[f32], mut sink: impl FnMut(&[f32])) {
+        let block = self.block_len();
+        let mut rest = samples;
+        while !rest.is_empty() {
+            let take = (block - self.filled).min(rest.len());
+            let start = block + self.filled;
+            self.input[start..start + take].copy_from_slice(&rest[..take]);
+            self.filled += take;
+            rest = &rest[take..];
+            if self.filled < block {
+                break;
+            }
+
+            self.time.copy_from_slice(&self.input);
+            self.forward
+                .process_with_scratch(&mut self.time, &mut self.spectrum, &mut self.forward_scratch)
+                .expect("FFT buffers are sized by the planner");
+            for (value, &response) in self.spectrum.iter_mut().zip(&self.response) {
+                *value *= response;
+            }
+            // 实信号的直流和奈奎斯特频点虚部为零，去掉舍入误差
+            let last = self.spectrum.len() - 1;
+            self.spectrum[0].im = 0.0;
+            self.spectrum[last].im = 0.0;
+            self.inverse
+                .process_with_scratch(&mut self.spectrum, &mut self.output, &mut self.inverse_scratch)
+                .expect("FFT buffers are sized by the planner");
+            // 前半受循环卷积的影响，只有后半是有效的线性卷积
+            sink(&self.output[block + self.skip..]);
+            self.skip = 0;
+            self.input.copy_within(block.., 0);
+            self.filled = 0;
+        }
+    }
+}
+
+// 频率采样法：在 L 个等间隔频点上取计权的幅度，逆变换得到零相位的冲激响应，移到中间成为线性相位
+fn design_kernel(weighting: &FrequencyWeighting, sample_rate: f32, length: usize, planner: &mut RealFftPlanner<f32>) -> Vec<f32> {
+    let inverse = planner.plan_fft_inverse(length);
+    let mut response = inverse.make_input_vec();
+    let bin_hz = sample_rate / length as f32;
+    for (k, value) in response.iter_mut().enumerate() {
+        *value = Complex::new(weighting.gain(k as f32 * bin_hz), 0.0);
+    }
+    let mut impulse = inverse.make_output_vec();
+    inverse.process(&mut response, &mut impulse).expect("FFT buffers are sized by the planner");
+    let scale = 1.0 / length as f32;
+    (0..length).map(|n| impulse[(n + length / 2) % length] * scale).collect()
+}
+
+// 一次读出的宽带电平（dBFS，满幅正弦为 0 dB）
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LevelReading {
+    // 时间计权的电平
+    pub level: f32,
+    // 从复位起的等效连续电平
+    pub leq: f32,
+    // Leq 的积分时长（秒）
+    pub duration: f32,
+}
+
+// 宽带电平表：频率计权后按时间计权求均方，并累计从复位起的 Leq；Z 计权时不经过滤波器
+pub struct LevelMeter {
+    weighting: FrequencyWeighting,
+    time_weighting: TimeWeighting,
+    sample_rate: f32,
+    filter: Option<WeightingFilter>,
+    integrator: Integrator,
+}
+
+// 时间计权的均方值，以及从复位起的平方和与样本数
+struct Integrator {
+    mean_square: f64,
+    decay: f64,
+    energy: f64,
+    samples: u64,
+}
+
+impl Integrator {
+    fn push(&mut self, block: &[f32]) {
+        let weight = 1.0 - self.decay;
+        for &x in block {
+            let square = x as f64 * x as f64;
+            self.mean_square += weight * (square - self.mean_square);
+            self.energy += square;
+        }
+        self.samples += block.len() as u64;
+    }
+}
+
+impl LevelMeter {
+    pub fn new(weighting: FrequencyWeighting, time_weighting: TimeWeighting, sample_rate: f32) -> Self {
+        let filter = (!weighting.is_flat()).then(|| WeightingFilter::new(&weighting, sample_rate));
+        let time_constant = time_weighting.time_constant_secs() as f64;
+        Self {
+            weighting,
+            time_weighting,
+            sample_rate,
+            filter,
+            integrator: Integrator {
+                mean_square: 0.0,
+                decay: (-1.0 / (time_constant * sample_rate as f64)).exp(),
+                energy: 0.0,
+                samples: 0,
+            },
+        }
+    }
+
+    pub fn weighting(&self) -> &FrequencyWeighting {
+        &self.weighting
+    }
+
+    pub fn time_weighting(&self) -> TimeWeighting {
+        self.time_weighting
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    // 不分配内存
+    pub fn process(&mut self, samples: &[f32]) {
+        let integrator = &mut self.integrator;
+        match &mut self.filter {
+            Some(filter) => filter.process(samples, |block| integrator.push(block)),
+            None => integrator.push(samples),
+        }
+    }
+
+    // 重新开始 Leq 的积分，时间计权的电平不受影响
+    pub fn reset_leq(&mut self) {
+        self.integrator.energy = 0.0;
+        self.integrator.samples = 0;
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.integrator.samples as f32 / self.sample_rate
+    }
+
+    pub fn reading(&self) -> LevelReading {
+        let to_db = |mean_square: f64| (10.0 * (2.0 * mean_square).max(MIN_LEVEL).log10()) as f32;
+        let integrator = &self.integrator;
+        LevelReading {
+            level: to_db(integrator.mean_square),
+            leq: to_db(integrator.energy / integrator.samples.max(1) as f64),
+            duration: self.duration_secs(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sine, FS};
+
+    // IEC 61672-1 表 3 的 1/3 倍频程（10 Hz 到 20 kHz），标称频率对应的准确频率为 1000·10^(n/10)
+    const A_TABLE: [f32; 34] = [
+        -70.4, -63.4, -56.7, -50.5, -44.7, -39.4, -34.6, -30.2, -26.2, -22.5, -19.1, -16.1, -13.4, -10.9, -8.6, -6.6,
+        -4.8, -3.2, -1.9, -0.8, 0.0, 0.6, 1.0, 1.2, 1.3, 1.2, 1.0, 0.5, -0.1, -1.1, -2.5, -4.3, -6.6, -9.3,
+    ];
+    const C_TABLE: [f32; 34] = [
+        -14.3, -11.2, -8.5, -6.2, -4.4, -3.0, -2.0, -1.3, -0.8, -0.5, -0.3, -0.2, -0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+        0.0, 0.0, 0.0, -0.1, -0.2, -0.3, -0.5, -0.8, -1.3, -2.0, -3.0, -4.4, -6.2, -8.5, -11.2,
+    ];
+    // IEC 60651 表 IV
+    const B_TABLE: [f32; 34] = [
+        -38.2, -33.2, -28.5, -24.2, -20.4, -17.1, -14.2, -11.6, -9.3, -7.4, -5.6, -4.2, -3.0, -2.0, -1.3, -0.8, -0.5,
+        -0.3, -0.1, 0.0, 0.0, 0.0, 0.0, -0.1, -0.2, -0.4, -0.7, -1.2, -1.9, -2.9, -4.3, -6.1, -8.4, -11.1,
+    ];
+    // ITU-R BS.468-4 表 1
+    const ITU468_TABLE: [(f32, f32); 21] = [
+        (31.5, -29.9),
+        (63.0, -23.9),
+        (100.0, -19.8),
+        (200.0, -13.8),
+        (400.0, -7.8),
+        (800.0, -1.9),
+        (1000.0, 0.0),
+        (2000.0, 5.6),
+        (3150.0, 9.0),
+        (4000.0, 10.5),
+        (5000.0, 11.7),
+        (6300.0, 12.2),
+        (7100.0, 12.0),
+        (8000.0, 11.4),
+        (9000.0, 10.1),
+        (10000.0, 8.1),
+        (12500.0, 0.0),
+        (14000.0, -5.3),
+        (16000.0, -11.7),
+        (20000.0, -22.2),
+        (31500.0, -42.7),
+    ];
+
+    fn third_octave(index: usize) -> f32 {
+        1000.0 * 10f32.powf((index as f32 - 20.0) / 10.0)
+    }
+
+    // 表中的值保留一位小数
+    fn check_table(weighting: FrequencyWeighting, table: &[f32]) {
+        for (index, &expected) in table.iter().enumerate() {
+            let freq = third_octave(index);
+            let gain = weighting.gain_db(freq);
+            assert!((gain - expected).abs() <= 0.051, "{} at {} Hz: {} vs {}", weighting.name(), freq, gain, expected);
+        }
+    }
+
+    #[test]
+    fn test_standard_tables() {
+        check_table(FrequencyWeighting::A, &A_TABLE);
+        check_table(FrequencyWeighting::B, &B_TABLE);
+        check_table(FrequencyWeighting::C, &C_TABLE);
+        check_table(FrequencyWeighting::Z, &[0.0; 34]);
+        for (freq, expected) in ITU468_TABLE {
+            let gain = FrequencyWeighting::Itu468.gain_db(freq);
+            assert!((gain - expected).abs() <= 0.06, "468 at {} Hz: {} vs {}", freq, gain, expected);
+        }
+
+        for weighting in FrequencyWeighting::STANDARD {
+            assert!(weighting.gain_db(1000.0).abs() < 1e-4, "{}", weighting.name());
+            assert_eq!(FrequencyWeighting::from_name(weighting.name()), Some(weighting));
+        }
+        assert_eq!(FrequencyWeighting::A.gain(0.0), 0.0);
+        assert_eq!(FrequencyWeighting::A.gain_db(0.0), -200.0);
+        assert_eq!(FrequencyWeighting::from_name("468"), Some(FrequencyWeighting::Itu468));
+        assert_eq!(FrequencyWeighting::from_name("D"), None);
+    }
+
+    #[test]
+    fn test_custom_curve() {
+        let text = "# microphone correction\nFreq(Hz) dB Phase\n100, -2.0, 0\n1000;0\n\n10000\t6 12\n";
+        let curve = WeightingCurve::parse("mic", text).unwrap();
+        assert_eq!(curve.points(), &[(100.0, -2.0), (1000.0, 0.0), (10000.0, 6.0)]);
+        let weighting = FrequencyWeighting::Custom(curve);
+        assert_eq!(weighting.name(), "mic");
+        // 对数频率上插值，两端之外保持
+        assert!((weighting.gain_db(316.227_77) + 1.0).abs() < 1e-4);
+        assert!((weighting.gain_db(3162.2778) - 3.0).abs() < 1e-4);
+        assert_eq!(weighting.gain_db(10.0), -2.0);
+        assert_eq!(weighting.gain_db(0.0), -2.0);
+        assert_eq!(weighting.gain_db(20000.0), 6.0);
+        assert!((weighting.gain(10000.0) - 10f32.powf(0.3)).abs() < 1e-5);
+
+        // 点的顺序无关
+        let reversed = WeightingCurve::new("r", vec![(1000.0, 1.0), (100.0, 0.0)]).unwrap();
+        assert_eq!(reversed.points()[0], (100.0, 0.0));
+
+        assert!(WeightingCurve::parse("empty", "# nothing\n").is_err());
+        assert!(WeightingCurve::parse("missing", "100\n").is_err());
+        assert!(WeightingCurve::parse("bad", "100 abc\n").is_err());
+        assert!(WeightingCurve::parse("negative", "-100 1\n").is_err());
+        assert!(WeightingCurve::parse("duplicate", "100 1\n100 2\n").is_err());
+    }
+
+    // 正弦稳定后读 Leq：先送 1 秒让滤波器稳定，复位后再积分，两段相位连续
+    fn sine_leq(weighting: &FrequencyWeighting, freq: f64) -> f32 {
+        let mut meter = LevelMeter::new(weighting.clone(), TimeWeighting::Fast, FS);
+        let signal = sine(freq, 0.5, 3.0);
+        let (settle, measure) = signal.split_at(FS as usize);
+        meter.process(settle);
+        meter.reset_leq();
+        meter.process(measure);
+        meter.reading().leq
+    }
+
+    #[test]
+    fn test_meter_follows_weighting() {
+        let sine_db = 20.0 * 0.5f32.log10();
+        let curve = WeightingCurve::new("tilt", vec![(100.0, -10.0), (10000.0, 10.0)]).unwrap();
+        let weightings = [
+            FrequencyWeighting::Z,
+            FrequencyWeighting::A,
+            FrequencyWeighting::C,
+            FrequencyWeighting::Itu468,
+            FrequencyWeighting::Custom(curve),
+        ];
+        for weighting in &weightings {
+            for freq in [31.5, 100.0, 1000.0, 6300.0, 16000.0] {
+                let expected = sine_db + weighting.gain_db(freq as f32);
+                let leq = sine_leq(weighting, freq);
+                assert!((leq - expected).abs() < 0.05, "{} at {} Hz: {} vs {}", weighting.name(), freq, leq, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_meter_time_weighting_and_leq() {
+        // -6 dBFS 正弦后接同样长的静音，长度取整块，滤波器输出的点数比输入少半块
+        let mut meter = LevelMeter::new(FrequencyWeighting::A, TimeWeighting::Fast, FS);
+        let block = meter.filter.as_ref().unwrap().block_len();
+        let samples = 6 * block;
+        let tone: Vec<f32> = sine(1000.0, 0.5, 1.0).into_iter().cycle().take(samples).collect();
+        meter.process(&tone);
+        let loud = meter.reading();
+        assert!((loud.level + 6.02).abs() < 0.05, "{}", loud.level);
+        meter.process(&vec![0.0; samples]);
+        let quiet = meter.reading();
+        assert!((quiet.duration - loud.duration - samples as f32 / FS).abs() < 1e-4);
+        assert!((quiet.duration - (2 * samples - block / 2) as f32 / FS).abs() < 1e-4);
+        // 正弦占积分时长的一半多一点，Leq 约低 3 dB
+        let expected = loud.level + 10.0 * (samples as f32 / (2 * samples - block / 2) as f32).log10();
+        assert!((quiet.leq - expected).abs() < 0.05, "{} vs {}", quiet.leq, expected);
+        // Fast 电平在静音的部分按 34.7 dB/s 衰减
+        let silence = (samples - block / 2) as f32 / FS;
+        let decay = 10.0 * std::f32::consts::LOG10_E * silence / 0.125;
+        assert!((loud.level - quiet.level - decay).abs() < 0.5, "{} {} {}", loud.level, quiet.level, decay);
+
+        meter.reset_leq();
+        assert_eq!(meter.duration_secs(), 0.0);
+        assert!(meter.reading().leq < -190.0);
+    }
+}
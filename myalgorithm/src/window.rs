@@ -0,0 +1,243 @@
+// 窗函数类型，带参数的窗携带自己的形状参数
+// 全部按周期型（DFT-even）生成，分母为N而不是N-1，适合频谱分析
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WindowFunction {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+    BlackmanHarris,
+    FlatTop,
+    Kaiser { beta: f32 },
+    Gaussian { sigma: f32 },
+    Tukey { alpha: f32 },
+}
+
+impl WindowFunction {
+    // 供界面列出的全部窗函数（带参数的窗使用常用默认参数）
+    pub const ALL: [WindowFunction; 9] = [
+        WindowFunction::Rectangular,
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::Blackman,
+        WindowFunction::BlackmanHarris,
+        WindowFunction::FlatTop,
+        WindowFunction::Kaiser { beta: 9.0 },
+        WindowFunction::Gaussian { sigma: 0.4 },
+        WindowFunction::Tukey { alpha: 0.5 },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowFunction::Rectangular => "Rectangular",
+            WindowFunction::Hann => "Hann",
+            WindowFunction::Hamming => "Hamming",
+            WindowFunction::Blackman => "Blackman",
+            WindowFunction::BlackmanHarris => "Blackman-Harris",
+            WindowFunction::FlatTop => "Flat-top",
+            WindowFunction::Kaiser { .. } => "Kaiser",
+            WindowFunction::Gaussian { .. } => "Gaussian",
+            WindowFunction::Tukey { .. } => "Tukey",
+        }
+    }
+
+    // 长度为n的窗在第i点的取值
+    pub fn value(&self, i: usize, n: usize) -> f32 {
+        let x = i as f64 / n as f64;
+        let w = match *self {
+            WindowFunction::Rectangular => 1.0,
+            WindowFunction::Hann => cosine_sum(x, &[0.5, 0.5]),
+            WindowFunction::Hamming => cosine_sum(x, &[0.54, 0.46]),
+            WindowFunction::Blackman => cosine_sum(x, &[0.42, 0.5, 0.08]),
+            WindowFunction::BlackmanHarris => {
+                cosine_sum(x, &[0.35875, 0.48829, 0.14128, 0.01168])
+            }
+            // SRS平顶窗，幅度读数误差小于0.01dB
+            WindowFunction::FlatTop => cosine_sum(
+                x,
+                &[0.21557895, 0.41663158, 0.277263158, 0.083578947, 0.006947368],
+            ),
+            WindowFunction::Kaiser { beta } => {
+                let beta = beta as f64;
+                let r = 2.0 * x - 1.0;
+                bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / bessel_i0(beta)
+            }
+            WindowFunction::Gaussian { sigma } => {
+                let r = (x - 0.5) / (sigma as f64 * 0.5);
+                (-0.5 * r * r).exp()
+            }
+            WindowFunction::Tukey { alpha } => {
+                let alpha = (alpha as f64).clamp(0.0, 1.0);
+                let edge = alpha / 2.0;
+                if alpha == 0.0 || (edge..=1.0 - edge).contains(&x) {
+                    1.0
+                } else {
+                    let t = if x < edge { x } else { 1.0 - x };
+                    0.5 * (1.0 - (std::f64::consts::PI * t / edge).cos())
+                }
+            }
+        };
+        w as f32
+    }
+
+    pub fn generate(&self, n: usize) -> Vec<f32> {
+        (0..n).map(|i| self.value(i, n)).collect()
+    }
+}
+
+// 广义余弦窗 a0 - a1*cos(2πx) + a2*cos(4πx) - ...
+fn cosine_sum(x: f64, coefficients: &[f64]) -> f64 {
+    coefficients
+        .iter()
+        .enumerate()
+        .map(|(k, &a)| {
+            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+            sign * a * (2.0 * std::f64::consts::PI * k as f64 * x).cos()
+        })
+        .sum()
+}
+
+// 第一类零阶修正贝塞尔函数，级数展开
+fn bessel_i0(x: f64) -> f64 {
+    let half_x = x / 2.0;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for k in 1..64 {
+        term *= half_x / k as f64;
+        let t2 = term * term;
+        sum += t2;
+        if t2 < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+// 预先计算好的窗系数表及其增益参数
+#[derive(Debug, Clone)]
+pub struct Window {
+    function: WindowFunction,
+    coefficients: Vec<f32>,
+    coherent_gain: f32,
+    enbw_bins: f32,
+}
+
+impl Window {
+    pub fn new(function: WindowFunction, n: usize) -> Self {
+        let coefficients = function.generate(n);
+        let sum: f64 = coefficients.iter().map(|&w| w as f64).sum();
+        let sum_sq: f64 = coefficients.iter().map(|&w| (w as f64) * (w as f64)).sum();
+
+        Self {
+            function,
+            coherent_gain: (sum / n as f64) as f32,
+            enbw_bins: (n as f64 * sum_sq / (sum * sum)) as f32,
+            coefficients,
+        }
+    }
+
+    pub fn function(&self) -> WindowFunction {
+        self.function
+    }
+
+    pub fn len(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    pub fn coefficients(&self) -> &[f32] {
+        &self.coefficients
+    }
+
+    // 相干增益：窗对正弦幅度的衰减系数，幅度读数需除以它
+    pub fn coherent_gain(&self) -> f32 {
+        self.coherent_gain
+    }
+
+    // 等效噪声带宽（单位：频点），噪声密度读数需除以它
+    pub fn enbw_bins(&self) -> f32 {
+        self.enbw_bins
+    }
+
+    pub fn enbw_hz(&self, bin_width: f32) -> f32 {
+        self.enbw_bins * bin_width
+    }
+
+    // 窗引起的噪声功率增益 sum(w²)/N
+    pub fn noise_power_gain(&self) -> f32 {
+        self.coherent_gain * self.coherent_gain * self.enbw_bins
+    }
+
+    // 处理增益损失（dB）
+    pub fn processing_loss_db(&self) -> f32 {
+        10.0 * self.enbw_bins.log10()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32, tol: f32, what: &str) {
+        assert!(
+            (actual - expected).abs() <= tol,
+            "{}: expected {}, got {}",
+            what,
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_reference_gains() {
+        // 参考值见 Harris (1978) 与 Heinzel 等 (2002)
+        let cases = [
+            (WindowFunction::Rectangular, 1.0, 1.0),
+            (WindowFunction::Hann, 0.5, 1.5),
+            (WindowFunction::Hamming, 0.54, 1.3628),
+            (WindowFunction::Blackman, 0.42, 1.7268),
+            (WindowFunction::BlackmanHarris, 0.35875, 2.0044),
+            (WindowFunction::FlatTop, 0.2156, 3.7702),
+        ];
+        for (function, cg, enbw) in cases {
+            let window = Window::new(function, 4096);
+            assert_close(window.coherent_gain(), cg, 1e-3, function.name());
+            assert_close(window.enbw_bins(), enbw, 2e-3, function.name());
+        }
+    }
+
+    #[test]
+    fn test_parametric_windows() {
+        // β=0 的Kaiser窗和α=0的Tukey窗退化为矩形窗
+        let kaiser = Window::new(WindowFunction::Kaiser { beta: 0.0 }, 1024);
+        assert_close(kaiser.coherent_gain(), 1.0, 1e-6, "kaiser beta 0");
+        let tukey = Window::new(WindowFunction::Tukey { alpha: 0.0 }, 1024);
+        assert_close(tukey.enbw_bins(), 1.0, 1e-6, "tukey alpha 0");
+        // α=1 的Tukey窗等于Hann窗
+        let tukey = Window::new(WindowFunction::Tukey { alpha: 1.0 }, 1024);
+        assert_close(tukey.enbw_bins(), 1.5, 1e-3, "tukey alpha 1");
+
+        // β越大主瓣越宽
+        let k6 = Window::new(WindowFunction::Kaiser { beta: 6.0 }, 1024);
+        let k12 = Window::new(WindowFunction::Kaiser { beta: 12.0 }, 1024);
+        assert!(k12.enbw_bins() > k6.enbw_bins());
+
+        let gaussian = WindowFunction::Gaussian { sigma: 0.4 }.generate(1024);
+        assert_close(gaussian[512], 1.0, 1e-6, "gaussian centre");
+    }
+
+    #[test]
+    fn test_windows_symmetric_and_peak_at_centre() {
+        for function in WindowFunction::ALL {
+            let w = function.generate(1024);
+            for i in 1..512 {
+                assert_close(w[i], w[1024 - i], 1e-5, function.name());
+            }
+            assert!(w.iter().all(|&v| v <= w[512] + 1e-6), "{}", function.name());
+        }
+    }
+}
@@ -0,0 +1,96 @@
+// 验证稳态下处理一帧不分配堆内存
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use myalgorithm::{
+    AmplitudeScale, AveragingSettings, BandLevels, Calibration, FrequencyWeighting, LevelMeter, OctaveFilterBank,
+    OctaveFraction, OctaveSettings, SpectrumAverager, SpectrumEngine, SpectrumScaler, TimeWeighting, WindowFunction,
+    FFT_SIZES,
+};
+
+struct CountingAllocator;
+
+// 按线程计数，并行运行的其他测试的分配不会计入
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // 线程退出时本地变量可能已经销毁，此时不计数
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_frame_processing_does_not_allocate() {
+    for &fft_size in &FFT_SIZES {
+        let signal: Vec<f32> = (0..fft_size).map(|i| (i as f32 * 0.1).sin()).collect();
+        let mut engine = SpectrumEngine::new(fft_size, WindowFunction::BlackmanHarris);
+        let mut averager = SpectrumAverager::new(engine.num_bins(), AveragingSettings::default());
+        let scaler = SpectrumScaler::new(AmplitudeScale::Psd, Calibration::default(), engine.window(), 48000.0);
+        let mut magnitudes = vec![0.0; engine.num_bins()];
+        let mut scaled = vec![0.0; engine.num_bins()];
+
+        let before = allocations();
+        for _ in 0..4 {
+            engine.process(&signal, &mut magnitudes);
+            averager.process(&magnitudes, 0.01);
+            scaler.scale_into(&magnitudes, &mut scaled);
+            scaler.scale_into(averager.average(), &mut scaled);
+        }
+        let after = allocations();
+
+        assert_eq!(after - before, 0, "fft size {} allocated", fft_size);
+    }
+}
+
+#[test]
+fn test_octave_bank_does_not_allocate() {
+    let signal: Vec<f32> = (0..1024).map(|i| (i as f32 * 0.1).sin()).collect();
+    for fraction in OctaveFraction::ALL {
+        let mut bank = OctaveFilterBank::new(OctaveSettings { fraction, ..Default::default() }, 48000.0);
+        let mut levels = BandLevels::default();
+        bank.process(&signal);
+        bank.read_levels(&mut levels);
+
+        let before = allocations();
+        for _ in 0..4 {
+            bank.process(&signal);
+            bank.read_levels(&mut levels);
+        }
+        let after = allocations();
+
+        assert_eq!(after - before, 0, "{} allocated", fraction.name());
+    }
+}
+
+#[test]
+fn test_level_meter_does_not_allocate() {
+    let signal: Vec<f32> = (0..5000).map(|i| (i as f32 * 0.1).sin()).collect();
+    for weighting in FrequencyWeighting::STANDARD {
+        let mut meter = LevelMeter::new(weighting.clone(), TimeWeighting::Fast, 48000.0);
+        meter.process(&signal);
+
+        let before = allocations();
+        for _ in 0..4 {
+            meter.process(&signal);
+            meter.reading();
+        }
+        let after = allocations();
+
+        assert_eq!(after - before, 0, "{} allocated", weighting.name());
+    }
+}
@@ -1,26 +1,137 @@
-use crate::ui::draw_spectrum;
+use crate::ui::{draw_band_levels, draw_spectrum, AmplitudeAxis, Trace, TraceStyle};
 use egui;
 use parking_lot::Mutex;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
-use myalgorithm::BUFFER_SZ;
+use egui::Rect;
+use myalgorithm::{AmplitudeScale, AnalysisConfig, Calibration, WindowFunction, MAX_OVERLAP};
+use myalgorithm::{max_average_frames, AveragingMode, ChannelMode, PeakHold};
+use myalgorithm::{find_peaks, Peak, PeakInterpolation, PeakSettings};
+use myalgorithm::{OctaveFraction, OctaveSettings, TimeWeighting};
+use myalgorithm::{FrequencyWeighting, WeightingCurve};
+use crossbeam_channel::Sender;
+use crate::audio::{AudioCommand, SourceStatus, Transport};
+use crate::distortion_panel::DistortionPanel;
+use crate::source_panel::{status_label, SourcePanel};
+use crate::spectrogram::Spectrogram;
+use crate::spectrum::{load_weighting_curve, AnalyzerSettings, InputState, SpectrumFrame, SpectrumReceiver};
+
+// 多路信号同时显示时各路的颜色
+const CHANNEL_COLORS: [egui::Color32; 8] = [
+    egui::Color32::from_rgb(230, 200, 40),
+    egui::Color32::from_rgb(60, 180, 230),
+    egui::Color32::from_rgb(230, 90, 160),
+    egui::Color32::from_rgb(110, 210, 90),
+    egui::Color32::from_rgb(240, 130, 40),
+    egui::Color32::from_rgb(150, 110, 230),
+    egui::Color32::from_rgb(40, 200, 170),
+    egui::Color32::from_rgb(200, 200, 200),
+];
+
+fn channel_color(index: usize) -> egui::Color32 {
+    CHANNEL_COLORS[index % CHANNEL_COLORS.len()]
+}
+
+// 各条曲线是否显示
+struct TraceVisibility {
+    live: bool,
+    average: bool,
+    max_hold: bool,
+    min_hold: bool,
+}
 
 pub struct SpectrumApp {
-    spectrum: Arc<Mutex<Vec<f32>>>,
-    display_buffer: Vec<f32>,
+    receiver: SpectrumReceiver,
+    input: Arc<Mutex<InputState>>,
+    settings: Arc<Mutex<AnalyzerSettings>>,
+    // 发给音频管理线程的命令
+    commands: Sender<AudioCommand>,
+    source_panel: SourcePanel,
+    show_sources: bool,
+    config: AnalysisConfig,
+    frames_per_second: f32,
+    // 统计帧率的起点和当时已收到的帧数
+    rate_start: Instant,
+    rate_received: u64,
+    amplitude_axis: AmplitudeAxis,
+    input_name: String,
+    input_channels: usize,
+    input_format: String,
+    // 文件输入的播放控制
+    transport: Option<Transport>,
+    status: SourceStatus,
+    // 每路最新的一帧
+    display: Vec<SpectrumFrame>,
+    visible: TraceVisibility,
+    spectrogram: Spectrogram,
+    show_spectrogram: bool,
+    // 第一路实时曲线上的寻峰
+    show_peaks: bool,
+    peak_settings: PeakSettings,
+    distortion: DistortionPanel,
+    // 倍频程柱状图
+    show_octave: bool,
+    octave: OctaveSettings,
+    // 柱高显示 Leq 而不是时间计权电平
+    show_leq: bool,
+    // 载入过的自定义计权曲线，与标准计权一起列在选择框里
+    weighting_curves: Vec<WeightingCurve>,
+    curve_path: String,
+    curve_error: Option<String>,
+    // 计权后的宽带电平读数
+    show_meter: bool,
     last_update: Instant,
-    frame_buffer: Vec<f32>,    // 添加帧缓冲
     interpolation: f32,        // 添加插值因子
     frame_time: Instant,
     frame_count: u32,
 }
 
 impl SpectrumApp {
-    pub fn new(spectrum: Arc<Mutex<Vec<f32>>>) -> Self {
+    pub fn new(
+        receiver: SpectrumReceiver,
+        input: Arc<Mutex<InputState>>,
+        settings: Arc<Mutex<AnalyzerSettings>>,
+        commands: Sender<AudioCommand>,
+        config: AnalysisConfig,
+    ) -> Self {
+        let initial = SpectrumFrame::new(config);
         Self {
-            spectrum,
-            display_buffer: vec![0.0; BUFFER_SZ],
-            frame_buffer: vec![0.0; BUFFER_SZ],
+            receiver,
+            input,
+            settings,
+            commands,
+            source_panel: SourcePanel::new(),
+            show_sources: false,
+            config,
+            frames_per_second: 0.0,
+            rate_start: Instant::now(),
+            rate_received: 0,
+            amplitude_axis: AmplitudeAxis::from_scaler(&initial.scaler),
+            input_name: String::new(),
+            input_channels: 1,
+            input_format: String::new(),
+            transport: None,
+            status: SourceStatus::default(),
+            display: vec![initial],
+            visible: TraceVisibility {
+                live: true,
+                average: false,
+                max_hold: true,
+                min_hold: false,
+            },
+            spectrogram: Spectrogram::new(),
+            show_spectrogram: false,
+            show_peaks: false,
+            peak_settings: PeakSettings::default(),
+            distortion: DistortionPanel::new(),
+            show_octave: false,
+            octave: OctaveSettings::default(),
+            show_leq: false,
+            weighting_curves: Vec::new(),
+            curve_path: String::new(),
+            curve_error: None,
+            show_meter: false,
             interpolation: 0.0,
             last_update: Instant::now(),
             frame_time: Instant::now(),
@@ -29,29 +140,370 @@ impl SpectrumApp {
     }
 
     fn update_display_buffer(&mut self) {
-        let spectrum = self.spectrum.lock();
-        // 使用双重缓冲和插值更新
-        self.frame_buffer.copy_from_slice(&self.display_buffer);
-        
-        for (i, &value) in spectrum.iter().enumerate() {
-            // 使用指数平滑
-            let target = value.max(self.display_buffer[i]*0.9);
-            self.display_buffer[i] = self.display_buffer[i] * 0.2 + target * 0.8;
+        // 平均与保持都在分析线程完成，这里只取走最新的帧，没有新帧时保持上一帧
+        let spectrogram = &mut self.spectrogram;
+        if self.receiver.receive(&mut self.display, |frame| spectrogram.push(frame)) {
+            let latest = &self.display[0];
+            self.config = latest.config;
+            self.amplitude_axis = AmplitudeAxis::from_scaler(&latest.scaler);
+        }
+
+        // 各路的帧数相同，按路数折算成每路每秒的帧数
+        let elapsed = self.rate_start.elapsed().as_secs_f32();
+        if elapsed >= 1.0 {
+            let received = self.receiver.stats().received;
+            let per_channel = (received - self.rate_received) as f32 / self.display.len() as f32;
+            self.frames_per_second = per_channel / elapsed;
+            self.rate_received = received;
+            self.rate_start = Instant::now();
+        }
+
+        let input = self.input.lock();
+        self.input_channels = input.info.channels.max(1);
+        if self.input_name != input.info.name {
+            self.input_name = input.info.name.clone();
+        }
+        if self.input_format != input.info.format {
+            self.input_format = input.info.format.clone();
         }
+        self.transport.clone_from(&input.transport);
+        self.status.clone_from(&input.status);
     }
     
-    // 显示事件列表
-    pub fn show_device_switcher(&self) {
-        use std::io::{self, Write};
-        
-        print!("按回车键显示设备列表...");
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_ok() {
-            // 继续使用当前设备
+    // 顶部控制栏：窗函数选择
+    fn show_controls(&mut self, ui: &mut egui::Ui) {
+        let mut settings = self.settings.lock().clone();
+
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.show_sources, "音频源");
+            ui.separator();
+
+            egui::ComboBox::from_label("窗函数")
+                .selected_text(settings.window.name())
+                .show_ui(ui, |ui| {
+                    for function in WindowFunction::ALL {
+                        let selected = std::mem::discriminant(&settings.window)
+                            == std::mem::discriminant(&function);
+                        if ui.selectable_label(selected, function.name()).clicked() && !selected {
+                            settings.window = function;
+                        }
+                    }
+                });
+
+            match &mut settings.window {
+                WindowFunction::Kaiser { beta } => {
+                    ui.add(egui::DragValue::new(beta).clamp_range(0.0..=40.0).speed(0.1).prefix("β="));
+                }
+                WindowFunction::Gaussian { sigma } => {
+                    ui.add(egui::DragValue::new(sigma).clamp_range(0.05..=1.0).speed(0.01).prefix("σ="));
+                }
+                WindowFunction::Tukey { alpha } => {
+                    ui.add(egui::DragValue::new(alpha).clamp_range(0.0..=1.0).speed(0.01).prefix("α="));
+                }
+                _ => {}
+            }
+
+            ui.label(format!(
+                "相干增益 {:.2} dB  ENBW {:.2} Hz",
+                20.0 * self.display[0].coherent_gain.log10(),
+                self.display[0].enbw_hz
+            ));
+
+            let mut overlap_percent = settings.overlap * 100.0;
+            let slider = egui::Slider::new(&mut overlap_percent, 0.0..=MAX_OVERLAP * 100.0)
+                .text("重叠")
+                .suffix("%");
+            if ui.add(slider).changed() {
+                settings.overlap = overlap_percent / 100.0;
+            }
+            ui.label(format!(
+                "{:.1} 帧/秒 (理论 {:.1}, 步进 {})",
+                self.frames_per_second,
+                self.config.frames_per_second(),
+                self.config.hop_size
+            ));
+            let stats = self.receiver.stats();
+            if stats.dropped > 0 || stats.duplicated > 0 {
+                ui.colored_label(
+                    egui::Color32::from_rgb(230, 120, 40),
+                    format!("丢帧 {}  重复 {}", stats.dropped, stats.duplicated),
+                );
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{}  {}  {:.0} Hz",
+                self.input_name,
+                self.input_format,
+                self.config.sample_rate
+            ));
+            if self.status != SourceStatus::Running {
+                status_label(ui, &self.status);
+            }
+            if let Some(notice) = self.source_panel.recent_notice() {
+                ui.colored_label(egui::Color32::from_rgb(60, 180, 230), notice);
+            }
+            ui.separator();
+
+            egui::ComboBox::from_label("单位")
+                .selected_text(settings.scale.name())
+                .show_ui(ui, |ui| {
+                    for scale in AmplitudeScale::ALL {
+                        ui.selectable_value(&mut settings.scale, scale, scale.name());
+                    }
+                });
+
+            // 校准参数按当前输入分别保存
+            let mut calibration = settings.calibration_for(&self.input_name);
+            ui.label("满幅峰值电压");
+            ui.add(egui::DragValue::new(&mut calibration.volts_per_fs).clamp_range(0.001..=100.0).speed(0.01).suffix(" V"));
+            ui.label("传声器灵敏度");
+            ui.add(egui::DragValue::new(&mut calibration.mic_sensitivity_mv_per_pa).clamp_range(0.01..=1000.0).speed(0.1).suffix(" mV/Pa"));
+            if calibration != settings.calibration_for(&self.input_name) {
+                if calibration == Calibration::default() {
+                    settings.calibrations.remove(&self.input_name);
+                } else {
+                    settings.calibrations.insert(self.input_name.clone(), calibration);
+                }
+            }
+
+            ui.separator();
+            self.show_weighting(ui, &mut settings.weighting);
+
+            ui.separator();
+            let channel_mode = settings.channel_mode.resolve(self.input_channels);
+            egui::ComboBox::from_label(format!("声道 ({})", self.input_channels))
+                .selected_text(channel_mode.name())
+                .show_ui(ui, |ui| {
+                    for mode in ChannelMode::available(self.input_channels) {
+                        ui.selectable_value(&mut settings.channel_mode, mode, mode.name());
+                    }
+                });
+            if self.display.len() > 1 {
+                for (index, channel) in self.display.iter().enumerate() {
+                    ui.colored_label(channel_color(index), &channel.name);
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let averaging = &mut settings.averaging;
+            egui::ComboBox::from_label("平均")
+                .selected_text(averaging.mode.name())
+                .show_ui(ui, |ui| {
+                    for mode in AveragingMode::ALL {
+                        ui.selectable_value(&mut averaging.mode, mode, mode.name());
+                    }
+                });
+            match averaging.mode {
+                AveragingMode::Exponential => {
+                    ui.add(egui::DragValue::new(&mut averaging.time_constant_ms).clamp_range(1.0..=60000.0).speed(5.0).prefix("τ=").suffix(" ms"));
+                }
+                AveragingMode::Linear | AveragingMode::RmsPower => {
+                    let max_frames = max_average_frames(self.config.freq_axis().num_bins()).min(1024);
+                    ui.add(egui::DragValue::new(&mut averaging.frames).clamp_range(1..=max_frames).prefix("N="));
+                }
+            }
+
+            let mut infinite = averaging.peak_hold == PeakHold::Infinite;
+            ui.checkbox(&mut infinite, "无限峰值保持");
+            averaging.peak_hold = match (infinite, averaging.peak_hold) {
+                (true, _) => PeakHold::Infinite,
+                (false, PeakHold::Infinite) => PeakHold::default(),
+                (false, decay) => decay,
+            };
+            if let PeakHold::Decay { db_per_second } = &mut averaging.peak_hold {
+                ui.add(egui::DragValue::new(db_per_second).clamp_range(0.1..=200.0).speed(0.5).prefix("回落 ").suffix(" dB/s"));
+            }
+            if ui.button("复位").clicked() {
+                settings.averaging_reset = settings.averaging_reset.wrapping_add(1);
+            }
+
+            ui.separator();
+            ui.checkbox(&mut self.visible.live, "实时");
+            ui.checkbox(&mut self.visible.average, "平均");
+            ui.checkbox(&mut self.visible.max_hold, "最大");
+            ui.checkbox(&mut self.visible.min_hold, "最小");
+            ui.separator();
+            ui.checkbox(&mut self.show_peaks, "寻峰");
+            ui.checkbox(&mut self.show_spectrogram, "时频图");
+            ui.checkbox(&mut self.show_octave, "倍频程");
+            ui.checkbox(&mut self.show_meter, "电平表");
+            ui.checkbox(&mut self.distortion.enabled, "失真分析");
+        });
+
+        if self.show_peaks {
+            ui.horizontal(|ui| {
+                let peaks = &mut self.peak_settings;
+                ui.add(egui::DragValue::new(&mut peaks.max_peaks).clamp_range(1..=20).prefix("峰数 "));
+                egui::ComboBox::from_label("插值")
+                    .selected_text(peaks.interpolation.name())
+                    .show_ui(ui, |ui| {
+                        for method in PeakInterpolation::ALL {
+                            ui.selectable_value(&mut peaks.interpolation, method, method.name());
+                        }
+                    });
+                ui.add(egui::DragValue::new(&mut peaks.min_separation_hz).clamp_range(0.0..=10000.0).speed(1.0).prefix("最小间隔 ").suffix(" Hz"));
+                ui.add(egui::DragValue::new(&mut peaks.min_prominence_db).clamp_range(0.0..=100.0).speed(0.5).prefix("突出度 ").suffix(" dB"));
+            });
+        }
+
+        if self.show_octave {
+            ui.horizontal(|ui| {
+                let octave = &mut self.octave;
+                egui::ComboBox::from_label("频带")
+                    .selected_text(octave.fraction.name())
+                    .show_ui(ui, |ui| {
+                        for fraction in OctaveFraction::ALL {
+                            ui.selectable_value(&mut octave.fraction, fraction, fraction.name());
+                        }
+                    });
+                ui.add(egui::DragValue::new(&mut octave.min_freq_hz).clamp_range(10.0..=octave.max_freq_hz).speed(1.0).prefix("从 ").suffix(" Hz"));
+                ui.add(egui::DragValue::new(&mut octave.max_freq_hz).clamp_range(octave.min_freq_hz..=96000.0).speed(10.0).prefix("到 ").suffix(" Hz"));
+                egui::ComboBox::from_label("时间计权")
+                    .selected_text(octave.time_weighting.name())
+                    .show_ui(ui, |ui| {
+                        for weighting in TimeWeighting::ALL {
+                            ui.selectable_value(&mut octave.time_weighting, weighting, weighting.name());
+                        }
+                    });
+                ui.checkbox(&mut self.show_leq, "显示 Leq");
+                ui.label(format!("Leq {}", format_time(self.display[0].bands.duration)));
+                if ui.button("复位 Leq").clicked() {
+                    settings.leq_reset = settings.leq_reset.wrapping_add(1);
+                }
+            });
+        }
+
+        if self.show_meter {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("电平时间计权")
+                    .selected_text(settings.level_time_weighting.name())
+                    .show_ui(ui, |ui| {
+                        for weighting in TimeWeighting::ALL {
+                            ui.selectable_value(&mut settings.level_time_weighting, weighting, weighting.name());
+                        }
+                    });
+                let (unit, offset_db) = self.level_unit();
+                let label = level_label(&settings.weighting, settings.level_time_weighting);
+                for (index, channel) in self.display.iter().enumerate() {
+                    ui.separator();
+                    let reading = &channel.level;
+                    ui.colored_label(
+                        channel_color(index),
+                        format!(
+                            "{}  {} {:.1} {}  Leq {:.1} {}",
+                            channel.name,
+                            label,
+                            reading.level + offset_db,
+                            unit,
+                            reading.leq + offset_db,
+                            unit
+                        ),
+                    );
+                }
+                ui.separator();
+                ui.label(format!("Leq {}", format_time(self.display[0].level.duration)));
+                if ui.button("复位 Leq").clicked() {
+                    settings.leq_reset = settings.leq_reset.wrapping_add(1);
+                }
+            });
+        }
+
+        if self.show_spectrogram {
+            ui.horizontal(|ui| {
+                self.spectrogram.show_controls(ui, &self.display, &self.amplitude_axis);
+            });
+        }
+
+        settings.distortion = self.distortion.settings();
+        settings.octave = self.show_octave.then_some(self.octave);
+        let mut shared = self.settings.lock();
+        if *shared != settings {
+            *shared = settings;
+        }
+    }
+
+    // 频率计权选择，以及从文件载入自定义曲线
+    fn show_weighting(&mut self, ui: &mut egui::Ui, weighting: &mut FrequencyWeighting) {
+        egui::ComboBox::from_label("频率计权")
+            .selected_text(weighting.name())
+            .show_ui(ui, |ui| {
+                for option in FrequencyWeighting::STANDARD {
+                    let selected = *weighting == option;
+                    if ui.selectable_label(selected, option.name()).clicked() && !selected {
+                        *weighting = option;
+                    }
+                }
+                for curve in &self.weighting_curves {
+                    let option = FrequencyWeighting::Custom(curve.clone());
+                    let selected = *weighting == option;
+                    if ui.selectable_label(selected, curve.name()).clicked() && !selected {
+                        *weighting = option;
+                    }
+                }
+            });
+
+        let edit = egui::TextEdit::singleline(&mut self.curve_path).hint_text("计权曲线文件").desired_width(140.0);
+        let response = ui.add(edit);
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if ui.button("载入").clicked() || submitted {
+            match load_weighting_curve(Path::new(self.curve_path.trim())) {
+                Ok(curve) => {
+                    // 同名曲线重新载入时替换旧的
+                    self.weighting_curves.retain(|c| c.name() != curve.name());
+                    self.weighting_curves.push(curve.clone());
+                    *weighting = FrequencyWeighting::Custom(curve);
+                    self.curve_error = None;
+                }
+                Err(e) => self.curve_error = Some(e),
+            }
+        }
+        if let Some(error) = &self.curve_error {
+            ui.colored_label(egui::Color32::from_rgb(230, 80, 60), error);
         }
     }
+
+    // 电平和倍频程柱状图的单位与相对 dBFS 的偏移；线性和功率谱密度单位下改用 dBFS
+    fn level_unit(&self) -> (&'static str, f32) {
+        let scaler = &self.display[0].scaler;
+        match scaler.scale() {
+            AmplitudeScale::Linear | AmplitudeScale::Psd => (AmplitudeScale::Dbfs.unit(), 0.0),
+            scale => (scale.unit(), scaler.full_scale_level()),
+        }
+    }
+
+    // 底部播放控制栏，只在文件输入时显示
+    fn show_transport(&self, ui: &mut egui::Ui, transport: &Transport) {
+        ui.horizontal(|ui| {
+            let playing = transport.is_playing();
+            if ui.button(if playing { "暂停" } else { "播放" }).clicked() {
+                transport.set_playing(!playing);
+            }
+
+            let duration = transport.duration_secs();
+            let mut position = transport.position_secs();
+            let slider = egui::Slider::new(&mut position, 0.0..=duration.max(0.001))
+                .show_value(false);
+            if ui.add(slider).changed() {
+                transport.seek(position);
+            }
+            ui.label(format!("{} / {}", format_time(position), format_time(duration)));
+            if transport.is_finished() {
+                ui.label("已结束");
+            }
+
+            let mut looping = transport.is_looping();
+            if ui.checkbox(&mut looping, "循环").changed() {
+                transport.set_looping(looping);
+            }
+            let mut fast = !transport.is_realtime();
+            if ui.checkbox(&mut fast, "全速分析").changed() {
+                transport.set_realtime(!fast);
+            }
+        });
+    }
 }
 
 impl eframe::App for SpectrumApp {
@@ -68,13 +520,134 @@ impl eframe::App for SpectrumApp {
         // 强制持续渲染
         ctx.request_repaint();
         
+        self.source_panel.poll();
+        egui::TopBottomPanel::top("controls").show(ctx, |ui| {
+            self.show_controls(ui);
+        });
+
+        if self.show_sources {
+            egui::SidePanel::left("sources").resizable(true).show(ctx, |ui| {
+                let input = self.input.lock().clone();
+                if let Some(spec) = self.source_panel.show(ui, &input) {
+                    let _ = self.commands.send(AudioCommand::SwitchSource(spec));
+                }
+            });
+        }
+
+        if self.distortion.enabled {
+            egui::SidePanel::right("distortion").resizable(true).show(ctx, |ui| {
+                self.distortion.show(ui, &self.display, channel_color);
+            });
+        }
+
+        if let Some(transport) = self.transport.clone() {
+            egui::TopBottomPanel::bottom("transport").show(ctx, |ui| {
+                self.show_transport(ui, &transport);
+            });
+        }
+
         // 优化绘制逻辑
         egui::CentralPanel::default()
             .frame(egui::Frame::none().fill(egui::Color32::from_rgb(0, 0, 0)))
             .show(ctx, |ui| {
                 ui.ctx().request_repaint(); // 确保连续重绘
                 self.update_display_buffer();
-                draw_spectrum(ui, &self.display_buffer);
+                let multiple = self.display.len() > 1;
+                // 时频图暂停回看时，所选那一路的实时曲线换成选中的历史帧
+                let selected = self.spectrogram.selected();
+                let mut traces = Vec::with_capacity(4 * self.display.len());
+                for (index, channel) in self.display.iter().enumerate() {
+                    // 单路时沿用原来的配色，多路时每路一种颜色，保持/平均曲线用较暗的同色
+                    let color = channel_color(index);
+                    let style = |single: egui::Color32, dim: f32| {
+                        if multiple {
+                            TraceStyle::Solid(color.linear_multiply(dim))
+                        } else {
+                            TraceStyle::Solid(single)
+                        }
+                    };
+                    if self.visible.min_hold {
+                        traces.push(Trace { values: &channel.traces.min_hold, style: style(egui::Color32::from_rgb(40, 90, 200), 0.35) });
+                    }
+                    if self.visible.average {
+                        traces.push(Trace { values: &channel.traces.average, style: style(egui::Color32::from_rgb(20, 140, 60), 0.7) });
+                    }
+                    if self.visible.max_hold {
+                        traces.push(Trace { values: &channel.traces.max_hold, style: style(egui::Color32::from_rgb(200, 60, 40), 0.5) });
+                    }
+                    if self.visible.live {
+                        let live = if multiple { TraceStyle::Solid(color) } else { TraceStyle::Gradient };
+                        let values = match selected {
+                            Some((selected, values)) if selected == index && values.len() == channel.traces.live.len() => values,
+                            _ => &channel.traces.live,
+                        };
+                        traces.push(Trace { values, style: live });
+                    }
+                }
+
+                // 寻峰使用第一路显示的实时曲线，暂停回看时为选中的历史帧
+                let mut peaks: Vec<Peak> = Vec::new();
+                if self.show_peaks {
+                    let first = &self.display[0];
+                    let values = match selected {
+                        Some((0, values)) if values.len() == first.traces.live.len() => values,
+                        _ => &first.traces.live,
+                    };
+                    peaks = find_peaks(values, &first.axis, first.scaler.scale().is_db(), &self.peak_settings);
+                }
+
+                // 从上到下依次为频谱曲线、倍频程柱状图、时频图，按权重分配高度；频谱曲线与时频图的频率轴对齐
+                let weights = [
+                    1.0,
+                    if self.show_octave { 1.0 } else { 0.0 },
+                    if self.show_spectrogram { 1.2 } else { 0.0 },
+                ];
+                let total: f32 = weights.iter().sum();
+                let rect = ui.available_rect_before_wrap();
+                let mut top = rect.top();
+                let areas = weights.map(|weight| {
+                    let bottom = top + rect.height() * weight / total;
+                    let area = Rect::from_min_max(egui::pos2(rect.left(), top), egui::pos2(rect.right(), bottom));
+                    top = bottom;
+                    area
+                });
+
+                ui.allocate_ui_at_rect(areas[0], |ui| draw_spectrum(ui, &traces, &peaks, &self.config, &self.amplitude_axis));
+                // 曲线可能引用时频图里的历史帧，绘制时频图前先释放
+                drop(traces);
+                if self.show_octave {
+                    // 线性和功率谱密度单位下柱状图改用 dBFS
+                    let (unit, offset_db) = self.level_unit();
+                    let axis = match self.display[0].scaler.scale() {
+                        AmplitudeScale::Linear | AmplitudeScale::Psd => AmplitudeAxis { unit, min: -140.0, max: 10.0 },
+                        _ => self.amplitude_axis,
+                    };
+                    let channels: Vec<_> = self
+                        .display
+                        .iter()
+                        .enumerate()
+                        .map(|(index, channel)| (&channel.bands, channel_color(index)))
+                        .collect();
+                    ui.allocate_ui_at_rect(areas[1], |ui| draw_band_levels(ui, &channels, self.show_leq, offset_db, &axis));
+                }
+                if self.show_spectrogram {
+                    ui.allocate_ui_at_rect(areas[2], |ui| self.spectrogram.show(ui));
+                }
             });
     }
 }
+
+// 电平的名称，如 LAF、LCS；非字母的计权写在括号里
+fn level_label(weighting: &FrequencyWeighting, time_weighting: TimeWeighting) -> String {
+    let time = &time_weighting.name()[..1];
+    match weighting {
+        FrequencyWeighting::Custom(_) | FrequencyWeighting::Itu468 => format!("L({}){}", weighting.name(), time),
+        _ => format!("L{}{}", weighting.name(), time),
+    }
+}
+
+// 秒数显示为 分:秒.毫秒
+fn format_time(seconds: f32) -> String {
+    let minutes = (seconds / 60.0).floor();
+    format!("{}:{:06.3}", minutes as u32, seconds - minutes * 60.0)
+}
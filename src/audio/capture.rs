@@ -1,170 +1,226 @@
-use cpal::traits::DeviceTrait;
-use parking_lot::Mutex;
-use ringbuf::HeapRb;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
-use myalgorithm::BUFFER_SZ;
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{FromSample, HostId, SampleFormat, SizedSample};
+use myalgorithm::SUPPORTED_SAMPLE_RATES;
 
-use super::device::AudioDeviceManager;
-use crate::spectrum::SpectrumAnalyzer;
+use super::device::{AudioDeviceManager, DeviceId};
+use super::policy::DevicePolicy;
+use super::source::{AudioSource, SourceInfo};
+use super::SourceSpec;
+use crate::spectrum::SampleSink;
 
-#[derive(Clone)]
+// 要打开的设备与流参数，未指定的项按设备支持情况自动选择
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeviceSpec {
+    // None 使用系统默认的音频主机
+    pub host: Option<HostId>,
+    // 设备的稳定标识，优先于编号
+    pub id: Option<DeviceId>,
+    // 设备在当前枚举结果中的编号（命令行使用），两者都为 None 时按 DevicePolicy 的规则自动选择
+    pub index: Option<usize>,
+    // 不指定设备时捕获系统输出
+    pub loopback: bool,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    // 每次回调的帧数
+    pub buffer_size: Option<u32>,
+}
+
+// 通过cpal打开的系统音频输入设备
 pub struct AudioCapture {
-    device_manager: AudioDeviceManager,
-    spectrum: Arc<Mutex<Vec<f32>>>,
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+    buffer_size: Option<u32>,
+    // 设备配置中的各声道增益（线性）
+    gains: Vec<f32>,
+    info: SourceInfo,
+    // 实际打开的设备与流参数，按规则选中的设备记下所在的主机
+    spec: DeviceSpec,
+    stream: Option<cpal::Stream>,
 }
 
 impl AudioCapture {
-    pub fn new(spectrum: Arc<Mutex<Vec<f32>>>) -> Self {
-        Self {
-            device_manager: AudioDeviceManager::new(),
-            spectrum,
-        }
-    }
-
-    pub fn start_capture(&self) -> Option<cpal::Stream> {
-        let device = self.device_manager.get_default_device();
-        match self.get_device_config(&device) {
-            Ok(config) => match self.create_audio_stream(device, config) {
-                Ok(stream) => Some(stream),
-                Err(e) => {
-                    println!("Failed to create audio stream: {}", e);
-                    None
+    pub fn open(spec: &DeviceSpec) -> Result<Self, String> {
+        let manager = match spec.id.as_ref().map(|id| id.host).or(spec.host) {
+            Some(id) => AudioDeviceManager::with_host(id)?,
+            None => AudioDeviceManager::new(),
+        };
+        let policy = DevicePolicy::current();
+        let mut spec = spec.clone();
+        let device = match (&spec.id, spec.index) {
+            (Some(id), _) => manager
+                .get_device_by_id(id)
+                .ok_or_else(|| format!("Device not found: {}", id))?,
+            (None, Some(index)) => manager
+                .get_device_by_index(index)
+                .ok_or_else(|| "Invalid device index".to_string())?,
+            (None, None) if spec.loopback => manager.find_loopback_device()?,
+            // 按配置的规则选择，规则要求的声道数和采样率作为未指定时的流参数
+            (None, None) => match policy.select(&manager) {
+                Some((device, rule)) => {
+                    spec.channels = spec.channels.or(rule.channels);
+                    spec.sample_rate = spec.sample_rate.or(rule.sample_rate);
+                    // 规则可能指定了其他主机，断开后要在那个主机上按名称找回
+                    spec.id = Some(DeviceId {
+                        host: rule.host.unwrap_or_else(|| manager.host_id()),
+                        name: device.name().unwrap_or_default(),
+                        ordinal: 0,
+                    });
+                    device
                 }
+                None => manager
+                    .get_default_device()
+                    .ok_or_else(|| "No input device available".to_string())?,
             },
-            Err(e) => {
-                println!("Failed to get device config: {}", e);
-                None
-            }
+        };
+        let config = get_device_config(&device, &spec)
+            .map_err(|e| format!("Failed to get device config: {}", e))?;
+        let info = SourceInfo {
+            name: device.name().unwrap_or_default(),
+            sample_rate: config.sample_rate().0,
+            channels: config.channels() as usize,
+            format: describe_sample_format(config.sample_format()),
+        };
+        let gains = policy.channel_gains(&info.name, info.channels);
+        if gains.iter().any(|&g| g != 1.0) {
+            let db: Vec<String> = gains.iter().map(|g| format!("{:+.2}", 20.0 * g.log10())).collect();
+            println!("Applying device gain: {} dB", db.join(", "));
         }
+        Ok(Self { device, config, buffer_size: spec.buffer_size, gains, info, spec, stream: None })
     }
 
-    pub fn switch_device(&self, index: usize) -> Option<cpal::Stream> {
-        if let Some(device) = self.device_manager.get_device_by_index(index) {
-            match self.get_device_config(&device) {
-                Ok(config) => {
-                    match self.create_audio_stream(device, config) {
-                        Ok(stream) => Some(stream),
-                        Err(err) => {
-                            println!("Failed to create audio stream: {}", err);
-                            None
-                        }
-                    }
-                }
-                Err(err) => {
-                    println!("Failed to get device config: {}", err);
-                    None
-                }
-            }
-        } else {
-            println!("Invalid device index");
-            None
+    pub fn print_device_list() {
+        AudioDeviceManager::new().print_device_list();
+    }
+}
+
+impl AudioSource for AudioCapture {
+    fn info(&self) -> &SourceInfo {
+        &self.info
+    }
+
+    fn start(&mut self, sink: SampleSink) -> Result<(), String> {
+        let device = &self.device;
+        let gains = self.gains.clone();
+        let sample_format = self.config.sample_format();
+        let mut stream_config: cpal::StreamConfig = self.config.clone().into();
+        if let Some(frames) = self.buffer_size {
+            stream_config.buffer_size = cpal::BufferSize::Fixed(frames);
         }
+        // 按设备的原生样本格式建立输入流，回调里统一转换成归一化的f32
+        let stream = match sample_format {
+            SampleFormat::I8 => build_input_stream::<i8>(device, &stream_config, sink, gains),
+            SampleFormat::I16 => build_input_stream::<i16>(device, &stream_config, sink, gains),
+            SampleFormat::I32 => build_input_stream::<i32>(device, &stream_config, sink, gains),
+            SampleFormat::U8 => build_input_stream::<u8>(device, &stream_config, sink, gains),
+            SampleFormat::U16 => build_input_stream::<u16>(device, &stream_config, sink, gains),
+            SampleFormat::U32 => build_input_stream::<u32>(device, &stream_config, sink, gains),
+            SampleFormat::F32 => build_input_stream::<f32>(device, &stream_config, sink, gains),
+            SampleFormat::F64 => build_input_stream::<f64>(device, &stream_config, sink, gains),
+            other => return Err(format!("Unsupported sample format: {:?}", other)),
+        }?;
+        println!("Input sample format: {}", self.info.format);
+        stream.play().map_err(|e| format!("Failed to start input stream: {}", e))?;
+        self.stream = Some(stream);
+        Ok(())
     }
 
-    pub fn print_device_list(&self) {
-        self.device_manager.print_device_list();
+    fn resolved_spec(&self) -> Option<SourceSpec> {
+        Some(SourceSpec::Device(self.spec.clone()))
     }
 
-    fn get_device_config(&self, device: &cpal::Device) -> Result<cpal::SupportedStreamConfig, String> {
-        println!("Trying to get config for device: {}", device.name().unwrap_or_default());
-        
-        let supported_configs = match device.supported_input_configs() {
-            Ok(configs) => configs,
-            Err(e) => return Err(format!("Failed to get supported configs: {}", e)),
-        };
+    fn stop(&mut self) {
+        // 释放cpal流即停止回调
+        self.stream = None;
+    }
+}
 
-        let mut configs: Vec<_> = supported_configs.collect();
-        if configs.is_empty() {
-            return Err("Device does not support any input configurations".to_string());
-        }
+// 在满足指定声道数和采样率的配置中选择，未指定采样率时按 SUPPORTED_SAMPLE_RATES 的顺序优先
+fn get_device_config(device: &cpal::Device, spec: &DeviceSpec) -> Result<cpal::SupportedStreamConfig, String> {
+    println!("Trying to get config for device: {}", device.name().unwrap_or_default());
+    
+    let supported_configs = match device.supported_input_configs() {
+        Ok(configs) => configs,
+        Err(e) => return Err(format!("Failed to get supported configs: {}", e)),
+    };
 
-        // Try preferred sample rates
-        let preferred_rates = [44100, 48000, 96000, 192000];
-        for &rate in &preferred_rates {
-            if let Some(config) = configs.iter()
-                .find(|c| {
-                    let min_rate = c.min_sample_rate().0;
-                    let max_rate = c.max_sample_rate().0;
-                    min_rate <= rate && rate <= max_rate
-                })
-            {
-                println!("Selected sample rate: {}Hz", rate);
-                return Ok(config.with_sample_rate(cpal::SampleRate(rate)));
-            }
-        }
+    let mut configs: Vec<_> = supported_configs
+        .filter(|c| spec.channels.is_none_or(|channels| c.channels() == channels))
+        .collect();
+    if configs.is_empty() {
+        return match spec.channels {
+            Some(channels) => Err(format!("Device does not support {} input channels", channels)),
+            None => Err("Device does not support any input configurations".to_string()),
+        };
+    }
 
-        // Fall back to the lowest supported rate
-        configs.sort_by_key(|c| c.min_sample_rate().0);
-        if let Some(config) = configs.first() {
-            let rate = config.min_sample_rate();
-            println!("Using minimum sample rate: {}Hz", rate.0);
-            Ok(config.with_sample_rate(rate))
-        } else {
-            Err("Could not find suitable audio configuration".to_string())
-        }
+    if let Some(rate) = spec.sample_rate {
+        return configs
+            .iter()
+            .find(|c| c.min_sample_rate().0 <= rate && rate <= c.max_sample_rate().0)
+            .map(|c| c.with_sample_rate(cpal::SampleRate(rate)))
+            .ok_or_else(|| format!("Device does not support {} Hz", rate));
     }
 
-    fn create_audio_stream(
-        &self,
-        device: cpal::Device,
-        config: cpal::SupportedStreamConfig,
-    ) -> Result<cpal::Stream, String> {
-        let ring = HeapRb::<f32>::new(8192);
-        let (mut producer, mut consumer) = ring.split();
-        let spectrum = self.spectrum.clone();
-        let sample_rate = config.sample_rate().0 as f32;
-
-        std::thread::Builder::new()
-            .name("audio_processing".to_string())
-            .spawn(move || {
-                let mut buffer = Vec::with_capacity(BUFFER_SZ);
-                let mut last_process = Instant::now();
-                let mut analyzer = SpectrumAnalyzer::new(sample_rate);
-                
-                loop {
-                    let now = Instant::now();
-                    if now.duration_since(last_process).as_millis() < 16 {
-                        std::thread::sleep(Duration::from_millis(1));
-                        continue;
-                    }
-                    
-                    while consumer.len() >= BUFFER_SZ {
-                        buffer.clear();
-                        buffer.extend(consumer.pop_iter().take(BUFFER_SZ));
-                        let spectrum_data = analyzer.compute_spectrum(&buffer);
-                        *spectrum.lock() = spectrum_data;
-                    }
-                    
-                    last_process = now;
-                }
+    // Try preferred sample rates
+    for &rate in &SUPPORTED_SAMPLE_RATES {
+        if let Some(config) = configs.iter()
+            .find(|c| {
+                let min_rate = c.min_sample_rate().0;
+                let max_rate = c.max_sample_rate().0;
+                min_rate <= rate && rate <= max_rate
             })
-            .map_err(|e| format!("Failed to spawn audio thread: {}", e))?;
-
-        let gain = if device.name()
-            .map(|n| n.to_lowercase().contains("vb-"))
-            .unwrap_or(false) 
         {
-            2.0
-        } else {
-            1.0
-        };
+            println!("Selected sample rate: {}Hz", rate);
+            return Ok(config.with_sample_rate(cpal::SampleRate(rate)));
+        }
+    }
 
-        device
-            .build_input_stream(
-                &config.into(),
-                move |data: &[f32], _| {
-                    let compensated: Vec<f32> = data.iter()
-                        .map(|&x| x * gain)
-                        .collect();
-                    
-                    if producer.push_slice(&compensated) < compensated.len() {
-                        eprintln!("Buffer overflow");
-                    }
-                },
-                |err| eprintln!("Audio stream error: {err:?}"),
-                None,
-            )
-            .map_err(|e| format!("Failed to build input stream: {}", e))
+    // Fall back to the lowest supported rate
+    configs.sort_by_key(|c| c.min_sample_rate().0);
+    if let Some(config) = configs.first() {
+        let rate = config.min_sample_rate();
+        println!("Using minimum sample rate: {}Hz", rate.0);
+        Ok(config.with_sample_rate(rate))
+    } else {
+        Err("Could not find suitable audio configuration".to_string())
     }
 }
+
+// 样本格式及位深，如 "I16 (16 bit)"
+fn describe_sample_format(format: SampleFormat) -> String {
+    format!("{:?} ({} bit)", format, format.sample_size() * 8)
+}
+
+fn build_input_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut sink: SampleSink,
+    gains: Vec<f32>,
+) -> Result<cpal::Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    // 转换缓冲在回调之间复用，只在块变大时扩容
+    let mut converted: Vec<f32> = Vec::new();
+    let errors = sink.error_reporter();
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| {
+                converted.clear();
+                converted.extend(
+                    data.iter()
+                        .zip(gains.iter().cycle())
+                        .map(|(&x, &gain)| x.to_sample::<f32>() * gain),
+                );
+
+                if !sink.push(&converted) {
+                    eprintln!("Buffer overflow");
+                }
+            },
+            move |err| errors.report(format!("Audio stream error: {}", err)),
+            None,
+        )
+        .map_err(|e| format!("Failed to build input stream: {}", e))
+}
@@ -1,155 +1,442 @@
 use cpal::traits::{DeviceTrait, HostTrait};
-use cpal::{Device, Host};
+use cpal::{Device, Host, HostId, SupportedBufferSize};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use parking_lot::Mutex;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-pub struct AudioDeviceManager {
-    host: Host,
-    devices: Vec<Device>,
+// 界面里提供选择的常用采样率
+const COMMON_SAMPLE_RATES: [u32; 9] = [8000, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 192000];
+// 界面里提供选择的缓冲大小（帧），只列出设备支持范围内的
+const BUFFER_SIZES: [u32; 8] = [64, 128, 256, 512, 1024, 2048, 4096, 8192];
+
+// 声卡驱动提供的回环输入的常见名称
+const LOOPBACK_NAMES: [&str; 3] = ["立体声混音", "stereo mix", "what u hear"];
+
+// 后台重新枚举设备的间隔；cpal 没有热插拔事件，只能定期检查
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+// 设备的稳定标识：主机、名称，以及同名设备中的序号。设备增减后列表编号会变，标识不会
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceId {
+    pub host: HostId,
+    pub name: String,
+    pub ordinal: usize,
 }
 
-// 手动实现 Clone，避免对 Host 的克隆要求
-impl Clone for AudioDeviceManager {
-    fn clone(&self) -> Self {
-        // 重新创建一个新的 Host 实例
-        Self {
-            host: cpal::default_host(),
-            devices: self.devices.clone(),
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.host.name(), self.name)?;
+        if self.ordinal > 0 {
+            write!(f, " #{}", self.ordinal + 1)?;
         }
+        Ok(())
     }
 }
 
+// 一个输入设备支持的流参数，供界面列出可选项
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceDescription {
+    pub id: DeviceId,
+    pub sample_rates: Vec<u32>,
+    pub channels: Vec<u16>,
+    pub buffer_sizes: Vec<u32>,
+    // 设备默认的输入配置
+    pub default_sample_rate: Option<u32>,
+    pub default_channels: Option<u16>,
+}
+
+pub struct AudioDeviceManager {
+    host: Host,
+    devices: Vec<Device>,
+    // 与 devices 一一对应
+    ids: Vec<DeviceId>,
+}
+
 impl AudioDeviceManager {
+    // 枚举失败时得到空列表，不会中断程序
     pub fn new() -> Self {
         let host = cpal::default_host();
-        let devices = Self::enumerate_devices(&host);
-        Self { host, devices }
-    }
-
-    fn enumerate_devices(host: &Host) -> Vec<Device> {
-        println!("\n=== 系统音频设备列表 ===");
-        let devices: Vec<_> = host.devices()
-            .expect("无法枚举音频设备")
-            .filter_map(|device| {
-                if let Ok(name) = device.name() {
-                    // 显示设备详细信息
-                    println!("发现设备: {}", name);
-                    if let Ok(config) = device.default_input_config() {
-                        println!("  采样率: {}Hz", config.sample_rate().0);
-                        println!("  声道数: {}", config.channels());
-                    }
-                    Some(device)
-                } else {
-                    None
+        let devices = Self::enumerate_devices(&host).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Vec::new()
+        });
+        Self::from_devices(host, devices)
+    }
+
+    // 使用指定的音频主机（如 ALSA、JACK、WASAPI、ASIO）
+    pub fn with_host(id: HostId) -> Result<Self, String> {
+        let host = cpal::host_from_id(id).map_err(|e| format!("Audio host {} unavailable: {}", id.name(), e))?;
+        let devices = Self::enumerate_devices(&host)?;
+        Ok(Self::from_devices(host, devices))
+    }
+
+    fn from_devices(host: Host, devices: Vec<Device>) -> Self {
+        let mut ids: Vec<DeviceId> = Vec::with_capacity(devices.len());
+        for device in &devices {
+            let name = device.name().unwrap_or_default();
+            let ordinal = ids.iter().filter(|id| id.name == name).count();
+            ids.push(DeviceId { host: host.id(), name, ordinal });
+        }
+        Self { host, devices, ids }
+    }
+
+    pub fn host_id(&self) -> HostId {
+        self.host.id()
+    }
+
+    // 本机可用的音频主机
+    pub fn available_hosts() -> Vec<HostId> {
+        cpal::available_hosts()
+    }
+
+    // 列出所有支持输入的设备及其可选的采样率、声道数和缓冲大小
+    pub fn describe_input_devices(&self) -> Vec<DeviceDescription> {
+        self.devices
+            .iter()
+            .zip(&self.ids)
+            .filter_map(|(device, id)| {
+                let configs: Vec<_> = device.supported_input_configs().ok()?.collect();
+                if configs.is_empty() {
+                    return None;
                 }
+                let sample_rates = COMMON_SAMPLE_RATES
+                    .iter()
+                    .copied()
+                    .filter(|&rate| {
+                        configs
+                            .iter()
+                            .any(|c| c.min_sample_rate().0 <= rate && rate <= c.max_sample_rate().0)
+                    })
+                    .collect();
+                let mut channels: Vec<u16> = configs.iter().map(|c| c.channels()).collect();
+                channels.sort_unstable();
+                channels.dedup();
+                let buffer_sizes = BUFFER_SIZES
+                    .iter()
+                    .copied()
+                    .filter(|&size| {
+                        configs.iter().any(|c| match *c.buffer_size() {
+                            SupportedBufferSize::Range { min, max } => min <= size && size <= max,
+                            SupportedBufferSize::Unknown => true,
+                        })
+                    })
+                    .collect();
+                let default = device.default_input_config().ok();
+                Some(DeviceDescription {
+                    id: id.clone(),
+                    sample_rates,
+                    channels,
+                    buffer_sizes,
+                    default_sample_rate: default.as_ref().map(|c| c.sample_rate().0),
+                    default_channels: default.as_ref().map(|c| c.channels()),
+                })
             })
+            .collect()
+    }
+
+    // 只保留能取到名称的设备；后台会定期调用，这里不打印
+    fn enumerate_devices(host: &Host) -> Result<Vec<Device>, String> {
+        let devices = host
+            .devices()
+            .map_err(|e| format!("无法枚举音频设备: {}", e))?
+            .filter(|device| device.name().is_ok())
             .collect();
+        Ok(devices)
+    }
 
-        if devices.is_empty() {
-            println!("警告: 未找到任何音频设备!");
-        }
-        devices
+    pub fn device_ids(&self) -> &[DeviceId] {
+        &self.ids
     }
 
-    pub fn find_loopback_device(&self) -> Option<Device> {
-        println!("\n=== 检测系统音频设备 ===");
+    // 第一个支持输入并满足条件的设备
+    pub fn find_input_device(&self, accept: impl Fn(&DeviceId, &Device) -> bool) -> Option<Device> {
+        self.devices
+            .iter()
+            .zip(&self.ids)
+            .find(|(device, id)| supports_input(device, None, None) && accept(id, device))
+            .map(|(device, _)| device.clone())
+    }
 
-        // 1. 首先尝试查找 VB-Cable 虚拟设备
-        let vb_device = self.devices.iter().find(|device| {
-            let name = device.name().unwrap_or_default().to_lowercase();
-            name.contains("vb-audio") || 
-            name.contains("cable input") ||
-            name.contains("voicemeeter")
-        });
+    // 主机的默认输入设备，没有时使用第一个支持输入的设备
+    pub fn get_default_device(&self) -> Option<Device> {
+        self.host
+            .default_input_device()
+            .or_else(|| self.find_input_device(|_, _| true))
+    }
 
-        if let Some(device) = vb_device {
-            println!("找到虚拟音频设备: {}", device.name().unwrap_or_default());
-            return Some(device.clone());
+    // 捕获系统输出的设备
+    pub fn find_loopback_device(&self) -> Result<Device, String> {
+        // WASAPI 可以在输出设备上建立输入流
+        #[cfg(target_os = "windows")]
+        if self.host.id() == HostId::Wasapi {
+            if let Some(output) = self.host.default_output_device() {
+                return Ok(output);
+            }
         }
 
-        // 2. 尝试使用 WASAPI 环回捕获
-        #[cfg(target_os = "windows")]
+        // PulseAudio/PipeWire 的 monitor 源：cpal 的 ALSA 主机只列出 ALSA PCM，不列出 pulse 的源，
+        // 需要用户在 ~/.asoundrc 里定义指向 monitor 源的 pulse PCM。先找名称就是默认输出 monitor 的设备，
+        // 再找名称里带 monitor 的设备。不通过 PULSE_SOURCE 环境变量改变 pulse 设备，其他线程会同时读取环境
+        #[cfg(target_os = "linux")]
         {
-            if let Some(output) = self.host.default_output_device() {
-                if let Ok(configs) = output.supported_input_configs() {
-                    if configs.count() > 0 {
-                        println!("使用 WASAPI 环回捕获: {}", 
-                                output.name().unwrap_or_default());
-                        return Some(output);
-                    }
-                }
+            let device = default_monitor_source()
+                .ok()
+                .and_then(|monitor| self.find_input_device(|id, _| id.name == monitor))
+                .or_else(|| self.find_input_device(|id, _| id.name.to_lowercase().contains("monitor")));
+            if let Some(device) = device {
+                return Ok(device);
             }
         }
 
-        // 3. 查找其他回环设备
-        let loopback = self.devices.iter().find(|device| {
-            let name = device.name().unwrap_or_default().to_lowercase();
-            name.contains("立体声混音") || 
-            name.contains("stereo mix") || 
-            name.contains("what u hear")
-        });
+        // 声卡驱动自带的回环输入
+        self.find_input_device(|id, _| {
+            let name = id.name.to_lowercase();
+            LOOPBACK_NAMES.iter().any(|pattern| name.contains(pattern))
+        })
+        .ok_or_else(loopback_missing)
+    }
 
-        if let Some(device) = loopback {
-            println!("找到系统回环设备: {}", device.name().unwrap_or_default());
-            return Some(device.clone());
-        }
+    pub fn get_device_by_index(&self, index: usize) -> Option<Device> {
+        self.devices.get(index).cloned()
+    }
 
-        println!("未找到专用回环设备，使用默认输入设备");
-        None
+    // 先找完全相同的标识；同名设备的序号变了时退而找同名的第一个
+    pub fn get_device_by_id(&self, id: &DeviceId) -> Option<Device> {
+        let position = self
+            .ids
+            .iter()
+            .position(|candidate| candidate == id)
+            .or_else(|| self.ids.iter().position(|candidate| candidate.name == id.name))?;
+        self.devices.get(position).cloned()
     }
 
-    pub fn get_default_device(&self) -> Device {
-        // 获取所有支持音频输入的设备
-        let available_devices: Vec<_> = self.devices.iter()
-            .filter(|device| {
-                if let Ok(configs) = device.supported_input_configs() {
-                    if configs.count() > 0 {
-                        println!("设备 {} 支持音频输入", device.name().unwrap_or_default());
-                        return true;
-                    }
-                }
-                false
-            })
-            .cloned()
-            .collect();
+    pub fn print_device_list(&self) {
+        println!("\n=== 可用音频设备列表 ===");
+        for (idx, (device, id)) in self.devices.iter().zip(&self.ids).enumerate() {
+            println!("[{}] {}", idx, id);
+            if let Ok(config) = device.default_input_config() {
+                println!("    {} Hz, {} ch", config.sample_rate().0, config.channels());
+            }
+        }
+        if self.devices.is_empty() {
+            println!("警告: 未找到任何音频设备!");
+        }
+        println!("\n在界面的音频源面板或用 --source 编号 选择设备");
+    }
+}
 
-        // 按优先级查找设备
-        available_devices.iter()
-            // 1. 查找 VB-Cable
-            .find(|device| {
-                device.name()
-                    .map(|n| n.to_lowercase().contains("vb-"))
-                    .unwrap_or(false)
-            })
-            // 2. 查找立体声混音
-            .or_else(|| {
-                available_devices.iter().find(|device| {
-                    device.name()
-                        .map(|n| n.to_lowercase().contains("立体声混音"))
-                        .unwrap_or(false)
-                })
+// 设备是否支持输入，以及指定的声道数和采样率
+pub fn supports_input(device: &Device, channels: Option<u16>, sample_rate: Option<u32>) -> bool {
+    device
+        .supported_input_configs()
+        .map(|mut configs| {
+            configs.any(|c| {
+                channels.is_none_or(|n| c.channels() == n)
+                    && sample_rate.is_none_or(|r| c.min_sample_rate().0 <= r && r <= c.max_sample_rate().0)
             })
-            // 3. 使用第一个可用设备
-            .cloned()
-            .or_else(|| available_devices.first().cloned())
-            .expect("未找到任何可用的音频设备")
+        })
+        .unwrap_or(false)
+}
+
+// 找不到回环设备时的说明；Linux 上告诉用户如何把 monitor 源定义成 ALSA 设备
+#[cfg(target_os = "linux")]
+fn loopback_missing() -> String {
+    let monitor = default_monitor_source().unwrap_or_else(|_| "<默认输出>.monitor".to_string());
+    format!(
+        "未找到回环设备: 在 ~/.asoundrc 中加入 pcm.monitor {{ type pulse; device \"{}\"; hint {{ show on; description \"monitor\" }} }} \
+         （需要 PulseAudio 或 pipewire-pulse 以及 ALSA 的 pulse 插件）",
+        monitor
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn loopback_missing() -> String {
+    "No loopback device found".to_string()
+}
+
+// 默认输出（sink）对应的 monitor 源名称
+#[cfg(target_os = "linux")]
+fn default_monitor_source() -> Result<String, String> {
+    let pactl = |args: &[&str]| -> Option<String> {
+        let output = std::process::Command::new("pactl").args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    // 较旧的 pactl 没有 get-default-sink，从 info 里读取
+    let sink = pactl(&["get-default-sink"])
+        .map(|text| text.trim().to_string())
+        .filter(|sink| !sink.is_empty())
+        .or_else(|| {
+            pactl(&["info"])?
+                .lines()
+                .find_map(|line| line.strip_prefix("Default Sink:"))
+                .map(|sink| sink.trim().to_string())
+        })
+        .ok_or_else(|| "无法通过 pactl 取得默认输出，需要 PulseAudio 或 PipeWire (pipewire-pulse)".to_string())?;
+    Ok(format!("{}.monitor", sink))
+}
+
+// 设备列表的变化
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceEvent {
+    Added(DeviceId),
+    Removed(DeviceId),
+}
+
+struct MonitorState {
+    host: Option<HostId>,
+    devices: Vec<DeviceDescription>,
+    error: Option<String>,
+    // 每次列表变化加一，界面据此判断是否需要重新取列表
+    generation: u64,
+    // 要求立即重新枚举
+    rescan: bool,
+}
+
+// 在后台线程里定期重新枚举所选主机的设备，列表变化时发出通知
+pub struct DeviceMonitor {
+    state: Arc<Mutex<MonitorState>>,
+    events: Receiver<DeviceEvent>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DeviceMonitor {
+    // host 为 None 时使用系统默认的音频主机
+    pub fn start(host: Option<HostId>) -> Self {
+        let state = Arc::new(Mutex::new(MonitorState {
+            host,
+            devices: Vec::new(),
+            error: None,
+            generation: 0,
+            rescan: true,
+        }));
+        let (sender, events) = unbounded();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let state = state.clone();
+            let shutdown = shutdown.clone();
+            std::thread::Builder::new()
+                .name("device_monitor".to_string())
+                .spawn(move || monitor(&state, &sender, &shutdown))
+                .map_err(|e| eprintln!("Failed to spawn device monitor: {}", e))
+                .ok()
+        };
+        Self { state, events, shutdown, thread }
     }
 
-    pub fn list_devices(&self) -> Vec<(usize, String)> {
-        self.devices.iter().enumerate()
-            .filter_map(|(idx, device)| {
-                device.name().ok().map(|name| (idx, name))
-            })
-            .collect()
+    pub fn host(&self) -> Option<HostId> {
+        self.state.lock().host
     }
 
-    pub fn get_device_by_index(&self, index: usize) -> Option<Device> {
-        self.devices.get(index).cloned()
+    // 换主机后立即重新枚举
+    pub fn set_host(&self, host: HostId) {
+        let mut state = self.state.lock();
+        if state.host != Some(host) {
+            state.host = Some(host);
+            state.rescan = true;
+        }
     }
 
-    pub fn print_device_list(&self) {
-        println!("\n=== 可用音频设备列表 ===");
-        for (idx, name) in self.list_devices() {
-            println!("[{}] {}", idx, name);
+    pub fn rescan(&self) {
+        self.state.lock().rescan = true;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.state.lock().generation
+    }
+
+    pub fn devices(&self) -> Vec<DeviceDescription> {
+        self.state.lock().devices.clone()
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.state.lock().error.clone()
+    }
+
+    // 取出上次调用之后的所有变化
+    pub fn events(&self) -> impl Iterator<Item = DeviceEvent> + '_ {
+        self.events.try_iter()
+    }
+}
+
+impl Drop for DeviceMonitor {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// 只在设备标识变化时才重新查询各设备支持的配置，查询可能较慢
+fn monitor(state: &Mutex<MonitorState>, events: &Sender<DeviceEvent>, shutdown: &AtomicBool) {
+    let mut known: Option<(HostId, Vec<DeviceId>)> = None;
+    let mut last_scan: Option<Instant> = None;
+
+    while !shutdown.load(Ordering::Relaxed) {
+        let host = {
+            let mut state = state.lock();
+            let due = state.rescan || last_scan.is_none_or(|t| t.elapsed() >= RESCAN_INTERVAL);
+            if !due {
+                drop(state);
+                std::thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            state.rescan = false;
+            state.host
+        };
+        last_scan = Some(Instant::now());
+
+        let manager = match host {
+            Some(id) => AudioDeviceManager::with_host(id),
+            None => Ok(AudioDeviceManager::new()),
+        };
+        let manager = match manager {
+            Ok(manager) => manager,
+            Err(e) => {
+                state.lock().error = Some(e);
+                continue;
+            }
+        };
+        let host = manager.host_id();
+        // 枚举成功就清除上次的错误，列表没变时也一样
+        {
+            let mut state = state.lock();
+            if state.host.is_none_or(|h| h == host) {
+                state.error = None;
+            }
+        }
+        let ids = manager.device_ids().to_vec();
+        if known.as_ref().is_some_and(|(h, k)| *h == host && *k == ids) {
+            continue;
+        }
+
+        // 同一主机下的增减才发通知，换主机只更新列表
+        if let Some((previous_host, previous)) = &known {
+            if *previous_host == host {
+                for id in previous.iter().filter(|id| !ids.contains(id)) {
+                    let _ = events.send(DeviceEvent::Removed(id.clone()));
+                }
+                for id in ids.iter().filter(|id| !previous.contains(id)) {
+                    let _ = events.send(DeviceEvent::Added(id.clone()));
+                }
+            }
+        }
+        let devices = manager.describe_input_devices();
+        {
+            let mut state = state.lock();
+            // 枚举期间界面可能换了主机，结果作废，下一轮重新枚举
+            if state.host.is_some_and(|h| h != host) {
+                continue;
+            }
+            state.host = Some(host);
+            state.devices = devices;
+            state.generation += 1;
         }
-        println!("\n输入设备编号以切换设备，输入其他内容继续使用当前设备");
+        known = Some((host, ids));
     }
 }
@@ -0,0 +1,521 @@
+use parking_lot::Mutex;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::source::{AudioSource, SourceInfo};
+use crate::spectrum::SampleSink;
+
+// 每次写入分析缓冲的最大帧数
+const CHUNK_FRAMES: usize = 1024;
+
+// 按块解码的音频文件：播放时只解码当前要送出的一段，不把整个文件读进内存。
+// 样本为交错的归一化f32
+pub struct AudioFile {
+    pub sample_rate: u32,
+    pub channels: usize,
+    // 原始编码格式与位深，仅用于显示
+    pub format: String,
+    path: PathBuf,
+    frames: usize,
+    // 下一次读取的位置（帧）
+    position: usize,
+    // 整数样本的归一化系数，满量程对应 ±1
+    scale: f32,
+    decoder: Decoder,
+}
+
+enum Decoder {
+    Wav(hound::WavReader<BufReader<File>>),
+    // FLAC 一次解码一整块，offset 是块内下一个未读的帧
+    Flac {
+        reader: claxon::FlacReader<File>,
+        block: claxon::Block,
+        offset: u32,
+    },
+}
+
+impl AudioFile {
+    // 按扩展名选择解码器，支持 WAV（PCM 16/24/32 位整数、32 位浮点）和 FLAC
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "wav" | "wave" => Self::open_wav(path),
+            "flac" => Self::open_flac(path),
+            _ => Err(format!("Unsupported audio file: {}", path.display())),
+        }
+    }
+
+    fn open_wav(path: &Path) -> Result<Self, String> {
+        let reader = hound::WavReader::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let spec = reader.spec();
+        let (scale, format) = match spec.sample_format {
+            hound::SampleFormat::Float => (1.0, format!("WAV float ({} bit)", spec.bits_per_sample)),
+            hound::SampleFormat::Int => (
+                1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32,
+                format!("WAV PCM ({} bit)", spec.bits_per_sample),
+            ),
+        };
+        Ok(Self {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels as usize,
+            format,
+            path: path.to_path_buf(),
+            frames: reader.duration() as usize,
+            position: 0,
+            scale,
+            decoder: Decoder::Wav(reader),
+        })
+    }
+
+    fn open_flac(path: &Path) -> Result<Self, String> {
+        let mut reader = claxon::FlacReader::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let info = reader.streaminfo();
+        let frames = match info.samples {
+            Some(samples) => samples as usize,
+            // 头里没有总长度时先完整解码一遍计数，之后重新打开
+            None => {
+                let mut frames = 0;
+                let mut blocks = reader.blocks();
+                let mut buffer = Vec::new();
+                while let Some(block) = blocks
+                    .read_next_or_eof(buffer)
+                    .map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?
+                {
+                    frames += block.duration() as usize;
+                    buffer = block.into_buffer();
+                }
+                reader = claxon::FlacReader::open(path)
+                    .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+                frames
+            }
+        };
+
+        Ok(Self {
+            sample_rate: info.sample_rate,
+            channels: info.channels as usize,
+            format: format!("FLAC ({} bit)", info.bits_per_sample),
+            path: path.to_path_buf(),
+            frames,
+            position: 0,
+            scale: 1.0 / (1u64 << (info.bits_per_sample - 1)) as f32,
+            decoder: Decoder::Flac { reader, block: claxon::Block::empty(), offset: 0 },
+        })
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    // 从当前位置起最多读取 frames 帧到 out（先清空），返回实际读到的帧数，0 表示已到结尾
+    pub fn read(&mut self, out: &mut Vec<f32>, frames: usize) -> Result<usize, String> {
+        out.clear();
+        let wanted = frames * self.channels;
+        let scale = self.scale;
+        match &mut self.decoder {
+            Decoder::Wav(reader) => {
+                if reader.spec().sample_format == hound::SampleFormat::Float {
+                    for sample in reader.samples::<f32>().take(wanted) {
+                        out.push(sample.map_err(|e| format!("Failed to decode {}: {}", self.path.display(), e))?);
+                    }
+                } else {
+                    for sample in reader.samples::<i32>().take(wanted) {
+                        let sample = sample.map_err(|e| format!("Failed to decode {}: {}", self.path.display(), e))?;
+                        out.push(sample as f32 * scale);
+                    }
+                }
+            }
+            Decoder::Flac { reader, block, offset } => {
+                while out.len() < wanted {
+                    if *offset >= block.duration() {
+                        let buffer = std::mem::replace(block, claxon::Block::empty()).into_buffer();
+                        match reader
+                            .blocks()
+                            .read_next_or_eof(buffer)
+                            .map_err(|e| format!("Failed to decode {}: {}", self.path.display(), e))?
+                        {
+                            Some(next) => *block = next,
+                            None => break,
+                        }
+                        *offset = 0;
+                    }
+                    let end = block.duration().min(*offset + ((wanted - out.len()) / self.channels) as u32);
+                    for frame in *offset..end {
+                        for channel in 0..block.channels() {
+                            out.push(block.sample(channel, frame) as f32 * scale);
+                        }
+                    }
+                    *offset = end;
+                }
+            }
+        }
+        let read = out.len() / self.channels.max(1);
+        self.position += read;
+        Ok(read)
+    }
+
+    pub fn seek(&mut self, frame: usize) -> Result<(), String> {
+        let frame = frame.min(self.frames);
+        if let Decoder::Wav(reader) = &mut self.decoder {
+            reader
+                .seek(frame as u32)
+                .map_err(|e| format!("Failed to seek {}: {}", self.path.display(), e))?;
+            self.position = frame;
+            return Ok(());
+        }
+        // FLAC 没有随机访问，向前跳转要从头重新解码
+        if frame < self.position {
+            let path = self.path.clone();
+            *self = Self::open_flac(&path)?;
+        }
+        let mut skipped = Vec::new();
+        while self.position < frame {
+            if self.read(&mut skipped, (frame - self.position).min(CHUNK_FRAMES))? == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct TransportState {
+    // 当前播放位置（帧）
+    position: usize,
+    frames: usize,
+    sample_rate: u32,
+    playing: bool,
+    looping: bool,
+    // true 按采样率实时播放，false 尽快送入分析
+    realtime: bool,
+    // 不循环时播放到结尾后置位
+    finished: bool,
+}
+
+// 文件播放的控制句柄，界面与播放线程共享
+#[derive(Clone)]
+pub struct Transport {
+    state: Arc<Mutex<TransportState>>,
+}
+
+impl Transport {
+    fn new(frames: usize, sample_rate: u32, realtime: bool) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TransportState {
+                position: 0,
+                frames,
+                sample_rate,
+                playing: true,
+                looping: false,
+                realtime,
+                finished: false,
+            })),
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state.lock().playing
+    }
+
+    pub fn set_playing(&self, playing: bool) {
+        let mut state = self.state.lock();
+        // 播放完后再次播放从头开始
+        if playing && state.finished {
+            state.position = 0;
+            state.finished = false;
+        }
+        state.playing = playing;
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.state.lock().looping
+    }
+
+    pub fn set_looping(&self, looping: bool) {
+        self.state.lock().looping = looping;
+    }
+
+    pub fn is_realtime(&self) -> bool {
+        self.state.lock().realtime
+    }
+
+    pub fn set_realtime(&self, realtime: bool) {
+        self.state.lock().realtime = realtime;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().finished
+    }
+
+    pub fn position_secs(&self) -> f32 {
+        let state = self.state.lock();
+        state.position as f32 / state.sample_rate as f32
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        let state = self.state.lock();
+        state.frames as f32 / state.sample_rate as f32
+    }
+
+    pub fn seek(&self, seconds: f32) {
+        let mut state = self.state.lock();
+        let frame = (seconds.max(0.0) * state.sample_rate as f32) as usize;
+        state.position = frame.min(state.frames);
+        state.finished = false;
+    }
+}
+
+// 音频文件输入：在单独的线程里边解码边按实时或最快速度送进分析流水线
+pub struct FileSource {
+    file: Arc<Mutex<AudioFile>>,
+    info: SourceInfo,
+    transport: Transport,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FileSource {
+    pub fn open(path: &Path, realtime: bool) -> Result<Self, String> {
+        let file = AudioFile::open(path)?;
+        if file.channels == 0 || file.sample_rate == 0 {
+            return Err(format!("Invalid audio file: {}", path.display()));
+        }
+        let info = SourceInfo {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            sample_rate: file.sample_rate,
+            channels: file.channels,
+            format: file.format.clone(),
+        };
+        let transport = Transport::new(file.frames(), file.sample_rate, realtime);
+        println!(
+            "Opened {}: {} Hz, {} ch, {}, {:.1} s",
+            path.display(),
+            info.sample_rate,
+            info.channels,
+            info.format,
+            transport.duration_secs()
+        );
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+            info,
+            transport,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        })
+    }
+}
+
+impl AudioSource for FileSource {
+    fn info(&self) -> &SourceInfo {
+        &self.info
+    }
+
+    fn start(&mut self, sink: SampleSink) -> Result<(), String> {
+        self.stop();
+        self.shutdown.store(false, Ordering::Relaxed);
+        let file = self.file.clone();
+        let transport = self.transport.clone();
+        let shutdown = self.shutdown.clone();
+        let thread = std::thread::Builder::new()
+            .name("file_playback".to_string())
+            .spawn(move || play(&file, &transport, sink, &shutdown))
+            .map_err(|e| format!("Failed to spawn playback thread: {}", e))?;
+        self.thread = Some(thread);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    fn transport(&self) -> Option<Transport> {
+        Some(self.transport.clone())
+    }
+}
+
+impl Drop for FileSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// 播放线程：实时模式按墙钟计算应送出的帧数，最快模式只受分析缓冲剩余空间限制
+fn play(file: &Mutex<AudioFile>, transport: &Transport, mut sink: SampleSink, shutdown: &AtomicBool) {
+    let mut file = file.lock();
+    let channels = file.channels;
+    let mut buffer = Vec::with_capacity(CHUNK_FRAMES * channels);
+    // 实时模式的时钟起点，暂停、跳转或切换模式后重新计时
+    let mut clock: Option<(Instant, usize)> = None;
+    // 上次送出之后应处的位置，不一致说明发生了跳转
+    let mut expected = None;
+
+    while !shutdown.load(Ordering::Relaxed) {
+        let (start, count) = {
+            let mut state = transport.state.lock();
+            if !state.playing || state.finished {
+                clock = None;
+                drop(state);
+                std::thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+            if state.position >= state.frames {
+                if state.looping && state.frames > 0 {
+                    state.position = 0;
+                } else {
+                    state.finished = true;
+                    state.playing = false;
+                    continue;
+                }
+            }
+
+            let mut count = (state.frames - state.position)
+                .min(CHUNK_FRAMES)
+                .min(sink.free_len() / channels);
+            if expected != Some(state.position) || !state.realtime {
+                clock = None;
+            }
+            if state.realtime {
+                let (origin, origin_position) = *clock.get_or_insert((Instant::now(), state.position));
+                let due = origin_position + (origin.elapsed().as_secs_f64() * file.sample_rate as f64) as usize;
+                count = count.min(due.saturating_sub(state.position));
+            }
+            let start = state.position;
+            state.position += count;
+            expected = Some(state.position);
+            (start, count)
+        };
+
+        if count == 0 {
+            std::thread::sleep(Duration::from_millis(2));
+            continue;
+        }
+        let read = if file.position() == start { Ok(()) } else { file.seek(start) }
+            .and_then(|()| file.read(&mut buffer, count));
+        match read {
+            Ok(read) => {
+                sink.push(&buffer);
+                // 文件比头里记录的短，以实际解码到的位置为结尾
+                if read < count {
+                    let mut state = transport.state.lock();
+                    state.frames = start + read;
+                    state.position = state.position.min(state.frames);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                let mut state = transport.state.lock();
+                state.finished = true;
+                state.playing = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufWriter;
+
+    // 用 hound 写一个测试文件，返回路径
+    fn write_wav(name: &str, spec: hound::WavSpec, write: impl FnOnce(&mut hound::WavWriter<BufWriter<File>>)) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}.wav", name, std::process::id()));
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        write(&mut writer);
+        writer.finalize().unwrap();
+        path
+    }
+
+    fn read_all(path: &Path) -> Vec<f32> {
+        let mut file = AudioFile::open(path).unwrap();
+        let mut samples = Vec::new();
+        file.read(&mut samples, file.frames()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        samples
+    }
+
+    #[test]
+    fn test_pcm_full_scale() {
+        for bits in [16u16, 24, 32] {
+            let spec = hound::WavSpec {
+                channels: 2,
+                sample_rate: 48000,
+                bits_per_sample: bits,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let max = ((1i64 << (bits - 1)) - 1) as i32;
+            let path = write_wav(&format!("pcm{}", bits), spec, |writer| {
+                for sample in [max, -max - 1, 0, 1 << (bits - 2)] {
+                    writer.write_sample(sample).unwrap();
+                }
+            });
+            let samples = read_all(&path);
+            assert_eq!(samples.len(), 4);
+            assert!((samples[0] - 1.0).abs() < 1e-4, "{} bit: {:?}", bits, samples);
+            assert_eq!(samples[1..], [-1.0, 0.0, 0.5], "{} bit", bits);
+        }
+    }
+
+    #[test]
+    fn test_float_full_scale() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 44100,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let path = write_wav("float", spec, |writer| {
+            for sample in [1.0f32, -1.0, 0.25] {
+                writer.write_sample(sample).unwrap();
+            }
+        });
+        assert_eq!(read_all(&path), vec![1.0, -1.0, 0.25]);
+    }
+
+    #[test]
+    fn test_chunked_read_and_seek() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let path = write_wav("seek", spec, |writer| {
+            for sample in 0..2000i16 {
+                writer.write_sample(sample).unwrap();
+            }
+        });
+        let mut file = AudioFile::open(&path).unwrap();
+        assert_eq!(file.frames(), 1000);
+
+        let mut chunk = Vec::new();
+        assert_eq!(file.read(&mut chunk, 300).unwrap(), 300);
+        assert_eq!(chunk[598..], [598.0 / 32768.0, 599.0 / 32768.0]);
+        file.seek(900).unwrap();
+        assert_eq!(file.read(&mut chunk, 300).unwrap(), 100);
+        assert_eq!(chunk[0], 1800.0 / 32768.0);
+        assert_eq!(file.read(&mut chunk, 300).unwrap(), 0);
+        file.seek(0).unwrap();
+        assert_eq!(file.read(&mut chunk, 1).unwrap(), 1);
+        assert_eq!(chunk, [0.0, 1.0 / 32768.0]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
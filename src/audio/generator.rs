@@ -0,0 +1,259 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use myalgorithm::{SignalGenerator, Waveform};
+
+use super::source::{AudioSource, SourceInfo};
+use crate::spectrum::SampleSink;
+
+// 每次写入分析缓冲的最大帧数
+const CHUNK_FRAMES: usize = 1024;
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+const DEFAULT_LEVEL_DBFS: f32 = -20.0;
+
+// 发生器的波形与电平，命令行写法见 parse
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorSpec {
+    pub waveform: Waveform,
+    pub level_dbfs: f32,
+    // 作为分析输入时的采样率，作为输出时使用设备的采样率
+    pub sample_rate: u32,
+}
+
+impl GeneratorSpec {
+    // 波形[:参数][@电平dBFS]，如 sine:1000@-6、multitone:100,1000,5000、square:440、saw:440、
+    // white、pink、sweep:20:20000:5（起止频率与时长秒）、impulse:10（每秒脉冲数）
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (body, level) = match text.trim().split_once('@') {
+            Some((body, level)) => (
+                body,
+                level
+                    .trim_end_matches("dBFS")
+                    .trim()
+                    .parse::<f32>()
+                    .map_err(|_| format!("Invalid generator level: {}", level))?,
+            ),
+            None => (text.trim(), DEFAULT_LEVEL_DBFS),
+        };
+        let mut parts = body.split(':');
+        let kind = parts.next().unwrap_or_default().to_lowercase();
+        let params: Vec<&str> = parts.collect();
+        let number = |index: usize, default: f32| -> Result<f32, String> {
+            match params.get(index) {
+                Some(value) => value
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|v| *v > 0.0)
+                    .ok_or_else(|| format!("Invalid generator parameter: {}", value)),
+                None => Ok(default),
+            }
+        };
+
+        let waveform = match kind.as_str() {
+            "sine" => Waveform::Sine { freq: number(0, 1000.0)? },
+            "multitone" => {
+                let freqs = params
+                    .first()
+                    .unwrap_or(&"100,1000,10000")
+                    .split(',')
+                    .map(|f| f.trim().parse::<f32>().ok().filter(|v| *v > 0.0))
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|freqs| !freqs.is_empty())
+                    .ok_or_else(|| format!("Invalid multi-tone frequencies: {}", body))?;
+                Waveform::MultiTone { freqs }
+            }
+            "square" => Waveform::Square { freq: number(0, 1000.0)? },
+            "saw" | "sawtooth" => Waveform::Sawtooth { freq: number(0, 1000.0)? },
+            "white" => Waveform::WhiteNoise,
+            "pink" => Waveform::PinkNoise,
+            "sweep" => {
+                let (start, end) = (number(0, 20.0)?, number(1, 20000.0)?);
+                let nyquist = DEFAULT_SAMPLE_RATE as f32 / 2.0;
+                if start == end {
+                    return Err(format!("Sweep start and end frequencies must differ: {}", body));
+                }
+                if start.max(end) >= nyquist {
+                    return Err(format!("Sweep frequencies must be below {} Hz: {}", nyquist, body));
+                }
+                Waveform::LogSweep { start, end, duration: number(2, 5.0)? }
+            }
+            "impulse" => Waveform::Impulse { rate: number(0, 1.0)? },
+            other => return Err(format!("Unknown generator waveform: {}", other)),
+        };
+        Ok(Self { waveform, level_dbfs: level, sample_rate: DEFAULT_SAMPLE_RATE })
+    }
+
+    fn generator(&self, sample_rate: u32) -> SignalGenerator {
+        SignalGenerator::new(self.waveform.clone(), self.level_dbfs, sample_rate as f32)
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} {:.1} dBFS", self.waveform.name(), self.level_dbfs)
+    }
+}
+
+// 信号发生器作为分析输入：不需要硬件即可自检整个分析链路
+pub struct GeneratorSource {
+    spec: GeneratorSpec,
+    info: SourceInfo,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GeneratorSource {
+    pub fn new(spec: GeneratorSpec) -> Self {
+        let info = SourceInfo {
+            name: format!("Generator ({})", spec.describe()),
+            sample_rate: spec.sample_rate,
+            channels: 1,
+            format: "Generator (32 bit float)".to_string(),
+        };
+        Self {
+            spec,
+            info,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+}
+
+impl AudioSource for GeneratorSource {
+    fn info(&self) -> &SourceInfo {
+        &self.info
+    }
+
+    fn start(&mut self, sink: SampleSink) -> Result<(), String> {
+        self.stop();
+        self.shutdown.store(false, Ordering::Relaxed);
+        let generator = self.spec.generator(self.info.sample_rate);
+        let sample_rate = self.info.sample_rate;
+        let shutdown = self.shutdown.clone();
+        let thread = std::thread::Builder::new()
+            .name("signal_generator".to_string())
+            .spawn(move || generate(generator, sample_rate, sink, &shutdown))
+            .map_err(|e| format!("Failed to spawn generator thread: {}", e))?;
+        self.thread = Some(thread);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for GeneratorSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// 按墙钟计算应生成的样本数，模拟实时输入
+fn generate(mut generator: SignalGenerator, sample_rate: u32, mut sink: SampleSink, shutdown: &AtomicBool) {
+    let mut buffer = vec![0.0; CHUNK_FRAMES];
+    let origin = Instant::now();
+    let mut produced = 0usize;
+
+    while !shutdown.load(Ordering::Relaxed) {
+        let due = (origin.elapsed().as_secs_f64() * sample_rate as f64) as usize;
+        let count = due.saturating_sub(produced).min(CHUNK_FRAMES);
+        if count == 0 {
+            std::thread::sleep(Duration::from_millis(2));
+            continue;
+        }
+        generator.fill(&mut buffer[..count]);
+        // 分析跟不上时丢弃，保持和实时输入一样的行为
+        if !sink.push(&buffer[..count]) {
+            eprintln!("Buffer overflow");
+        }
+        produced += count;
+    }
+}
+
+// 通过默认输出设备播放激励信号，所有声道输出相同的信号；返回的流释放后停止播放
+#[cfg(feature = "cpal")]
+pub fn play_output(spec: &GeneratorSpec) -> Result<cpal::Stream, String> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::SampleFormat;
+
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| "No output device available".to_string())?;
+    let config = device
+        .default_output_config()
+        .map_err(|e| format!("Failed to get output config: {}", e))?;
+    let generator = spec.generator(config.sample_rate().0);
+    let stream_config: cpal::StreamConfig = config.clone().into();
+    let stream = match config.sample_format() {
+        SampleFormat::I8 => build_output_stream::<i8>(&device, &stream_config, generator),
+        SampleFormat::I16 => build_output_stream::<i16>(&device, &stream_config, generator),
+        SampleFormat::I32 => build_output_stream::<i32>(&device, &stream_config, generator),
+        SampleFormat::U8 => build_output_stream::<u8>(&device, &stream_config, generator),
+        SampleFormat::U16 => build_output_stream::<u16>(&device, &stream_config, generator),
+        SampleFormat::U32 => build_output_stream::<u32>(&device, &stream_config, generator),
+        SampleFormat::F32 => build_output_stream::<f32>(&device, &stream_config, generator),
+        SampleFormat::F64 => build_output_stream::<f64>(&device, &stream_config, generator),
+        other => return Err(format!("Unsupported sample format: {:?}", other)),
+    }?;
+    stream.play().map_err(|e| format!("Failed to start output stream: {}", e))?;
+    println!(
+        "Playing {} on {} ({} Hz, {} ch)",
+        spec.describe(),
+        device.name().unwrap_or_default(),
+        stream_config.sample_rate.0,
+        stream_config.channels
+    );
+    Ok(stream)
+}
+
+#[cfg(feature = "cpal")]
+fn build_output_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut generator: SignalGenerator,
+) -> Result<cpal::Stream, String>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    use cpal::traits::DeviceTrait;
+
+    let channels = config.channels.max(1) as usize;
+    // 单声道缓冲在回调之间复用，只在块变大时扩容
+    let mut mono: Vec<f32> = Vec::new();
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                mono.resize(data.len() / channels, 0.0);
+                generator.fill(&mut mono);
+                for (frame, &value) in data.chunks_mut(channels).zip(&mono) {
+                    frame.fill(T::from_sample(value));
+                }
+            },
+            |err| eprintln!("Output stream error: {err:?}"),
+            None,
+        )
+        .map_err(|e| format!("Failed to build output stream: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sweep() {
+        let spec = GeneratorSpec::parse("sweep:100:10000:2@-6").unwrap();
+        assert_eq!(spec.waveform, Waveform::LogSweep { start: 100.0, end: 10000.0, duration: 2.0 });
+        assert_eq!(spec.level_dbfs, -6.0);
+
+        let error = GeneratorSpec::parse("sweep:1000:1000").unwrap_err();
+        assert!(error.contains("must differ"), "{}", error);
+        let error = GeneratorSpec::parse("sweep:20:24000").unwrap_err();
+        assert!(error.contains("below 24000 Hz"), "{}", error);
+        assert!(GeneratorSpec::parse("sweep:30000:100").is_err());
+    }
+}
@@ -1,61 +1,182 @@
-use jack::{self, AudioIn, Client, ClientOptions, ProcessHandler};
-use jack_sys as j;
-use parking_lot::Mutex;
-use std::sync::Arc;
-use std::time::Duration;
+use jack::{self, AudioIn, Client, ClientOptions, NotificationHandler, PortFlags, ProcessHandler};
 
-use crate::spectrum::compute_spectrum;
+use super::source::{AudioSource, SourceInfo};
+use crate::spectrum::{ErrorReporter, SampleSink};
 
+// 实时回调里每次交错处理的最大帧数，缓冲按此预先分配
+const CHUNK_FRAMES: usize = 1024;
+
+// JACK输入的端口数与自动连接规则
+#[derive(Debug, Clone, PartialEq)]
+pub struct JackOptions {
+    // 注册的输入端口数，即分析的声道数
+    pub ports: usize,
+    // 自动连接的源端口（JACK端口名正则），按顺序连到各输入端口；None 表示不自动连接
+    pub connect: Option<String>,
+}
+
+impl Default for JackOptions {
+    fn default() -> Self {
+        Self {
+            ports: 2,
+            connect: Some("system:capture_.*".to_string()),
+        }
+    }
+}
+
+impl JackOptions {
+    // 解析 "端口数[:源端口正则]"，如 "2:system:monitor_.*"；正则留空表示不自动连接
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut options = Self::default();
+        if text.is_empty() {
+            return Ok(options);
+        }
+        let (ports, connect) = match text.split_once(':') {
+            Some((ports, connect)) => (ports, Some(connect)),
+            None => (text, None),
+        };
+        options.ports = ports
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("Invalid JACK port count: {}", ports))?;
+        if let Some(connect) = connect {
+            options.connect = (!connect.is_empty()).then(|| connect.to_string());
+        }
+        Ok(options)
+    }
+}
+
+// 通过JACK服务器采集的音频输入
 pub struct JackAudioCapture {
-    _client: jack::AsyncClient<(), JackHandler>,
-    spectrum: Arc<Mutex<Vec<f32>>>,
+    options: JackOptions,
+    client: Option<Client>,
+    ports: Vec<jack::Port<AudioIn>>,
+    port_names: Vec<String>,
+    active: Option<jack::AsyncClient<JackNotifications, JackHandler>>,
+    info: SourceInfo,
 }
 
 struct JackHandler {
-    port: jack::Port<AudioIn>,
-    spectrum: Arc<Mutex<Vec<f32>>>,
-    buffer: Vec<f32>,
+    ports: Vec<jack::Port<AudioIn>>,
+    // 交错后的样本，长度为 CHUNK_FRAMES × 端口数
+    interleaved: Vec<f32>,
+    sink: SampleSink,
+}
+
+// JACK服务器关闭时报告错误
+struct JackNotifications {
+    errors: ErrorReporter,
 }
 
 impl JackAudioCapture {
-    pub fn new(spectrum: Arc<Mutex<Vec<f32>>>) -> Result<Self, Box<dyn std::error::Error>> {
-        let (client, _status) = Client::new("spectrum_analyzer", ClientOptions::NO_START_SERVER)?;
+    pub fn new(options: JackOptions) -> Result<Self, String> {
+        let (client, _status) = Client::new("spectrum_analyzer", ClientOptions::NO_START_SERVER)
+            .map_err(|e| format!("Failed to open JACK client: {}", e))?;
 
         println!("JACK 采样率: {}", client.sample_rate());
-        
-        let port = client.register_port("input", AudioIn::default())?;
-        let handler = JackHandler {
-            port,
-            spectrum: spectrum.clone(),
-            buffer: Vec::with_capacity(4096),
-        };
 
-        let active_client = client.activate_async((), handler)?;
+        let ports = (0..options.ports.max(1))
+            .map(|i| client.register_port(&format!("input_{}", i + 1), AudioIn))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to register JACK port: {}", e))?;
+        let port_names = ports
+            .iter()
+            .map(|port| port.name())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to get JACK port name: {}", e))?;
+        let info = SourceInfo {
+            name: format!("JACK {}", client.name()),
+            sample_rate: client.sample_rate() as u32,
+            channels: ports.len(),
+            format: "F32 (32 bit)".to_string(),
+        };
 
         Ok(Self {
-            _client: active_client,
-            spectrum,
+            options,
+            client: Some(client),
+            ports,
+            port_names,
+            active: None,
+            info,
         })
     }
 
-    pub fn start_capture(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // JACK client 已经在创建时自动启动
-        std::thread::sleep(Duration::from_millis(100)); // 等待JACK启动
+    // 激活之后才能连接端口
+    fn auto_connect(&self, client: &Client) {
+        let Some(pattern) = &self.options.connect else {
+            return;
+        };
+        let sources = client.ports(Some(pattern), Some("audio"), PortFlags::IS_OUTPUT);
+        if sources.is_empty() {
+            println!("没有找到匹配 {} 的JACK端口", pattern);
+        }
+        for (source, destination) in sources.iter().zip(&self.port_names) {
+            match client.connect_ports_by_name(source, destination) {
+                Ok(()) => println!("已连接 {} -> {}", source, destination),
+                Err(e) => eprintln!("Failed to connect {} -> {}: {}", source, destination, e),
+            }
+        }
+    }
+}
+
+impl AudioSource for JackAudioCapture {
+    fn info(&self) -> &SourceInfo {
+        &self.info
+    }
+
+    fn start(&mut self, sink: SampleSink) -> Result<(), String> {
+        let client = self
+            .client
+            .take()
+            .ok_or_else(|| "JACK client already started".to_string())?;
+        let ports = std::mem::take(&mut self.ports);
+        let notifications = JackNotifications { errors: sink.error_reporter() };
+        let handler = JackHandler {
+            interleaved: vec![0.0; CHUNK_FRAMES * ports.len()],
+            ports,
+            sink,
+        };
+        let active = client
+            .activate_async(notifications, handler)
+            .map_err(|e| format!("Failed to activate JACK client: {}", e))?;
+        self.auto_connect(active.as_client());
+        self.active = Some(active);
         Ok(())
     }
+
+    fn stop(&mut self) {
+        if let Some(active) = self.active.take() {
+            if let Err(e) = active.deactivate() {
+                eprintln!("Failed to deactivate JACK client: {}", e);
+            }
+        }
+    }
+}
+
+impl NotificationHandler for JackNotifications {
+    fn shutdown(&mut self, _status: jack::ClientStatus, reason: &str) {
+        self.errors.report(format!("JACK server shut down: {}", reason));
+    }
 }
 
 impl ProcessHandler for JackHandler {
+    // 实时回调里只把各端口的样本交错写进环形缓冲，分帧和FFT在分析线程完成，不分配内存
     fn process(&mut self, _: &Client, ps: &jack::ProcessScope) -> jack::Control {
-        let in_port = self.port.as_slice(ps);
-        self.buffer.extend_from_slice(in_port);
-
-        if self.buffer.len() >= 4096 {
-            let spectrum_data = compute_spectrum(&self.buffer[..4096]);
-            *self.spectrum.lock() = spectrum_data;
-            self.buffer.clear();
+        let channels = self.ports.len();
+        let frames = ps.n_frames() as usize;
+        let mut start = 0;
+        while start < frames {
+            let count = (frames - start).min(CHUNK_FRAMES);
+            for (channel, port) in self.ports.iter().enumerate() {
+                let samples = &port.as_slice(ps)[start..start + count];
+                for (frame, &sample) in samples.iter().enumerate() {
+                    self.interleaved[frame * channels + channel] = sample;
+                }
+            }
+            self.sink.push(&self.interleaved[..count * channels]);
+            start += count;
         }
-
         jack::Control::Continue
     }
 }
@@ -1,4 +1,184 @@
+mod source;
+mod file;
+mod generator;
+mod session;
+#[cfg(feature = "cpal")]
 mod device;
+#[cfg(feature = "cpal")]
 mod capture;
+#[cfg(feature = "cpal")]
+mod policy;
+#[cfg(feature = "jack")]
+mod jack_capture;
 
-pub use capture::AudioCapture;
+pub use source::{AudioSource, SourceInfo, SourceStatus};
+pub use file::{FileSource, Transport};
+pub use generator::{GeneratorSource, GeneratorSpec};
+pub use session::{CaptureSession, Recovery};
+#[cfg(feature = "cpal")]
+pub use generator::play_output;
+#[cfg(feature = "cpal")]
+pub use capture::{AudioCapture, DeviceSpec};
+#[cfg(feature = "cpal")]
+pub use policy::DevicePolicy;
+#[cfg(feature = "cpal")]
+pub use device::{AudioDeviceManager, DeviceDescription, DeviceEvent, DeviceId, DeviceMonitor};
+#[cfg(feature = "jack")]
+pub use jack_capture::{JackAudioCapture, JackOptions};
+
+use std::path::{Path, PathBuf};
+
+#[cfg(not(any(feature = "cpal", feature = "jack")))]
+compile_error!("at least one audio backend feature must be enabled: cpal or jack");
+
+// 设备断开后按名称重连的次数，之后试一次默认设备
+#[cfg(feature = "cpal")]
+const RECONNECT_BY_NAME: u32 = 3;
+
+// 界面发给音频管理线程的命令
+pub enum AudioCommand {
+    SwitchSource(SourceSpec),
+    Quit,
+}
+
+// 可以选择的音频源，新增的音频源只需要在这里注册
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceSpec {
+    // 系统音频设备及流参数
+    #[cfg(feature = "cpal")]
+    Device(DeviceSpec),
+    // 音频文件（WAV/FLAC），按实时速度播放
+    File(PathBuf),
+    // 内置信号发生器，不需要硬件
+    Generator(GeneratorSpec),
+    // JACK服务器，端口数与自动连接规则见 JackOptions
+    #[cfg(feature = "jack")]
+    Jack(JackOptions),
+}
+
+impl Default for SourceSpec {
+    #[cfg(feature = "cpal")]
+    fn default() -> Self {
+        SourceSpec::Device(DeviceSpec::default())
+    }
+
+    #[cfg(not(feature = "cpal"))]
+    fn default() -> Self {
+        SourceSpec::Jack(JackOptions::default())
+    }
+}
+
+impl SourceSpec {
+    // 解析命令行写法：default、loopback（系统输出）、设备编号、jack[:端口数[:源端口正则]]、.wav/.flac 文件路径、gen:波形
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.trim() {
+            #[cfg(feature = "cpal")]
+            "default" => Ok(SourceSpec::Device(DeviceSpec::default())),
+            #[cfg(feature = "cpal")]
+            "loopback" => Ok(SourceSpec::Device(DeviceSpec { loopback: true, ..Default::default() })),
+            #[cfg(feature = "jack")]
+            "jack" => Ok(SourceSpec::Jack(JackOptions::default())),
+            other if is_audio_file(other) => Ok(SourceSpec::File(PathBuf::from(other))),
+            other => {
+                if let Some(generator) = other.strip_prefix("gen:") {
+                    return Ok(SourceSpec::Generator(GeneratorSpec::parse(generator)?));
+                }
+                #[cfg(feature = "jack")]
+                if let Some(options) = other.strip_prefix("jack:") {
+                    return Ok(SourceSpec::Jack(JackOptions::parse(options)?));
+                }
+                #[cfg(feature = "cpal")]
+                if let Ok(index) = other.parse::<usize>() {
+                    return Ok(SourceSpec::Device(DeviceSpec { index: Some(index), ..Default::default() }));
+                }
+                Err(format!("Unknown audio source: {}", other))
+            }
+        }
+    }
+
+    // 自动恢复时第 attempt 次要打开的音频源及其说明：设备按名称重连，每隔几次退回默认设备的选择规则；
+    // 文件和信号发生器不会断开，返回 None
+    #[cfg_attr(not(feature = "cpal"), allow(unused_variables))]
+    pub fn reconnect_target(&self, name: &str, attempt: u32) -> Option<(SourceSpec, String)> {
+        match self {
+            // 回环跟随当前的默认输出，按原样重新打开
+            #[cfg(feature = "cpal")]
+            SourceSpec::Device(spec) if spec.loopback && spec.id.is_none() && spec.index.is_none() => {
+                Some((self.clone(), name.to_string()))
+            }
+            #[cfg(feature = "cpal")]
+            SourceSpec::Device(spec) => {
+                if attempt % (RECONNECT_BY_NAME + 1) == RECONNECT_BY_NAME {
+                    let fallback = DeviceSpec { host: spec.host, ..Default::default() };
+                    Some((SourceSpec::Device(fallback), "默认设备".to_string()))
+                } else {
+                    // 按名称打开的设备保留原来的标识，自动选择的设备按名称找回
+                    let id = spec.id.clone().unwrap_or_else(|| DeviceId {
+                        host: spec.host.unwrap_or_else(|| cpal::default_host().id()),
+                        name: name.to_string(),
+                        ordinal: 0,
+                    });
+                    let same = DeviceSpec { id: Some(id), index: None, loopback: false, ..spec.clone() };
+                    Some((SourceSpec::Device(same), name.to_string()))
+                }
+            }
+            #[cfg(feature = "jack")]
+            SourceSpec::Jack(_) => Some((self.clone(), name.to_string())),
+            SourceSpec::File(_) | SourceSpec::Generator(_) => None,
+        }
+    }
+
+    pub fn open(&self) -> Result<Box<dyn AudioSource>, String> {
+        match self {
+            #[cfg(feature = "cpal")]
+            SourceSpec::Device(spec) => Ok(Box::new(AudioCapture::open(spec)?)),
+            SourceSpec::File(path) => Ok(Box::new(FileSource::open(path, true)?)),
+            SourceSpec::Generator(spec) => Ok(Box::new(GeneratorSource::new(spec.clone()))),
+            #[cfg(feature = "jack")]
+            SourceSpec::Jack(options) => Ok(Box::new(JackAudioCapture::new(options.clone())?)),
+        }
+    }
+}
+
+fn is_audio_file(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    [".wav", ".wave", ".flac"].iter().any(|ext| lower.ends_with(ext))
+}
+
+// 读取设备选择规则与增益配置：指定的文件必须能读取，未指定时读取当前目录下的 devices.conf（如果有）
+pub fn load_device_policy(path: Option<&Path>) -> Result<(), String> {
+    #[cfg(feature = "cpal")]
+    {
+        let default = Path::new(policy::DEFAULT_POLICY_FILE);
+        let path = match path {
+            Some(path) => path,
+            None if default.exists() => default,
+            None => return Ok(()),
+        };
+        let loaded = DevicePolicy::load(path)?;
+        println!(
+            "已读取设备配置 {}: {} 条选择规则, {} 个增益配置",
+            path.display(),
+            loaded.rules.len(),
+            loaded.profiles.len()
+        );
+        loaded.install()
+    }
+    #[cfg(not(feature = "cpal"))]
+    match path {
+        Some(_) => Err("--device-config 需要 cpal 后端".to_string()),
+        None => Ok(()),
+    }
+}
+
+// 打印各个后端可用的音频源
+pub fn print_sources() {
+    #[cfg(feature = "cpal")]
+    AudioCapture::print_device_list();
+    #[cfg(feature = "cpal")]
+    println!("输入 loopback 捕获系统输出（Windows 为 WASAPI 回环，Linux 为 ~/.asoundrc 中指向 PulseAudio/PipeWire monitor 源的 ALSA 设备）");
+    println!("输入 .wav/.flac 文件路径分析音频文件");
+    println!("输入 gen:波形[:参数][@电平] 使用信号发生器，如 gen:sine:1000@-20、gen:pink、gen:sweep:20:20000:5");
+    #[cfg(feature = "jack")]
+    println!("输入 jack[:端口数[:源端口正则]] 使用JACK服务器作为音频源，如 jack:2:system:capture_.*");
+}
@@ -0,0 +1,314 @@
+// 设备选择规则与各设备的增益校准，可从配置文件读取，格式见 devices.example.conf
+use cpal::{Device, HostId};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::device::{supports_input, AudioDeviceManager};
+
+// 未用 --device-config 指定时，当前目录下有这个文件就读取
+pub const DEFAULT_POLICY_FILE: &str = "devices.conf";
+
+static POLICY: OnceLock<DevicePolicy> = OnceLock::new();
+
+// 自动选择设备的一条规则，未填写的条件不参与匹配
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeviceRule {
+    // 设备名称包含的文字，不区分大小写
+    pub name: Option<String>,
+    pub host: Option<HostId>,
+    // 设备需要支持的声道数和采样率，选中后也作为流参数
+    pub channels: Option<u16>,
+    pub sample_rate: Option<u32>,
+    // 捕获系统输出：Windows 为 WASAPI 回环，Linux 为指向 PulseAudio/PipeWire monitor 源的 ALSA 设备
+    pub loopback: bool,
+}
+
+impl DeviceRule {
+    fn matches(&self, name: &str, device: &Device) -> bool {
+        self.name.as_ref().is_none_or(|pattern| contains_ignore_case(name, pattern))
+            && supports_input(device, self.channels, self.sample_rate)
+    }
+}
+
+// 名称匹配的设备使用的增益，用于校准不同声卡或麦克风的灵敏度
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeviceProfile {
+    pub name: String,
+    pub gain_db: f32,
+    // 各声道在 gain_db 之外的修正，未列出的声道为 0
+    pub channel_gain_db: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevicePolicy {
+    // 按顺序尝试，第一个找到设备的规则生效
+    pub rules: Vec<DeviceRule>,
+    pub profiles: Vec<DeviceProfile>,
+}
+
+impl Default for DevicePolicy {
+    // 内置规则：Windows 上优先虚拟声卡和立体声混音，其他平台使用主机的默认输入设备
+    fn default() -> Self {
+        let rules = if cfg!(target_os = "windows") {
+            ["vb-", "立体声混音"]
+                .iter()
+                .map(|name| DeviceRule { name: Some(name.to_string()), ..Default::default() })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self { rules, profiles: Vec::new() }
+    }
+}
+
+impl DevicePolicy {
+    // 程序范围内使用的规则，没有安装时为内置规则
+    pub fn current() -> &'static DevicePolicy {
+        POLICY.get_or_init(DevicePolicy::default)
+    }
+
+    // 只能在打开任何设备之前安装一次
+    pub fn install(self) -> Result<(), String> {
+        POLICY.set(self).map_err(|_| "Device policy already installed".to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("无法读取设备配置 {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // [prefer] 段为一条选择规则，[profile] 段为一个增益配置；有 [prefer] 段时替换内置规则
+    pub fn parse(text: &str) -> Result<Self, String> {
+        enum Section {
+            None,
+            Prefer,
+            Profile,
+        }
+        let mut rules = Vec::new();
+        let mut profiles: Vec<DeviceProfile> = Vec::new();
+        let mut section = Section::None;
+
+        for (number, line) in text.lines().enumerate() {
+            let error = |message: String| format!("第 {} 行: {}", number + 1, message);
+            // 只有 # 开头的行是注释，值里的 # 保留，如 USB Audio #2
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = match name.trim() {
+                    "prefer" => {
+                        rules.push(DeviceRule::default());
+                        Section::Prefer
+                    }
+                    "profile" => {
+                        profiles.push(DeviceProfile::default());
+                        Section::Profile
+                    }
+                    other => return Err(error(format!("未知的配置段 [{}]", other))),
+                };
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| error(format!("应为 键 = 值: {}", line)))?;
+            let number_value = |value: &str| value.parse::<f32>().map_err(|_| error(format!("{} 需要数值: {}", key, value)));
+
+            match section {
+                Section::Prefer => {
+                    let rule = rules.last_mut().unwrap();
+                    match key {
+                        "name" => rule.name = Some(value.to_string()),
+                        "host" => rule.host = Some(parse_host(value).map_err(error)?),
+                        "channels" => {
+                            rule.channels = Some(value.parse().map_err(|_| error(format!("无效的声道数: {}", value)))?)
+                        }
+                        "sample_rate" => {
+                            rule.sample_rate = Some(value.parse().map_err(|_| error(format!("无效的采样率: {}", value)))?)
+                        }
+                        "loopback" => rule.loopback = parse_bool(value).map_err(error)?,
+                        other => return Err(error(format!("[prefer] 中未知的键: {}", other))),
+                    }
+                }
+                Section::Profile => {
+                    let profile = profiles.last_mut().unwrap();
+                    match key {
+                        "name" => profile.name = value.to_string(),
+                        "gain_db" => profile.gain_db = number_value(value)?,
+                        "channel_gain_db" => {
+                            profile.channel_gain_db =
+                                value.split(',').map(|v| number_value(v.trim())).collect::<Result<_, _>>()?
+                        }
+                        other => return Err(error(format!("[profile] 中未知的键: {}", other))),
+                    }
+                }
+                Section::None => return Err(error(format!("{} 不在 [prefer] 或 [profile] 段中", key))),
+            }
+        }
+
+        if let Some(index) = profiles.iter().position(|p| p.name.is_empty()) {
+            return Err(format!("第 {} 个 [profile] 缺少 name", index + 1));
+        }
+        let mut policy = Self { profiles, ..Default::default() };
+        if !rules.is_empty() {
+            policy.rules = rules;
+        }
+        Ok(policy)
+    }
+
+    // 按规则顺序选择设备，规则指定了其他主机时在那个主机上查找；都不匹配时返回 None
+    pub fn select(&self, manager: &AudioDeviceManager) -> Option<(Device, &DeviceRule)> {
+        for rule in &self.rules {
+            let other;
+            let manager = match rule.host.filter(|&host| host != manager.host_id()) {
+                Some(host) => match AudioDeviceManager::with_host(host) {
+                    Ok(found) => {
+                        other = found;
+                        &other
+                    }
+                    Err(_) => continue,
+                },
+                None => manager,
+            };
+            let device = if rule.loopback {
+                manager
+                    .find_loopback_device()
+                    .map_err(|e| println!("回环规则未找到设备: {}", e))
+                    .ok()
+            } else {
+                manager.find_input_device(|id, device| rule.matches(&id.name, device))
+            };
+            if let Some(device) = device {
+                return Some((device, rule));
+            }
+        }
+        None
+    }
+
+    // 设备各声道的线性增益，没有匹配的配置时为 1
+    pub fn channel_gains(&self, name: &str, channels: usize) -> Vec<f32> {
+        let profile = self.profiles.iter().find(|p| contains_ignore_case(name, &p.name));
+        (0..channels)
+            .map(|channel| {
+                let db = profile.map_or(0.0, |p| p.gain_db + p.channel_gain_db.get(channel).copied().unwrap_or(0.0));
+                10f32.powf(db / 20.0)
+            })
+            .collect()
+    }
+}
+
+fn contains_ignore_case(text: &str, pattern: &str) -> bool {
+    text.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+// 主机名如 ALSA、JACK、WASAPI、ASIO、CoreAudio，不区分大小写
+fn parse_host(text: &str) -> Result<HostId, String> {
+    cpal::ALL_HOSTS
+        .iter()
+        .copied()
+        .find(|id| id.name().eq_ignore_ascii_case(text))
+        .ok_or_else(|| {
+            let names: Vec<&str> = cpal::ALL_HOSTS.iter().map(|id| id.name()).collect();
+            format!("未知的音频主机: {} (可选 {})", text, names.join(", "))
+        })
+}
+
+fn parse_bool(text: &str) -> Result<bool, String> {
+    match text.to_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(format!("应为 true 或 false: {}", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(text: &str) -> String {
+        DevicePolicy::parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn test_example_config() {
+        let policy = DevicePolicy::parse(include_str!("../../devices.example.conf")).unwrap();
+        assert_eq!(policy.rules.len(), 4);
+        assert_eq!(policy.rules[0].name.as_deref(), Some("vb-"));
+        assert_eq!(
+            policy.rules[2],
+            DeviceRule {
+                name: Some("USB Audio".to_string()),
+                channels: Some(2),
+                sample_rate: Some(48000),
+                ..Default::default()
+            }
+        );
+        assert!(policy.rules[3].loopback && policy.rules[3].name.is_none());
+        assert_eq!(policy.profiles.len(), 2);
+        assert_eq!(policy.profiles[1].channel_gain_db, vec![0.0, -0.35]);
+    }
+
+    #[test]
+    fn test_sections_and_keys() {
+        let host = cpal::ALL_HOSTS[0];
+        let policy = DevicePolicy::parse(&format!("# comment\n[prefer]\nhost = {}\nloopback = no\n", host.name().to_uppercase())).unwrap();
+        assert_eq!(policy.rules, vec![DeviceRule { host: Some(host), ..Default::default() }]);
+
+        assert!(parse_error("[devices]\n").contains("未知的配置段 [devices]"));
+        assert!(parse_error("[prefer]\nport = 1\n").contains("[prefer] 中未知的键: port"));
+        assert!(parse_error("[profile]\nname = x\nvolume = 1\n").contains("[profile] 中未知的键: volume"));
+        assert!(parse_error("name = x\n").contains("不在 [prefer] 或 [profile] 段中"));
+        assert!(parse_error("[prefer]\nname\n").starts_with("第 2 行"));
+        assert!(parse_error("[prefer]\nhost = nope\n").contains("未知的音频主机"));
+        assert!(parse_error("[prefer]\nchannels = two\n").contains("无效的声道数"));
+        assert!(parse_error("[profile]\nname = x\ngain_db = loud\n").contains("gain_db 需要数值"));
+    }
+
+    #[test]
+    fn test_hash_inside_value() {
+        let policy = DevicePolicy::parse("  # second card\n[prefer]\nname = USB Audio #2\n[profile]\nname = Mic#1\n").unwrap();
+        assert_eq!(policy.rules[0].name.as_deref(), Some("USB Audio #2"));
+        assert_eq!(policy.profiles[0].name, "Mic#1");
+    }
+
+    #[test]
+    fn test_profile_without_name() {
+        let error = parse_error("[profile]\nname = a\n[profile]\ngain_db = 3\n");
+        assert!(error.contains("第 2 个 [profile] 缺少 name"), "{}", error);
+    }
+
+    #[test]
+    fn test_bad_loopback() {
+        let error = parse_error("[prefer]\nloopback = maybe\n");
+        assert!(error.starts_with("第 2 行") && error.contains("应为 true 或 false"), "{}", error);
+        for (text, expected) in [("TRUE", true), ("yes", true), ("1", true), ("False", false), ("0", false)] {
+            assert_eq!(parse_bool(text), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_builtin_rules_kept_without_prefer() {
+        let policy = DevicePolicy::parse("[profile]\nname = USB\ngain_db = 3\n").unwrap();
+        assert_eq!(policy.rules, DevicePolicy::default().rules);
+        assert_eq!(policy.profiles.len(), 1);
+        assert_eq!(DevicePolicy::parse("").unwrap(), DevicePolicy::default());
+
+        let policy = DevicePolicy::parse("[prefer]\nname = mic\n").unwrap();
+        assert_eq!(policy.rules, vec![DeviceRule { name: Some("mic".to_string()), ..Default::default() }]);
+    }
+
+    #[test]
+    fn test_channel_gains() {
+        let policy = DevicePolicy::parse("[profile]\nname = usb audio\ngain_db = 6\nchannel_gain_db = 0, -6\n").unwrap();
+        let db = |gains: Vec<f32>| gains.iter().map(|g| 20.0 * g.log10()).collect::<Vec<_>>();
+        // 未列出的第三个声道只有 gain_db
+        let gains = db(policy.channel_gains("My USB Audio Device", 3));
+        for (gain, expected) in gains.iter().zip([6.0, 0.0, 6.0]) {
+            assert!((gain - expected).abs() < 1e-4, "{:?}", gains);
+        }
+        // 名称不匹配时为 1
+        assert_eq!(policy.channel_gains("Built-in Microphone", 2), vec![1.0, 1.0]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::time::{Duration, Instant};
+
+use super::source::{AudioSource, SourceInfo, SourceStatus};
+use super::SourceSpec;
+use crate::spectrum::{AnalysisPipeline, AnalysisWorker};
+
+// 自动恢复的重试间隔从这里开始每次加倍
+const RETRY_INITIAL: Duration = Duration::from_millis(500);
+const RETRY_MAX: Duration = Duration::from_secs(10);
+
+// 一次采集：音频源和它的分析线程一起启动，停止时先停音频源再等分析线程退出
+pub struct CaptureSession {
+    spec: SourceSpec,
+    source: Box<dyn AudioSource>,
+    worker: AnalysisWorker,
+    pipeline: AnalysisPipeline,
+}
+
+impl CaptureSession {
+    // 打开音频源并接到分析流水线上，状态随之更新
+    pub fn start(spec: &SourceSpec, pipeline: &AnalysisPipeline) -> Result<Self, String> {
+        pipeline.set_status(SourceStatus::Starting);
+        let result = spec.open().and_then(|mut source| {
+            let (sink, worker) = pipeline.spawn(source.info())?;
+            // 启动失败时 worker 在这里释放，分析线程随之退出
+            source.start(sink)?;
+            let spec = source.resolved_spec().unwrap_or_else(|| spec.clone());
+            Ok(Self { spec, source, worker, pipeline: pipeline.clone() })
+        });
+        match result {
+            Ok(session) => {
+                pipeline.set_transport(session.source.transport());
+                pipeline.set_status(SourceStatus::Running);
+                Ok(session)
+            }
+            Err(e) => {
+                pipeline.set_status(SourceStatus::Error(e.clone()));
+                Err(e)
+            }
+        }
+    }
+
+    pub fn info(&self) -> &SourceInfo {
+        self.source.info()
+    }
+
+    // 音频源报告了错误或长时间没有数据
+    pub fn needs_recovery(&self) -> bool {
+        matches!(self.pipeline.status(), SourceStatus::Error(_) | SourceStatus::Stalled)
+    }
+
+    // 可以重复调用；出错后停止时保留错误状态，界面上能看到会话为什么结束
+    pub fn stop(&mut self) {
+        self.source.stop();
+        self.worker.stop();
+        self.pipeline.set_transport(None);
+        if !self.needs_recovery() {
+            self.pipeline.set_status(SourceStatus::Stopped);
+        }
+    }
+}
+
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// 会话出错或断流后的自动恢复：按退避间隔重新打开，设备先按名称重连，再退回默认设备
+pub struct Recovery {
+    spec: SourceSpec,
+    name: String,
+    attempt: u32,
+    next_try: Instant,
+}
+
+impl Recovery {
+    // 不会断开的音频源（文件、信号发生器）返回 None
+    pub fn new(session: &CaptureSession) -> Option<Self> {
+        session.spec.reconnect_target(&session.info().name, 0)?;
+        Some(Self {
+            spec: session.spec.clone(),
+            name: session.info().name.clone(),
+            attempt: 0,
+            next_try: Instant::now() + RETRY_INITIAL,
+        })
+    }
+
+    pub fn is_due(&self) -> bool {
+        Instant::now() >= self.next_try
+    }
+
+    // 尝试一次，失败后把下一次推迟到退避间隔之后
+    pub fn retry(&mut self, pipeline: &AnalysisPipeline) -> Option<CaptureSession> {
+        let (spec, target) = self.spec.reconnect_target(&self.name, self.attempt)?;
+        self.attempt += 1;
+        pipeline.set_status(SourceStatus::Reconnecting { attempt: self.attempt, target });
+        match CaptureSession::start(&spec, pipeline) {
+            Ok(session) => Some(session),
+            Err(_) => {
+                let delay = RETRY_INITIAL.saturating_mul(1 << self.attempt.min(5)).min(RETRY_MAX);
+                self.next_try = Instant::now() + delay;
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use super::file::Transport;
+use super::SourceSpec;
+use crate::spectrum::SampleSink;
+
+// 音频源打开后确定的流格式
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceInfo {
+    // 显示名称，也用于查找校准参数
+    pub name: String,
+    pub sample_rate: u32,
+    // 交错样本的声道数
+    pub channels: usize,
+    // 原生样本格式与位深，仅用于显示
+    pub format: String,
+}
+
+// 音频源的运行状态，由音频管理线程更新后显示在界面上
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum SourceStatus {
+    #[default]
+    Stopped,
+    Starting,
+    Running,
+    // 已启动但一段时间没有收到样本
+    Stalled,
+    // 打开失败或运行中出错
+    Error(String),
+    // 自动恢复中，等待下一次重新打开
+    Reconnecting { attempt: u32, target: String },
+}
+
+// 所有音频输入的统一接口：打开时确定格式，start 之后把交错的f32样本写入 sink
+pub trait AudioSource {
+    fn info(&self) -> &SourceInfo;
+
+    fn start(&mut self, sink: SampleSink) -> Result<(), String>;
+
+    fn stop(&mut self);
+
+    // 可以跳转和暂停的音频源（如文件）提供播放控制
+    fn transport(&self) -> Option<Transport> {
+        None
+    }
+
+    // 自动恢复时重新打开用的写法，自动选择的设备在这里记下实际选中的设备；None 时使用打开时的写法
+    fn resolved_spec(&self) -> Option<SourceSpec> {
+        None
+    }
+}
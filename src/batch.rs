@@ -0,0 +1,505 @@
+// 无界面的批处理分析：读取文件或实时输入，输出平均频谱、逐帧频谱图、各频带的 Leq 和汇总指标
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use myalgorithm::{AmplitudeScale, AnalysisConfig, AveragingMode, ChannelMode, ChannelSplitter, WindowFunction};
+use myalgorithm::{AveragingSettings, BandLevels, FrequencyWeighting, OctaveFraction, OctaveSettings, PeakHold};
+
+use crate::audio::{load_device_policy, AudioSource, FileSource, SourceSpec};
+use crate::export::{csv_field, ExportFormat, JsonValue, Table, TableWriter};
+use crate::spectrum::{load_weighting_curve, AnalyzerSettings, ChannelPipeline, SampleSink};
+
+const USAGE: &str = "用法: rust_spectrum_analyse batch --input <文件|设备编号|default|loopback|jack|gen:波形> --output <输出前缀>
+    [--format csv|json|npy] [--duration 秒(实时输入，默认10)] [--device-config 设备配置文件]
+    [--fft-size N] [--hop-size N] [--window 名称] [--scale dBFS|dBV|dBu|dBSPL|Linear|PSD]
+    [--channels mono|chN|midside|perchannel] [--averaging linear|exponential|rms]
+    [--frames N (平均的帧数，默认0即整段输入)] [--peak-hold inf|dB/s (最大保持的回落速率，默认inf即整段最大值)]
+    [--octave 1|3|6|12|24 (输出 20 Hz - 20 kHz 各分数倍频程频带的 Leq)]
+    [--weighting A|B|C|Z|468|曲线文件 (频谱、频带和汇总中的 Leq 均按此计权)]";
+
+struct BatchOptions {
+    input: SourceSpec,
+    output: PathBuf,
+    format: ExportFormat,
+    duration: Duration,
+    config: AnalysisConfig,
+    settings: AnalyzerSettings,
+}
+
+impl BatchOptions {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut input = None;
+        let mut output = None;
+        let mut format = ExportFormat::Csv;
+        let mut duration = Duration::from_secs(10);
+        let mut config = AnalysisConfig::default();
+        let mut hop_size = None;
+        // 平均频谱和最大保持默认覆盖整段输入，而不是界面上的最近几帧
+        let mut settings = AnalyzerSettings {
+            averaging: AveragingSettings { frames: 0, peak_hold: PeakHold::Infinite, ..Default::default() },
+            ..Default::default()
+        };
+        let mut device_config = None;
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} 缺少参数\n{}", arg, USAGE))?;
+            let number = || value.parse::<usize>().map_err(|_| format!("{} 需要整数: {}", arg, value));
+            match arg.as_str() {
+                "--input" => input = Some(SourceSpec::parse(&value)?),
+                "--output" => output = Some(PathBuf::from(&value)),
+                "--device-config" => device_config = Some(PathBuf::from(&value)),
+                "--format" => format = ExportFormat::parse(&value)?,
+                "--duration" => {
+                    let seconds = value.parse::<f32>().map_err(|_| format!("无效的时长: {}", value))?;
+                    duration = Duration::from_secs_f32(seconds.max(0.0));
+                }
+                "--fft-size" => config.fft_size = number()?,
+                "--hop-size" => hop_size = Some(number()?),
+                "--window" => settings.window = parse_named(&value, &WindowFunction::ALL, |w| w.name())?,
+                "--scale" => settings.scale = parse_named(&value, &AmplitudeScale::ALL, |s| s.name())?,
+                "--channels" => settings.channel_mode = parse_channel_mode(&value)?,
+                "--averaging" => settings.averaging.mode = parse_named(&value, &AveragingMode::ALL, |m| m.name())?,
+                "--frames" => settings.averaging.frames = number()?,
+                "--peak-hold" => settings.averaging.peak_hold = parse_peak_hold(&value)?,
+                "--octave" => {
+                    settings.octave = Some(OctaveSettings { fraction: parse_fraction(&value)?, ..Default::default() })
+                }
+                "--weighting" => {
+                    settings.weighting = match FrequencyWeighting::from_name(&value) {
+                        Some(weighting) => weighting,
+                        None => FrequencyWeighting::Custom(load_weighting_curve(Path::new(&value))?),
+                    }
+                }
+                _ => return Err(format!("无法识别的参数: {}\n{}", arg, USAGE)),
+            }
+        }
+
+        load_device_policy(device_config.as_deref())?;
+        config.hop_size = hop_size.unwrap_or(config.fft_size);
+        config.validate()?;
+        settings.overlap = config.overlap();
+        Ok(Self {
+            input: input.ok_or_else(|| format!("缺少 --input\n{}", USAGE))?,
+            output: output.ok_or_else(|| format!("缺少 --output\n{}", USAGE))?,
+            format,
+            duration,
+            config,
+            settings,
+        })
+    }
+}
+
+// 忽略大小写、空格和连字符比较名称
+fn parse_named<T: Copy>(text: &str, all: &[T], name: impl Fn(&T) -> &'static str) -> Result<T, String> {
+    let normalize = |s: &str| s.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+    let wanted = normalize(text);
+    all.iter()
+        .find(|item| normalize(name(item)) == wanted)
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<&str> = all.iter().map(&name).collect();
+            format!("未知的名称: {} (可选 {})", text, names.join(", "))
+        })
+}
+
+// inf 为一直保持，数值为每秒回落的 dB
+fn parse_peak_hold(text: &str) -> Result<PeakHold, String> {
+    if text.eq_ignore_ascii_case("inf") {
+        return Ok(PeakHold::Infinite);
+    }
+    text.trim_end_matches("dB/s")
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|rate| *rate > 0.0)
+        .map(|db_per_second| PeakHold::Decay { db_per_second })
+        .ok_or_else(|| format!("无效的峰值保持: {} (应为 inf 或每秒回落的 dB)", text))
+}
+
+// 每倍频程的频带数，也可写成 1/3 这样的分数
+fn parse_fraction(text: &str) -> Result<OctaveFraction, String> {
+    let bands = text.strip_prefix("1/").unwrap_or(text).parse::<u32>().ok();
+    OctaveFraction::ALL
+        .into_iter()
+        .find(|fraction| Some(fraction.bands_per_octave()) == bands)
+        .ok_or_else(|| format!("不支持的倍频程划分: {} (可选 1、3、6、12、24)", text))
+}
+
+fn parse_channel_mode(text: &str) -> Result<ChannelMode, String> {
+    let lower = text.to_lowercase();
+    match lower.as_str() {
+        "mono" => Ok(ChannelMode::MonoSum),
+        "midside" | "mid-side" | "ms" => Ok(ChannelMode::MidSide),
+        "perchannel" | "per-channel" | "all" => Ok(ChannelMode::PerChannel),
+        _ => lower
+            .strip_prefix("ch")
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .map(|n| ChannelMode::Single(n - 1))
+            .ok_or_else(|| format!("未知的声道模式: {}", text)),
+    }
+}
+
+// 一路信号的分析状态与累积结果
+struct BatchChannel {
+    name: String,
+    pipeline: ChannelPipeline,
+    // 每帧的实时频谱随分析写入文件，不留在内存里
+    spectrogram: TableWriter,
+    spectrogram_path: PathBuf,
+    // 写一行用的缓冲：帧中心时间，然后是各频点
+    row: Vec<f32>,
+    config: AnalysisConfig,
+    sum_squares: f64,
+    sample_count: u64,
+    peak: f32,
+}
+
+impl BatchChannel {
+    // 频谱图每路一个文件，每行一帧，第一列为帧中心时间
+    fn new(name: String, options: &BatchOptions, config: AnalysisConfig, input_name: &str) -> std::io::Result<Self> {
+        let mut pipeline = ChannelPipeline::new(config);
+        pipeline.analyzer.apply_settings(&options.settings, input_name);
+        let axis = config.freq_axis();
+        let mut columns = vec!["time_s".to_string()];
+        columns.extend(axis.frequencies().map(|f| f.to_string()));
+        let spectrogram_path = output_path(options, &format!("spectrogram_{}", name), options.format.extension());
+        Ok(Self {
+            spectrogram: TableWriter::create(&spectrogram_path, options.format, &columns)?,
+            spectrogram_path,
+            row: Vec::with_capacity(columns.len()),
+            config,
+            name,
+            pipeline,
+            sum_squares: 0.0,
+            sample_count: 0,
+            peak: 0.0,
+        })
+    }
+
+    fn push(&mut self, samples: &[f32]) -> std::io::Result<()> {
+        for &x in samples {
+            self.sum_squares += (x as f64) * (x as f64);
+            self.peak = self.peak.max(x.abs());
+        }
+        self.sample_count += samples.len() as u64;
+
+        let ChannelPipeline { stft, analyzer, traces } = &mut self.pipeline;
+        let (spectrogram, row) = (&mut self.spectrogram, &mut self.row);
+        let config = self.config;
+        let mut result = Ok(());
+        analyzer.process_levels(samples);
+        stft.push(samples, |frame| {
+            analyzer.compute_spectrum(frame, traces);
+            if result.is_ok() {
+                row.clear();
+                row.push(frame_time(config, spectrogram.rows()));
+                row.extend_from_slice(&traces.live);
+                result = spectrogram.write_row(row);
+            }
+        });
+        result
+    }
+
+    fn frames(&self) -> usize {
+        self.spectrogram.rows()
+    }
+
+    fn rms(&self) -> f32 {
+        (self.sum_squares / self.sample_count.max(1) as f64).sqrt() as f32
+    }
+
+    // 时域RMS，按满幅正弦为 0 dBFS 的约定
+    fn rms_dbfs(&self) -> f32 {
+        20.0 * (self.rms() * std::f32::consts::SQRT_2).max(1e-10).log10()
+    }
+
+    // 峰值因数，正弦为 3.01 dB
+    fn crest_db(&self) -> f32 {
+        20.0 * (self.peak.max(1e-10) / self.rms().max(1e-10)).log10()
+    }
+
+    fn peak_dbfs(&self) -> f32 {
+        20.0 * self.peak.max(1e-10).log10()
+    }
+
+    // 频率计权后从头到尾的 Leq
+    fn weighted_leq_dbfs(&self) -> f32 {
+        self.pipeline.analyzer.level_reading().leq
+    }
+
+    // 平均频谱中最高的频点（不含直流）
+    fn spectral_peak(&self) -> (usize, f32) {
+        self.pipeline
+            .traces
+            .average
+            .iter()
+            .copied()
+            .enumerate()
+            .skip(1)
+            .fold((0, f32::NEG_INFINITY), |best, (bin, level)| if level > best.1 { (bin, level) } else { best })
+    }
+}
+
+// 批处理使用尽快读取的文件源，其他音频源按原样打开
+fn open_source(spec: &SourceSpec) -> Result<Box<dyn AudioSource>, String> {
+    match spec {
+        SourceSpec::File(path) => Ok(Box::new(FileSource::open(path, false)?)),
+        other => other.open(),
+    }
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let options = BatchOptions::parse(args)?;
+    let mut source = open_source(&options.input)?;
+    let info = source.info().clone();
+    let config = options.config.with_sample_rate(info.sample_rate as f32);
+    let channels = info.channels.max(1);
+    // 界面上声道不够时退回单声道，批处理要求输出与参数一致
+    let channel_mode = options.settings.channel_mode;
+    if channel_mode.resolve(channels) != channel_mode {
+        return Err(format!("{} 只有 {} 个声道，不支持声道模式 {}", info.name, channels, channel_mode.name()));
+    }
+
+    let capacity = (config.fft_size * 2).max(8192) * channels;
+    let (sink, mut consumer) = SampleSink::new(capacity);
+    let mut splitter = ChannelSplitter::new(channels, options.settings.channel_mode);
+    let mut outputs = splitter
+        .mode()
+        .output_names(channels)
+        .into_iter()
+        .map(|name| BatchChannel::new(name, &options, config, &info.name))
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|e| format!("无法创建输出文件: {}", e))?;
+
+    source.start(sink)?;
+    let transport = source.transport();
+    println!("分析 {} ({} Hz, {} ch, {})", info.name, info.sample_rate, channels, info.format);
+
+    let started = Instant::now();
+    let mut scratch = vec![0.0; capacity];
+    loop {
+        // 先读结束标志再取样本：播放线程在两次调用之间推入最后一块并结束时，留到下一轮取出
+        let finished = transport.as_ref().is_some_and(|transport| transport.is_finished());
+        let count = consumer.pop_slice(&mut scratch);
+        if count > 0 {
+            for (output, samples) in outputs.iter_mut().zip(splitter.process(&scratch[..count])) {
+                if let Err(e) = output.push(samples) {
+                    source.stop();
+                    return Err(format!("写入结果失败: {}", e));
+                }
+            }
+        }
+        // 文件读完且缓冲取空后结束，实时输入按给定时长结束
+        let done = match &transport {
+            Some(_) => count == 0 && finished,
+            None => started.elapsed() >= options.duration,
+        };
+        if done {
+            break;
+        }
+        if count == 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+    source.stop();
+
+    let frames = outputs.first().map_or(0, |o| o.frames());
+    if frames == 0 {
+        // 没有内容的频谱图文件不留下
+        for output in outputs {
+            let _ = std::fs::remove_file(&output.spectrogram_path);
+        }
+        return Err(format!("输入太短，不足一帧 ({} 点)", config.fft_size));
+    }
+    write_results(&options, &info.name, &info.format, config, &mut outputs)
+        .map_err(|e| format!("写入结果失败: {}", e))?;
+    println!("完成: {} 帧, 用时 {:.2} s", frames, started.elapsed().as_secs_f32());
+    Ok(())
+}
+
+// 第 frame 帧的中心时刻（秒）
+fn frame_time(config: AnalysisConfig, frame: usize) -> f32 {
+    (frame * config.hop_size + config.fft_size / 2) as f32 / config.sample_rate
+}
+
+fn output_path(options: &BatchOptions, suffix: &str, extension: &str) -> PathBuf {
+    let mut name = options.output.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(format!("_{}.{}", suffix, extension));
+    options.output.with_file_name(name)
+}
+
+fn write_results(
+    options: &BatchOptions,
+    input_name: &str,
+    input_format: &str,
+    config: AnalysisConfig,
+    outputs: &mut [BatchChannel],
+) -> std::io::Result<()> {
+    // 频谱图已经随分析逐帧写入，这里补上结尾
+    for output in outputs.iter_mut() {
+        output.spectrogram.finish()?;
+        println!("已写入 {}", output.spectrogram_path.display());
+    }
+    let outputs = &*outputs;
+
+    let extension = options.format.extension();
+    let axis = config.freq_axis();
+
+    // 平均频谱：频率，然后每路的平均、最大保持、最小保持
+    let mut columns = vec!["frequency_hz".to_string()];
+    for output in outputs {
+        for trace in ["average", "max_hold", "min_hold"] {
+            columns.push(format!("{}_{}", output.name, trace));
+        }
+    }
+    let rows = axis
+        .frequencies()
+        .enumerate()
+        .map(|(bin, freq)| {
+            let mut row = vec![freq];
+            for output in outputs {
+                let traces = &output.pipeline.traces;
+                row.extend([traces.average[bin], traces.max_hold[bin], traces.min_hold[bin]]);
+            }
+            row
+        })
+        .collect();
+    let path = output_path(options, "spectrum", extension);
+    Table { columns, rows }.write(&path, options.format)?;
+    println!("已写入 {}", path.display());
+
+    // 各频带从头到尾的 Leq：每个频带一行，标称频率、边缘，然后每路的 Leq（dBFS）
+    if options.settings.octave.is_some() {
+        let levels: Vec<BandLevels> = outputs
+            .iter()
+            .map(|output| {
+                let mut levels = BandLevels::default();
+                output.pipeline.analyzer.read_band_levels(&mut levels);
+                levels
+            })
+            .collect();
+        let mut columns: Vec<String> = ["nominal_hz", "center_hz", "lower_hz", "upper_hz"].map(String::from).into();
+        columns.extend(outputs.iter().map(|output| format!("{}_leq_dbfs", output.name)));
+        let rows = levels[0]
+            .bands
+            .iter()
+            .enumerate()
+            .map(|(index, band)| {
+                let mut row = vec![band.nominal, band.center, band.lower, band.upper];
+                row.extend(levels.iter().map(|l| l.leq[index]));
+                row
+            })
+            .collect();
+        let path = output_path(options, "octave", extension);
+        Table { columns, rows }.write(&path, options.format)?;
+        println!("已写入 {}", path.display());
+    }
+
+    // 汇总指标：CSV 时每路一行，JSON/NPY 时写成 JSON
+    let scale = options.settings.scale;
+    let frames = outputs[0].frames();
+    let duration = outputs[0].sample_count as f32 / config.sample_rate;
+    if options.format == ExportFormat::Csv {
+        let mut text = String::from("channel,rms_dbfs,peak_dbfs,crest_db,weighted_leq_dbfs,peak_frequency_hz,peak_level\n");
+        for output in outputs {
+            let (bin, level) = output.spectral_peak();
+            text.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                csv_field(&output.name),
+                output.rms_dbfs(),
+                output.peak_dbfs(),
+                output.crest_db(),
+                output.weighted_leq_dbfs(),
+                axis.bin_to_hz(bin),
+                level
+            ));
+        }
+        let path = output_path(options, "summary", "csv");
+        std::fs::write(&path, text)?;
+        println!("已写入 {}", path.display());
+    } else {
+        let text = |s: &str| JsonValue::Text(s.to_string());
+        let channels = outputs
+            .iter()
+            .map(|output| {
+                let (bin, level) = output.spectral_peak();
+                JsonValue::Object(vec![
+                    ("name".to_string(), text(&output.name)),
+                    ("rms_dbfs".to_string(), JsonValue::Number(output.rms_dbfs() as f64)),
+                    ("peak_dbfs".to_string(), JsonValue::Number(output.peak_dbfs() as f64)),
+                    ("crest_db".to_string(), JsonValue::Number(output.crest_db() as f64)),
+                    ("weighted_leq_dbfs".to_string(), JsonValue::Number(output.weighted_leq_dbfs() as f64)),
+                    ("peak_frequency_hz".to_string(), JsonValue::Number(axis.bin_to_hz(bin) as f64)),
+                    ("peak_level".to_string(), JsonValue::Number(level as f64)),
+                ])
+            })
+            .collect();
+        let summary = JsonValue::Object(vec![
+            ("input".to_string(), text(input_name)),
+            ("format".to_string(), text(input_format)),
+            ("sample_rate".to_string(), JsonValue::Number(config.sample_rate as f64)),
+            ("fft_size".to_string(), JsonValue::Number(config.fft_size as f64)),
+            ("hop_size".to_string(), JsonValue::Number(config.hop_size as f64)),
+            ("window".to_string(), text(options.settings.window.name())),
+            ("unit".to_string(), text(scale.unit())),
+            ("weighting".to_string(), text(options.settings.weighting.name())),
+            ("frames".to_string(), JsonValue::Number(frames as f64)),
+            ("duration_s".to_string(), JsonValue::Number(duration as f64)),
+            ("channels".to_string(), JsonValue::Array(channels)),
+        ]);
+        let path = output_path(options, "summary", "json");
+        summary.write(&path)?;
+        println!("已写入 {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FS: u32 = 48000;
+    const FFT: usize = 1024;
+
+    // 前 4 帧为满幅正弦，后 28 帧降低 20 dB，频率落在第 40 个频点的中心
+    fn write_step_wav(path: &Path) {
+        let spec = hound::WavSpec { channels: 1, sample_rate: FS, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        let freq = 40.0 * FS as f32 / FFT as f32;
+        for i in 0..32 * FFT {
+            let amplitude = if i < 4 * FFT { 1.0 } else { 0.1 };
+            let t = i as f32 / FS as f32;
+            writer.write_sample(amplitude * (std::f32::consts::TAU * freq * t).sin()).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn test_average_and_max_hold_cover_whole_file() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("batch_step_{}.wav", std::process::id()));
+        let output = dir.join(format!("batch_step_{}", std::process::id()));
+        write_step_wav(&input);
+        let args = ["--input", input.to_str().unwrap(), "--output", output.to_str().unwrap(), "--fft-size", "1024"];
+        run(args.iter().map(|a| a.to_string())).unwrap();
+
+        let spectrum = output.with_file_name(format!("batch_step_{}_spectrum.csv", std::process::id()));
+        let text = std::fs::read_to_string(&spectrum).unwrap();
+        for suffix in ["spectrum.csv", "summary.csv", "spectrogram_Mono.csv"] {
+            let _ = std::fs::remove_file(output.with_file_name(format!("batch_step_{}_{}", std::process::id(), suffix)));
+        }
+        std::fs::remove_file(&input).unwrap();
+
+        // 频率, 平均, 最大保持, 最小保持
+        let row: Vec<f32> = text.lines().nth(1 + 40).unwrap().split(',').map(|v| v.parse().unwrap()).collect();
+        let (average, max_hold, min_hold) = (row[1], row[2], row[3]);
+        // 32 帧幅度的算术平均 (4 × 1 + 28 × 0.1) / 32，而不是最后 8 帧的 0.1
+        let expected = 20.0 * (6.8f32 / 32.0).log10();
+        assert!((average - max_hold - expected).abs() < 0.1, "{} {}", average, max_hold);
+        // 最大保持是开头的满幅帧，不随时间回落
+        assert!((max_hold - min_hold - 20.0).abs() < 0.1, "{} {}", max_hold, min_hold);
+    }
+}
@@ -0,0 +1,133 @@
+// 失真分析面板：设置谐波次数、陷波宽度和测量频段，显示每路的 THD、THD+N、SINAD、SNR、ENOB 和各次谐波
+use myalgorithm::{DistortionResult, DistortionSettings};
+
+use crate::spectrum::SpectrumFrame;
+use crate::ui::format_frequency;
+
+// 限制测量频段上限时的默认值
+const DEFAULT_MAX_FREQ_HZ: f32 = 20000.0;
+const DEFAULT_FUNDAMENTAL_HZ: f32 = 1000.0;
+
+pub struct DistortionPanel {
+    pub enabled: bool,
+    settings: DistortionSettings,
+    // 关闭后保留输入的数值，再勾选时恢复
+    max_freq_hz: f32,
+    fundamental_hz: f32,
+}
+
+impl DistortionPanel {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            settings: DistortionSettings::default(),
+            max_freq_hz: DEFAULT_MAX_FREQ_HZ,
+            fundamental_hz: DEFAULT_FUNDAMENTAL_HZ,
+        }
+    }
+
+    // 交给分析线程的参数，没有开启时为 None
+    pub fn settings(&self) -> Option<DistortionSettings> {
+        self.enabled.then_some(self.settings)
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, frames: &[SpectrumFrame], colors: impl Fn(usize) -> egui::Color32) {
+        ui.heading("失真分析");
+        self.show_settings(ui);
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (index, frame) in frames.iter().enumerate() {
+                if frames.len() > 1 {
+                    ui.colored_label(colors(index), &frame.name);
+                }
+                match &frame.distortion {
+                    Some(result) => show_result(ui, index, result),
+                    None => {
+                        ui.label("未找到基波");
+                    }
+                }
+                ui.separator();
+            }
+        });
+    }
+
+    fn show_settings(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.settings;
+        ui.add(egui::DragValue::new(&mut settings.max_harmonic).clamp_range(2..=50).prefix("最高谐波 "));
+        ui.add(
+            egui::DragValue::new(&mut settings.notch_width_hz)
+                .clamp_range(0.0..=5000.0)
+                .speed(1.0)
+                .prefix("陷波宽度 ")
+                .suffix(" Hz"),
+        );
+        ui.add(
+            egui::DragValue::new(&mut settings.min_freq_hz)
+                .clamp_range(0.0..=1000.0)
+                .speed(1.0)
+                .prefix("频段下限 ")
+                .suffix(" Hz"),
+        );
+
+        ui.horizontal(|ui| {
+            let mut limited = settings.max_freq_hz.is_some();
+            ui.checkbox(&mut limited, "频段上限");
+            if limited {
+                ui.add(egui::DragValue::new(&mut self.max_freq_hz).clamp_range(100.0..=200000.0).speed(10.0).suffix(" Hz"));
+            }
+            settings.max_freq_hz = limited.then_some(self.max_freq_hz);
+        });
+        ui.horizontal(|ui| {
+            let mut fixed = settings.fundamental_hz.is_some();
+            ui.checkbox(&mut fixed, "指定基波");
+            if fixed {
+                ui.add(egui::DragValue::new(&mut self.fundamental_hz).clamp_range(1.0..=100000.0).speed(1.0).suffix(" Hz"));
+            }
+            settings.fundamental_hz = fixed.then_some(self.fundamental_hz);
+        });
+        ui.checkbox(&mut settings.include_aliased, "计入混叠的谐波");
+    }
+}
+
+fn show_result(ui: &mut egui::Ui, index: usize, result: &DistortionResult) {
+    egui::Grid::new(("distortion", index)).num_columns(2).striped(true).show(ui, |ui| {
+        ui.label("基波");
+        ui.label(format!("{}  {:.2} dBFS", format_frequency(result.fundamental_hz), result.fundamental_dbfs()));
+        ui.end_row();
+        ui.label("THD");
+        ui.label(format!("{:.4} %  {:.2} dB", result.thd * 100.0, result.thd_db()));
+        ui.end_row();
+        ui.label("THD+N");
+        ui.label(format!("{:.4} %  {:.2} dB", result.thd_n * 100.0, result.thd_n_db()));
+        ui.end_row();
+        ui.label("SINAD");
+        ui.label(format!("{:.2} dB", result.sinad_db));
+        ui.end_row();
+        ui.label("SNR");
+        ui.label(format!("{:.2} dB", result.snr_db));
+        ui.end_row();
+        ui.label("ENOB");
+        ui.label(format!("{:.2} 位 (满幅折算 {:.2})", result.enob, result.enob_full_scale()));
+        ui.end_row();
+    });
+
+    if result.harmonics.is_empty() {
+        return;
+    }
+    egui::CollapsingHeader::new("各次谐波").id_source(("harmonics", index)).default_open(true).show(ui, |ui| {
+        egui::Grid::new(("harmonic_levels", index)).num_columns(4).striped(true).show(ui, |ui| {
+            for heading in ["次数", "频率", "dBc", "dBFS"] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for harmonic in &result.harmonics {
+                ui.label(harmonic.order.to_string());
+                ui.label(format_frequency(harmonic.frequency));
+                ui.label(format!("{:.2}", harmonic.level_dbc));
+                ui.label(format!("{:.2}", 20.0 * harmonic.amplitude.max(1e-12).log10()));
+                ui.end_row();
+            }
+        });
+    });
+}
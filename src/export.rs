@@ -0,0 +1,328 @@
+// 批处理结果的文件格式：CSV、JSON 和 NumPy .npy（float32）
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Npy,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Npy => "npy",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "npy" => Ok(ExportFormat::Npy),
+            other => Err(format!("Unknown output format: {} (expected csv, json or npy)", other)),
+        }
+    }
+}
+
+// 按行存放的二维表，列名用于CSV表头和JSON键
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<f32>>,
+}
+
+impl Table {
+    pub fn write(&self, path: &Path, format: ExportFormat) -> io::Result<()> {
+        let mut writer = TableWriter::create(path, format, &self.columns)?;
+        for row in &self.rows {
+            writer.write_row(row)?;
+        }
+        writer.finish()
+    }
+}
+
+// 逐行写入的表，不必把所有行留在内存里：CSV、JSON 直接追加，
+// NPY 先写行数为零的头，结束时回到文件开头补上实际行数
+pub struct TableWriter {
+    out: BufWriter<File>,
+    format: ExportFormat,
+    columns: usize,
+    rows: usize,
+}
+
+impl TableWriter {
+    pub fn create(path: &Path, format: ExportFormat, columns: &[String]) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        match format {
+            ExportFormat::Csv => {
+                let columns: Vec<String> = columns.iter().map(|c| csv_field(c)).collect();
+                writeln!(out, "{}", columns.join(","))?;
+            }
+            // {"columns": [...], "rows": [[...], ...]}
+            ExportFormat::Json => {
+                let columns: Vec<String> = columns.iter().map(|c| json_string(c)).collect();
+                write!(out, "{{\"columns\": [{}], \"rows\": [", columns.join(", "))?;
+            }
+            // 行数 × 列数 的 float32 数组，列名不写入
+            ExportFormat::Npy => write_npy_header(&mut out, 0, columns.len())?,
+        }
+        Ok(Self { out, format, columns: columns.len(), rows: 0 })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn write_row(&mut self, row: &[f32]) -> io::Result<()> {
+        if row.len() != self.columns {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("row has {} values, expected {}", row.len(), self.columns),
+            ));
+        }
+        match self.format {
+            ExportFormat::Csv => {
+                let line: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+                writeln!(self.out, "{}", line.join(","))?;
+            }
+            ExportFormat::Json => {
+                let values: Vec<String> = row.iter().map(|&v| json_number(v as f64)).collect();
+                let separator = if self.rows == 0 { "" } else { "," };
+                write!(self.out, "{}\n  [{}]", separator, values.join(", "))?;
+            }
+            ExportFormat::Npy => {
+                for value in row {
+                    self.out.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    // 写完最后一行后调用一次
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self.format {
+            ExportFormat::Csv => {}
+            ExportFormat::Json => writeln!(self.out, "\n]}}")?,
+            ExportFormat::Npy => {
+                self.out.seek(SeekFrom::Start(0))?;
+                write_npy_header(&mut self.out, self.rows, self.columns)?;
+            }
+        }
+        self.out.flush()
+    }
+}
+
+// 行数最多的位数，头按这个长度预留，补写实际行数时长度不变
+const NPY_ROW_DIGITS: usize = 20;
+
+// NPY 1.0 格式头：魔数、版本、头长度，然后是按64字节对齐的字典
+fn write_npy_header(out: &mut impl Write, rows: usize, columns: usize) -> io::Result<()> {
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        rows, columns
+    );
+    header.push_str(&" ".repeat(NPY_ROW_DIGITS - rows.to_string().len()));
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())
+}
+
+// 简单的键值对，写成 JSON 对象
+pub enum JsonValue {
+    Number(f64),
+    Text(String),
+    Object(Vec<(String, JsonValue)>),
+    Array(Vec<JsonValue>),
+}
+
+impl JsonValue {
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let mut text = String::new();
+        self.format_into(&mut text, 0);
+        writeln!(out, "{}", text)?;
+        out.flush()
+    }
+
+    fn format_into(&self, text: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent + 1);
+        match self {
+            JsonValue::Number(value) => text.push_str(&json_number(*value)),
+            JsonValue::Text(value) => text.push_str(&json_string(value)),
+            JsonValue::Object(entries) => {
+                text.push_str("{\n");
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    text.push_str(&pad);
+                    text.push_str(&json_string(key));
+                    text.push_str(": ");
+                    value.format_into(text, indent + 1);
+                    text.push_str(if i + 1 < entries.len() { ",\n" } else { "\n" });
+                }
+                text.push_str(&"  ".repeat(indent));
+                text.push('}');
+            }
+            JsonValue::Array(items) => {
+                text.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    text.push_str(&pad);
+                    item.format_into(text, indent + 1);
+                    text.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                text.push_str(&"  ".repeat(indent));
+                text.push(']');
+            }
+        }
+    }
+}
+
+// 含逗号、引号或换行的字段按 RFC 4180 用引号括起，内部的引号写两次
+pub fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// JSON 不支持 NaN/无穷大，写成 null
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // 写一个两列三行的表，返回文件路径，调用方负责删除
+    fn write_table(name: &str, format: ExportFormat, rows: &[[f32; 2]]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}.{}", name, std::process::id(), format.extension()));
+        let columns = vec!["frequency_hz".to_string(), "level \"db\"".to_string()];
+        let mut writer = TableWriter::create(&path, format, &columns).unwrap();
+        for row in rows {
+            writer.write_row(row).unwrap();
+        }
+        assert_eq!(writer.rows(), rows.len());
+        writer.finish().unwrap();
+        path
+    }
+
+    const ROWS: [[f32; 2]; 3] = [[0.0, f32::NEG_INFINITY], [23.4375, -6.5], [46.875, 0.25]];
+
+    #[test]
+    fn test_npy_layout() {
+        let path = write_table("export_npy", ExportFormat::Npy, &ROWS);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        let data_start = 10 + header_len;
+        assert_eq!(data_start % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..data_start]).unwrap();
+        assert!(header.contains("'shape': (3, 2)"), "{}", header);
+        assert!(header.ends_with('\n'));
+
+        let payload = &bytes[data_start..];
+        assert_eq!(payload.len(), ROWS.len() * 2 * 4);
+        let values: Vec<f32> = payload.chunks(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect();
+        assert_eq!(values[..2], ROWS[0]);
+        assert_eq!(values[4..], ROWS[2]);
+    }
+
+    #[test]
+    fn test_npy_header_length_fixed() {
+        // 行数位数不同时头长度相同，结束时才能原地补写
+        let mut empty = Vec::new();
+        let mut large = Vec::new();
+        write_npy_header(&mut empty, 0, 4097).unwrap();
+        write_npy_header(&mut large, 123_456_789, 4097).unwrap();
+        assert_eq!(empty.len(), large.len());
+        assert_eq!(empty.len() % 64, 0);
+    }
+
+    #[test]
+    fn test_json_rows() {
+        let path = write_table("export_json", ExportFormat::Json, &ROWS);
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["columns"], serde_json::json!(["frequency_hz", "level \"db\""]));
+        let rows = value["rows"].as_array().unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0][0].as_f64(), Some(0.0));
+        assert!(rows[0][1].is_null());
+        assert_eq!(rows[1][1].as_f64(), Some(-6.5));
+    }
+
+    #[test]
+    fn test_json_value_escaping() {
+        let path = std::env::temp_dir().join(format!("export_summary_{}.json", std::process::id()));
+        JsonValue::Object(vec![
+            ("name".to_string(), JsonValue::Text("a\\b \"c\"\n\u{1}".to_string())),
+            ("thd".to_string(), JsonValue::Number(f64::NAN)),
+            ("levels".to_string(), JsonValue::Array(vec![JsonValue::Number(-3.0), JsonValue::Number(f64::INFINITY)])),
+        ])
+        .write(&path)
+        .unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["name"], "a\\b \"c\"\n\u{1}");
+        assert!(value["thd"].is_null());
+        assert_eq!(value["levels"][0].as_f64(), Some(-3.0));
+        assert!(value["levels"][1].is_null());
+    }
+
+    #[test]
+    fn test_csv_layout() {
+        let path = write_table("export_csv", ExportFormat::Csv, &ROWS[1..]);
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines, ["frequency_hz,\"level \"\"db\"\"\"", "23.4375,-6.5", "46.875,0.25"]);
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+    }
+
+    #[test]
+    fn test_row_width_checked() {
+        let path = std::env::temp_dir().join(format!("export_width_{}.csv", std::process::id()));
+        let mut writer = TableWriter::create(&path, ExportFormat::Csv, &["a".to_string()]).unwrap();
+        let error = writer.write_row(&[1.0, 2.0]).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
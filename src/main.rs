@@ -1,75 +1,184 @@
 mod app;
 mod audio;
+mod batch;
+mod distortion_panel;
+mod export;
+mod source_panel;
+mod spectrogram;
 mod spectrum;
 mod ui;
 
 use parking_lot::Mutex;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use std::io::{self, Write};
-use crossbeam_channel::unbounded;
-use cpal::traits::StreamTrait;
-use crate::audio::AudioCapture;
+use crossbeam_channel::{unbounded, RecvTimeoutError};
+use crate::audio::{AudioCommand, CaptureSession, GeneratorSpec, Recovery, SourceSpec};
+use crate::spectrum::{spectrum_channel, AnalysisPipeline, AnalyzerSettings, InputState};
+use myalgorithm::AnalysisConfig;
 
-// 定义设备切换命令
-enum AudioCommand {
-    SwitchDevice(usize),
-    Quit,
+// 音频管理线程检查会话状态的间隔
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+// 从命令行读取参数：--fft-size N --hop-size N --source 源 --play 激励信号 --device-config 设备配置文件，
+// 采样率由打开的音频流决定
+// 步进在运行时可以通过界面上的重叠比例调整
+fn parse_args() -> (AnalysisConfig, SourceSpec, Option<GeneratorSpec>) {
+    let mut config = AnalysisConfig::default();
+    let mut source = SourceSpec::default();
+    let mut stimulus = None;
+    let mut hop_size = None;
+    let mut device_config = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fft-size" => {
+                if let Some(n) = parse_number(&arg, args.next()) {
+                    config.fft_size = n;
+                }
+            }
+            "--hop-size" => hop_size = parse_number(&arg, args.next()).or(hop_size),
+            "--source" => match args.next().as_deref().map(SourceSpec::parse) {
+                Some(Ok(spec)) => source = spec,
+                Some(Err(e)) => eprintln!("{}, 使用默认音频源", e),
+                None => eprintln!("--source 缺少参数"),
+            },
+            "--play" => match args.next().as_deref().map(|v| GeneratorSpec::parse(v.trim_start_matches("gen:"))) {
+                Some(Ok(spec)) => stimulus = Some(spec),
+                Some(Err(e)) => eprintln!("{}, 不播放激励信号", e),
+                None => eprintln!("--play 缺少参数"),
+            },
+            "--device-config" => match args.next() {
+                Some(path) => device_config = Some(PathBuf::from(path)),
+                None => eprintln!("--device-config 缺少参数"),
+            },
+            _ => eprintln!("忽略无法识别的参数: {}", arg),
+        }
+    }
+    // 打开任何设备之前安装设备选择规则
+    if let Err(e) = audio::load_device_policy(device_config.as_deref()) {
+        eprintln!("{}, 使用内置的设备选择规则", e);
+    }
+    config.hop_size = hop_size.unwrap_or(config.fft_size);
+
+    match config.validate() {
+        Ok(()) => (config, source, stimulus),
+        Err(e) => {
+            eprintln!("分析参数无效 ({}), 使用默认值", e);
+            (AnalysisConfig::default(), source, stimulus)
+        }
+    }
+}
+
+// 读取整数参数，缺少或无法解析时打印原因并忽略
+fn parse_number(arg: &str, value: Option<String>) -> Option<usize> {
+    match value.map(|v| v.parse::<usize>().map_err(|_| v)) {
+        Some(Ok(n)) => Some(n),
+        Some(Err(value)) => {
+            eprintln!("{} 需要整数: {}", arg, value);
+            None
+        }
+        None => {
+            eprintln!("{} 缺少参数", arg);
+            None
+        }
+    }
+}
+
+// 打开音频源，失败时只打印原因，界面上显示错误状态
+fn start_source(spec: &SourceSpec, pipeline: &AnalysisPipeline) -> Option<CaptureSession> {
+    match CaptureSession::start(spec, pipeline) {
+        Ok(session) => {
+            println!("已打开音频源: {}", session.info().name);
+            Some(session)
+        }
+        Err(e) => {
+            eprintln!("Failed to start audio source: {}", e);
+            None
+        }
+    }
 }
 
 fn main() {
-    let spectrum = Arc::new(Mutex::new(vec![0.0; 2048]));
-    let audio_capture = AudioCapture::new(spectrum.clone());
+    // batch 子命令不打开窗口，分析完写出结果后退出
+    if std::env::args().nth(1).as_deref() == Some("batch") {
+        if let Err(e) = batch::run(std::env::args().skip(2)) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let (config, source_spec, stimulus) = parse_args();
+    // 界面来不及取走时最多积压的帧数，超出后覆盖最旧的帧
+    let (publisher, receiver) = spectrum_channel(64);
+    let input = Arc::new(Mutex::new(InputState::default()));
+    let settings = Arc::new(Mutex::new(AnalyzerSettings {
+        overlap: config.overlap(),
+        ..Default::default()
+    }));
+    let pipeline = AnalysisPipeline::new(publisher, input.clone(), settings.clone(), config);
     
     // 显示设备列表
-    audio_capture.print_device_list();
+    audio::print_sources();
+
+    // 通过输出设备播放激励信号，流在窗口关闭前一直保持
+    #[cfg(feature = "cpal")]
+    let _stimulus_stream = stimulus.and_then(|spec| match audio::play_output(&spec) {
+        Ok(stream) => Some(stream),
+        Err(e) => {
+            eprintln!("Failed to play stimulus: {}", e);
+            None
+        }
+    });
+    #[cfg(not(feature = "cpal"))]
+    if stimulus.is_some() {
+        eprintln!("--play 需要 cpal 后端");
+    }
     
     // 创建命令通道
     let (cmd_tx, cmd_rx) = unbounded::<AudioCommand>();
     
-    // 启动音频管理线程
+    // 启动音频管理线程，同时监视当前会话，出错或断流时自动恢复
     let audio_handle = std::thread::spawn(move || {
-        let mut current_stream = audio_capture.start_capture()
-            .and_then(|stream| stream.play().ok().map(|_| stream));
-            
-        while let Ok(cmd) = cmd_rx.recv() {
-            match cmd {
-                AudioCommand::SwitchDevice(index) => {
-                    // 先停止当前流
-                    if let Some(stream) = current_stream.take() {
-                        drop(stream);
-                    }
-                    
-                    // 创建新流
-                    if let Some(new_stream) = audio_capture.switch_device(index) {
-                        if new_stream.play().is_ok() {
-                            current_stream = Some(new_stream);
-                            println!("成功切换到新设备");
-                        }
+        let mut current_source = start_source(&source_spec, &pipeline);
+        let mut recovery: Option<Recovery> = None;
+
+        loop {
+            match cmd_rx.recv_timeout(WATCH_INTERVAL) {
+                Ok(AudioCommand::SwitchSource(spec)) => {
+                    // 先停止当前音频源，等它的分析线程退出后再打开新的
+                    if let Some(mut session) = current_source.take() {
+                        session.stop();
                     }
+                    recovery = None;
+                    current_source = start_source(&spec, &pipeline);
                 }
-                AudioCommand::Quit => break,
+                Ok(AudioCommand::Quit) | Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {}
             }
-        }
-    });
 
-    // 启动用户输入线程
-    let cmd_tx_clone = cmd_tx.clone();
-    std::thread::spawn(move || {
-        loop {
-            print!("\n输入设备编号切换设备 (按回车继续): ");
-            io::stdout().flush().unwrap();
-            
-            let mut input = String::new();
-            if io::stdin().read_line(&mut input).is_ok() {
-                if let Ok(index) = input.trim().parse::<usize>() {
-                    let _ = cmd_tx_clone.send(AudioCommand::SwitchDevice(index));
+            if current_source.as_ref().is_some_and(|session| session.needs_recovery()) {
+                if let Some(mut session) = current_source.take() {
+                    recovery = Recovery::new(&session);
+                    session.stop();
                 }
             }
-            std::thread::sleep(Duration::from_millis(100));
+            if let Some(pending) = recovery.as_mut().filter(|r| r.is_due()) {
+                if let Some(session) = pending.retry(&pipeline) {
+                    println!("已恢复音频源: {}", session.info().name);
+                    current_source = Some(session);
+                    recovery = None;
+                }
+            }
+        }
+        if let Some(mut session) = current_source {
+            session.stop();
         }
     });
 
+    // 界面通过命令通道切换音频源
+    let app_commands = cmd_tx.clone();
     let mut options = eframe::NativeOptions::default();
     options.vsync = true;
     options.multisampling = 16;
@@ -87,7 +196,7 @@ fn main() {
         Box::new(move |cc| {
             cc.egui_ctx.set_visuals(egui::Visuals::dark());
             cc.egui_ctx.set_pixels_per_point(1.0);
-            Box::new(app::SpectrumApp::new(spectrum.clone()))
+            Box::new(app::SpectrumApp::new(receiver, input, settings, app_commands, config))
         }),
     )
     .unwrap();
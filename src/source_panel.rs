@@ -0,0 +1,296 @@
+// 音频源面板：选择音频主机、设备和流参数，或输入文件路径、信号发生器、JACK，在界面里直接切换
+#[cfg(feature = "cpal")]
+use cpal::HostId;
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "cpal")]
+use crate::audio::{AudioDeviceManager, DeviceDescription, DeviceEvent, DeviceId, DeviceMonitor, DeviceSpec};
+use crate::audio::{SourceSpec, SourceStatus};
+use crate::spectrum::InputState;
+
+// 设备变化通知在顶部控制栏显示的时长
+const NOTICE_DURATION: Duration = Duration::from_secs(5);
+// 面板里保留的通知条数
+#[cfg(feature = "cpal")]
+const NOTICE_HISTORY: usize = 20;
+
+// 某个音频主机下的输入设备列表和当前的选择，列表由后台线程定期刷新
+#[cfg(feature = "cpal")]
+struct DeviceList {
+    hosts: Vec<HostId>,
+    monitor: DeviceMonitor,
+    devices: Vec<DeviceDescription>,
+    // 已取到的后台列表版本
+    generation: u64,
+    selected: DeviceSpec,
+}
+
+#[cfg(feature = "cpal")]
+impl DeviceList {
+    fn new() -> Self {
+        Self {
+            hosts: AudioDeviceManager::available_hosts(),
+            monitor: DeviceMonitor::start(None),
+            devices: Vec::new(),
+            generation: 0,
+            selected: DeviceSpec::default(),
+        }
+    }
+
+    // 后台列表有变化时取回；选中的设备消失后回到自动选择
+    fn update(&mut self) {
+        let generation = self.monitor.generation();
+        if generation == self.generation {
+            return;
+        }
+        self.generation = generation;
+        self.devices = self.monitor.devices();
+        self.selected.host = self.monitor.host();
+        if self.selected.id.is_some() && self.description().is_none() {
+            self.select(None);
+        }
+    }
+
+    fn description(&self) -> Option<&DeviceDescription> {
+        let id = self.selected.id.as_ref()?;
+        self.devices.iter().find(|d| &d.id == id)
+    }
+
+    // 换设备时流参数恢复为自动
+    fn select(&mut self, id: Option<DeviceId>) {
+        self.selected = DeviceSpec { host: self.selected.host, id, ..Default::default() };
+    }
+}
+
+pub struct SourcePanel {
+    #[cfg(feature = "cpal")]
+    devices: DeviceList,
+    // 文件路径、gen:… 或 jack:… 等其他音频源
+    spec_text: String,
+    error: Option<String>,
+    // 设备接入、移除的通知，最新的在最后
+    notices: VecDeque<(Instant, String)>,
+}
+
+impl SourcePanel {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "cpal")]
+            devices: DeviceList::new(),
+            spec_text: String::new(),
+            error: None,
+            notices: VecDeque::new(),
+        }
+    }
+
+    // 每帧调用，面板隐藏时也要收取设备变化
+    pub fn poll(&mut self) {
+        #[cfg(feature = "cpal")]
+        {
+            let events: Vec<DeviceEvent> = self.devices.monitor.events().collect();
+            for event in events {
+                let text = match event {
+                    DeviceEvent::Added(id) => format!("设备接入: {}", id),
+                    DeviceEvent::Removed(id) => format!("设备移除: {}", id),
+                };
+                if self.notices.len() == NOTICE_HISTORY {
+                    self.notices.pop_front();
+                }
+                self.notices.push_back((Instant::now(), text));
+            }
+            self.devices.update();
+        }
+    }
+
+    // 几秒内最新的一条通知
+    pub fn recent_notice(&self) -> Option<&str> {
+        self.notices
+            .back()
+            .filter(|(time, _)| time.elapsed() < NOTICE_DURATION)
+            .map(|(_, text)| text.as_str())
+    }
+
+    // 显示面板，返回用户要切换到的音频源
+    pub fn show(&mut self, ui: &mut egui::Ui, input: &InputState) -> Option<SourceSpec> {
+        let mut request = None;
+        ui.heading("音频源");
+        show_status(ui, input);
+        ui.separator();
+
+        #[cfg(feature = "cpal")]
+        if let Some(spec) = self.show_devices(ui) {
+            request = Some(SourceSpec::Device(spec));
+        }
+
+        ui.label("其他音频源");
+        let edit = egui::TextEdit::singleline(&mut self.spec_text).hint_text("文件路径、gen:sine:1000@-20、jack");
+        let response = ui.add(edit);
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if ui.button("打开").clicked() || submitted {
+            match SourceSpec::parse(&self.spec_text) {
+                Ok(spec) => {
+                    self.error = None;
+                    request = Some(spec);
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::from_rgb(230, 80, 60), error);
+        }
+
+        if !self.notices.is_empty() {
+            ui.separator();
+            egui::CollapsingHeader::new("设备变化").show(ui, |ui| {
+                for (time, text) in self.notices.iter().rev() {
+                    ui.label(format!("{:.0} s 前  {}", time.elapsed().as_secs_f32(), text));
+                }
+            });
+        }
+        request
+    }
+
+    #[cfg(feature = "cpal")]
+    fn show_devices(&mut self, ui: &mut egui::Ui) -> Option<DeviceSpec> {
+        let list = &mut self.devices;
+        ui.horizontal(|ui| {
+            let mut host = list.selected.host;
+            egui::ComboBox::from_label("主机")
+                .selected_text(host.map_or("默认", |id| id.name()))
+                .show_ui(ui, |ui| {
+                    for &id in &list.hosts {
+                        ui.selectable_value(&mut host, Some(id), id.name());
+                    }
+                });
+            if let Some(id) = host.filter(|&id| Some(id) != list.selected.host) {
+                list.monitor.set_host(id);
+                list.selected.host = Some(id);
+                list.select(None);
+            }
+            if ui.button("刷新").clicked() {
+                list.monitor.rescan();
+            }
+        });
+        if let Some(error) = list.monitor.error() {
+            ui.colored_label(egui::Color32::from_rgb(230, 80, 60), error);
+        }
+
+        egui::ScrollArea::vertical()
+            .id_source("input_devices")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                let automatic = list.selected.id.is_none();
+                if ui.selectable_label(automatic && !list.selected.loopback, "自动选择").clicked() {
+                    list.select(None);
+                }
+                if ui.selectable_label(automatic && list.selected.loopback, "系统输出（回环）").clicked() {
+                    list.select(None);
+                    list.selected.loopback = true;
+                }
+                let mut clicked = None;
+                for device in &list.devices {
+                    let selected = list.selected.id.as_ref() == Some(&device.id);
+                    if ui.selectable_label(selected, device.id.to_string()).clicked() && !selected {
+                        clicked = Some(device.id.clone());
+                    }
+                }
+                if clicked.is_some() {
+                    list.select(clicked);
+                }
+            });
+
+        if let Some(device) = list.description().cloned() {
+            let selected = &mut list.selected;
+            let automatic = |default: Option<String>| match default {
+                Some(value) => format!("自动 ({})", value),
+                None => "自动".to_string(),
+            };
+            egui::ComboBox::from_label("采样率")
+                .selected_text(selected.sample_rate.map_or_else(
+                    || automatic(device.default_sample_rate.map(|r| format!("{} Hz", r))),
+                    |r| format!("{} Hz", r),
+                ))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected.sample_rate, None, "自动");
+                    for &rate in &device.sample_rates {
+                        ui.selectable_value(&mut selected.sample_rate, Some(rate), format!("{} Hz", rate));
+                    }
+                });
+            egui::ComboBox::from_label("声道")
+                .selected_text(selected.channels.map_or_else(
+                    || automatic(device.default_channels.map(|c| c.to_string())),
+                    |c| c.to_string(),
+                ))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected.channels, None, "自动");
+                    for &channels in &device.channels {
+                        ui.selectable_value(&mut selected.channels, Some(channels), channels.to_string());
+                    }
+                });
+            egui::ComboBox::from_label("缓冲")
+                .selected_text(selected.buffer_size.map_or_else(|| "默认".to_string(), |b| format!("{} 帧", b)))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected.buffer_size, None, "默认");
+                    for &frames in &device.buffer_sizes {
+                        ui.selectable_value(&mut selected.buffer_size, Some(frames), format!("{} 帧", frames));
+                    }
+                });
+        }
+
+        let request = ui.button("使用此设备").clicked().then(|| list.selected.clone());
+        ui.separator();
+        request
+    }
+}
+
+// 状态标签，顶部控制栏也使用
+pub fn status_label(ui: &mut egui::Ui, status: &SourceStatus) {
+    match status {
+        SourceStatus::Stopped => {
+            ui.label("未启动");
+        }
+        SourceStatus::Starting => {
+            ui.label("正在打开…");
+        }
+        SourceStatus::Running => {
+            ui.colored_label(egui::Color32::from_rgb(110, 210, 90), "运行中");
+        }
+        SourceStatus::Stalled => {
+            ui.colored_label(egui::Color32::from_rgb(240, 170, 40), "无数据");
+        }
+        SourceStatus::Error(error) => {
+            ui.colored_label(egui::Color32::from_rgb(230, 80, 60), format!("错误: {}", error));
+        }
+        SourceStatus::Reconnecting { attempt, target } => {
+            ui.colored_label(
+                egui::Color32::from_rgb(240, 170, 40),
+                format!("正在重新连接 {} (第 {} 次)", target, attempt),
+            );
+        }
+    }
+}
+
+fn show_status(ui: &mut egui::Ui, input: &InputState) {
+    status_label(ui, &input.status);
+    if !input.info.name.is_empty() {
+        ui.label(&input.info.name);
+        ui.label(format!(
+            "{} Hz  {} ch  {}",
+            input.info.sample_rate, input.info.channels, input.info.format
+        ));
+    }
+
+    if !input.history.is_empty() {
+        egui::CollapsingHeader::new("状态记录").show(ui, |ui| {
+            for (time, status) in input.history.iter().rev() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{:.0} s 前", time.elapsed().as_secs_f32()));
+                    status_label(ui, status);
+                });
+            }
+        });
+    }
+}
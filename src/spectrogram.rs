@@ -0,0 +1,433 @@
+// 时频图（瀑布图）：保存最近若干帧的实时频谱，渲染成纹理，最新的一帧在最上面
+use egui::{Align2, Color32, ColorImage, FontId, Pos2, Rect, Sense, TextureHandle, TextureOptions, Ui};
+use myalgorithm::{AmplitudeScale, FrequencyAxis};
+use std::collections::VecDeque;
+
+use crate::spectrum::SpectrumFrame;
+use crate::ui::{freq_to_x_coord, AmplitudeAxis};
+
+// 纹理的列数，频点按列合并，显示时再由 GPU 插值拉伸
+const COLUMNS: usize = 512;
+const DEFAULT_HISTORY: usize = 600;
+const MAX_HISTORY: usize = 4000;
+// 对数频率轴的起点，与频谱曲线一致
+const LOG_MIN_FREQ: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMap {
+    Viridis,
+    Inferno,
+    Grayscale,
+}
+
+impl ColorMap {
+    pub const ALL: [ColorMap; 3] = [ColorMap::Viridis, ColorMap::Inferno, ColorMap::Grayscale];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorMap::Viridis => "Viridis",
+            ColorMap::Inferno => "Inferno",
+            ColorMap::Grayscale => "Grayscale",
+        }
+    }
+
+    // 等间距的控制点，取自 matplotlib 的同名色图
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            ColorMap::Viridis => &[
+                [68, 1, 84],
+                [71, 44, 122],
+                [59, 81, 139],
+                [44, 113, 142],
+                [33, 144, 141],
+                [39, 173, 129],
+                [92, 200, 99],
+                [170, 220, 50],
+                [253, 231, 37],
+            ],
+            ColorMap::Inferno => &[
+                [0, 0, 4],
+                [31, 12, 72],
+                [85, 15, 109],
+                [136, 34, 106],
+                [186, 54, 85],
+                [227, 89, 51],
+                [249, 140, 10],
+                [249, 201, 50],
+                [252, 255, 164],
+            ],
+            ColorMap::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+        }
+    }
+
+    // 256 级查找表，控制点之间线性插值
+    fn lookup_table(&self) -> Vec<Color32> {
+        let stops = self.stops();
+        let segments = (stops.len() - 1) as f32;
+        (0..256)
+            .map(|level| {
+                let position = level as f32 / 255.0 * segments;
+                let index = (position as usize).min(stops.len() - 2);
+                let t = position - index as f32;
+                let [r, g, b] = [0, 1, 2].map(|c| {
+                    let (a, b) = (stops[index][c] as f32, stops[index + 1][c] as f32);
+                    (a + (b - a) * t).round() as u8
+                });
+                Color32::from_rgb(r, g, b)
+            })
+            .collect()
+    }
+}
+
+// 一帧频谱：输入流中的时刻、各频点读数和着色后的一行像素
+struct Row {
+    timestamp: f64,
+    values: Vec<f32>,
+    pixels: Vec<Color32>,
+}
+
+pub struct Spectrogram {
+    // 显示第几路信号
+    channel: usize,
+    color_map: ColorMap,
+    lut: Vec<Color32>,
+    log_frequency: bool,
+    // 色图两端对应的读数，单位与频谱纵轴相同
+    min: f32,
+    max: f32,
+    history: usize,
+    // 暂停时不再记录新帧，cursor 为选中的行（0 为最新）
+    paused: bool,
+    cursor: usize,
+    // 最新的在前
+    rows: VecDeque<Row>,
+    // 当前记录的频率轴与单位，变化时清空历史
+    axis: Option<(FrequencyAxis, AmplitudeScale)>,
+    // 每列合并的频点范围
+    columns: Vec<(usize, usize)>,
+    texture: Option<TextureHandle>,
+    dirty: bool,
+}
+
+impl Spectrogram {
+    pub fn new() -> Self {
+        let color_map = ColorMap::Viridis;
+        Self {
+            channel: 0,
+            color_map,
+            lut: color_map.lookup_table(),
+            log_frequency: true,
+            min: -120.0,
+            max: 0.0,
+            history: DEFAULT_HISTORY,
+            paused: false,
+            cursor: 0,
+            rows: VecDeque::with_capacity(DEFAULT_HISTORY),
+            axis: None,
+            columns: Vec::new(),
+            texture: None,
+            dirty: true,
+        }
+    }
+
+    // 记录一帧，只取所选那一路的实时曲线
+    pub fn push(&mut self, frame: &SpectrumFrame) {
+        // 路数变少（如从逐声道切到单声道）后所选的一路不再存在，改看第一路
+        if self.channel >= frame.channel_count {
+            self.select_channel(0);
+        }
+        if self.paused || frame.channel != self.channel {
+            return;
+        }
+        let axis = (frame.axis, frame.scaler.scale());
+        if self.axis != Some(axis) {
+            // 换了频率轴或单位后旧数据不可比，色图范围改用新单位的显示范围
+            self.rows.clear();
+            self.axis = Some(axis);
+            (self.min, self.max) = frame.scaler.display_range();
+            self.update_columns();
+        }
+
+        // 历史满时复用最旧一行的内存
+        let mut row = if self.rows.len() >= self.history {
+            self.rows.pop_back().unwrap()
+        } else {
+            Row { timestamp: 0.0, values: Vec::new(), pixels: Vec::new() }
+        };
+        row.timestamp = frame.timestamp;
+        row.values.clear();
+        row.values.extend_from_slice(&frame.traces.live);
+        self.colorize(&mut row);
+        self.rows.push_front(row);
+        self.dirty = true;
+    }
+
+    // 暂停时选中的那一帧，用于在频谱图上显示历史曲线
+    pub fn selected(&self) -> Option<(usize, &[f32])> {
+        if !self.paused {
+            return None;
+        }
+        self.rows.get(self.cursor).map(|row| (self.channel, row.values.as_slice()))
+    }
+
+    // 控制栏：色图、频率轴、范围、历史长度和暂停回看
+    pub fn show_controls(&mut self, ui: &mut Ui, channels: &[SpectrumFrame], amplitude: &AmplitudeAxis) {
+        let previous = (self.color_map, self.log_frequency, self.min, self.max);
+
+        egui::ComboBox::from_label("色图")
+            .selected_text(self.color_map.name())
+            .show_ui(ui, |ui| {
+                for map in ColorMap::ALL {
+                    ui.selectable_value(&mut self.color_map, map, map.name());
+                }
+            });
+        ui.checkbox(&mut self.log_frequency, "对数频率");
+        ui.label("范围");
+        ui.add(egui::DragValue::new(&mut self.min).speed(0.5).suffix(amplitude.unit));
+        ui.add(egui::DragValue::new(&mut self.max).speed(0.5).suffix(amplitude.unit));
+        if self.max <= self.min {
+            self.max = self.min + 1.0;
+        }
+
+        let mut history = self.history;
+        ui.add(egui::DragValue::new(&mut history).clamp_range(10..=MAX_HISTORY).speed(5.0).prefix("历史 ").suffix(" 帧"));
+        if history != self.history {
+            self.history = history;
+            self.rows.truncate(history);
+            self.cursor = self.cursor.min(self.rows.len().saturating_sub(1));
+            self.dirty = true;
+        }
+
+        if self.channel >= channels.len() {
+            self.select_channel(0);
+        }
+        if channels.len() > 1 {
+            let name = channels.get(self.channel).map_or("", |c| c.name.as_str());
+            let mut channel = self.channel;
+            egui::ComboBox::from_label("信号")
+                .selected_text(name)
+                .show_ui(ui, |ui| {
+                    for (index, frame) in channels.iter().enumerate() {
+                        ui.selectable_value(&mut channel, index, &frame.name);
+                    }
+                });
+            if channel != self.channel {
+                self.select_channel(channel);
+            }
+        }
+
+        if ui.button(if self.paused { "继续" } else { "暂停" }).clicked() {
+            self.set_paused(!self.paused);
+        }
+        if self.paused && !self.rows.is_empty() {
+            let mut cursor = self.cursor;
+            let slider = egui::Slider::new(&mut cursor, 0..=self.rows.len() - 1).show_value(false).text("回看");
+            if ui.add(slider).changed() {
+                self.cursor = cursor;
+            }
+            ui.label(format!("{:.2} s", -self.age(self.cursor)));
+        }
+        if ui.button("清除").clicked() {
+            self.clear();
+        }
+
+        if previous.0 != self.color_map {
+            self.lut = self.color_map.lookup_table();
+        }
+        if previous.1 != self.log_frequency {
+            self.update_columns();
+        }
+        if previous != (self.color_map, self.log_frequency, self.min, self.max) {
+            self.recolor();
+        }
+    }
+
+    // 绘制时频图，点击或拖动时暂停并选中对应的帧
+    pub fn show(&mut self, ui: &mut Ui) {
+        let rect = ui.available_rect_before_wrap();
+        let plot_rect = rect.shrink(30.0);
+        if plot_rect.width() <= 0.0 || plot_rect.height() <= 0.0 {
+            return;
+        }
+
+        let response = ui.interact(plot_rect, ui.id().with("spectrogram"), Sense::click_and_drag());
+        if let Some(pointer) = response.interact_pointer_pos() {
+            if !self.rows.is_empty() {
+                let row = ((pointer.y - plot_rect.top()) / plot_rect.height() * self.history as f32) as usize;
+                self.set_paused(true);
+                self.cursor = row.min(self.rows.len() - 1);
+            }
+        }
+
+        self.update_texture(ui.ctx());
+        let painter = ui.painter();
+        painter.rect_filled(plot_rect, 0.0, Color32::BLACK);
+        if let Some(texture) = &self.texture {
+            let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+            painter.image(texture.id(), plot_rect, uv, Color32::WHITE);
+        }
+
+        if self.paused && self.cursor < self.rows.len() {
+            let y = plot_rect.top() + (self.cursor as f32 + 0.5) / self.history as f32 * plot_rect.height();
+            painter.line_segment(
+                [Pos2::new(plot_rect.left(), y), Pos2::new(plot_rect.right(), y)],
+                (1.0, Color32::WHITE),
+            );
+        }
+        self.draw_frequency_marks(painter, &plot_rect);
+        self.draw_time_marks(painter, &plot_rect);
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.cursor = 0;
+    }
+
+    fn select_channel(&mut self, channel: usize) {
+        self.channel = channel;
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+        self.axis = None;
+        self.cursor = 0;
+        self.dirty = true;
+    }
+
+    // 第 row 行距最新一帧的秒数
+    fn age(&self, row: usize) -> f64 {
+        match (self.rows.front(), self.rows.get(row)) {
+            (Some(newest), Some(row)) => newest.timestamp - row.timestamp,
+            _ => 0.0,
+        }
+    }
+
+    // 横轴与频谱曲线相同：对数时从 10 Hz 到奈奎斯特，线性时从直流开始
+    fn max_freq(&self) -> Option<f32> {
+        self.axis.map(|(axis, _)| axis.nyquist())
+    }
+
+    fn column_freq(&self, position: f32, max_freq: f32) -> f32 {
+        if self.log_frequency {
+            let span = max_freq.log10() - LOG_MIN_FREQ.log10();
+            10f32.powf(LOG_MIN_FREQ.log10() + position * span)
+        } else {
+            position * max_freq
+        }
+    }
+
+    fn update_columns(&mut self) {
+        let Some((axis, _)) = self.axis else {
+            self.columns.clear();
+            return;
+        };
+        let last = axis.num_bins() - 1;
+        self.columns = (0..COLUMNS)
+            .map(|column| {
+                let low = self.column_freq(column as f32 / COLUMNS as f32, axis.nyquist());
+                let high = self.column_freq((column + 1) as f32 / COLUMNS as f32, axis.nyquist());
+                // 一列窄于一个频点时取最近的频点
+                let first = axis.hz_to_bin_clamped(low).min(last);
+                let end = axis.hz_to_bin_clamped(high).clamp(first + 1, last + 1);
+                (first, end)
+            })
+            .collect();
+    }
+
+    // 每列取合并频点中的最大值，再按范围查色
+    fn colorize(&self, row: &mut Row) {
+        let span = self.max - self.min;
+        row.pixels.clear();
+        row.pixels.extend(self.columns.iter().map(|&(first, end)| {
+            let value = row.values[first.min(row.values.len())..end.min(row.values.len())]
+                .iter()
+                .fold(f32::NEG_INFINITY, |m, &v| m.max(v));
+            let level = ((value - self.min) / span).clamp(0.0, 1.0);
+            self.lut[(level * 255.0) as usize]
+        }));
+    }
+
+    fn recolor(&mut self) {
+        let mut rows = std::mem::take(&mut self.rows);
+        for row in rows.iter_mut() {
+            self.colorize(row);
+        }
+        self.rows = rows;
+        self.dirty = true;
+    }
+
+    // 历史不满时下方留黑
+    fn update_texture(&mut self, ctx: &egui::Context) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let mut image = ColorImage::new([COLUMNS, self.history], Color32::BLACK);
+        for (index, row) in self.rows.iter().enumerate() {
+            if row.pixels.len() == COLUMNS {
+                image.pixels[index * COLUMNS..(index + 1) * COLUMNS].copy_from_slice(&row.pixels);
+            }
+        }
+        match &mut self.texture {
+            Some(texture) => texture.set(image, TextureOptions::LINEAR),
+            None => self.texture = Some(ctx.load_texture("spectrogram", image, TextureOptions::LINEAR)),
+        }
+    }
+
+    fn draw_frequency_marks(&self, painter: &egui::Painter, plot_rect: &Rect) {
+        let Some(max_freq) = self.max_freq() else {
+            return;
+        };
+        let marks: Vec<f32> = if self.log_frequency {
+            [20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0, 50000.0]
+                .into_iter()
+                .filter(|&f| f <= max_freq)
+                .collect()
+        } else {
+            let step = [100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0]
+                .into_iter()
+                .find(|&step| max_freq / step <= 12.0)
+                .unwrap_or(50000.0);
+            (0..=(max_freq / step) as usize).map(|i| i as f32 * step).collect()
+        };
+
+        for freq in marks {
+            let x = if self.log_frequency {
+                freq_to_x_coord(freq, plot_rect, max_freq)
+            } else {
+                plot_rect.left() + freq / max_freq * plot_rect.width()
+            };
+            painter.line_segment(
+                [Pos2::new(x, plot_rect.bottom()), Pos2::new(x, plot_rect.bottom() + 5.0)],
+                (1.0, Color32::LIGHT_GRAY),
+            );
+            let label = if freq >= 1000.0 { format!("{}k", freq / 1000.0) } else { format!("{}", freq) };
+            painter.text(
+                Pos2::new(x, plot_rect.bottom() + 8.0),
+                Align2::CENTER_TOP,
+                label,
+                FontId::monospace(10.0),
+                Color32::LIGHT_GRAY,
+            );
+        }
+    }
+
+    // 纵轴为时间，标出最新一帧和最旧一帧距现在的秒数
+    fn draw_time_marks(&self, painter: &egui::Painter, plot_rect: &Rect) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let oldest = self.rows.len() - 1;
+        let y = plot_rect.top() + (oldest as f32 + 1.0) / self.history as f32 * plot_rect.height();
+        for (y, label) in [(plot_rect.top(), "0 s".to_string()), (y, format!("{:.1} s", -self.age(oldest)))] {
+            painter.text(
+                Pos2::new(plot_rect.left() - 4.0, y),
+                Align2::RIGHT_CENTER,
+                label,
+                FontId::monospace(10.0),
+                Color32::LIGHT_GRAY,
+            );
+        }
+    }
+}
@@ -1,181 +1,748 @@
-use rustfft::{num_complex::Complex, FftPlanner};
-use std::f32::consts::PI;
-use myalgorithm::get_freq;
-use myalgorithm::get_normalized_db;
-use myalgorithm::SAMPLE_RATE;
-use myalgorithm::BUFFER_SZ_HALF;
-use myalgorithm::BUFFER_SZ;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crossbeam::queue::ArrayQueue;
+use crossbeam_channel::{unbounded, Sender};
+use parking_lot::Mutex;
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+use myalgorithm::{AnalysisConfig, FrequencyAxis, StftBuffer};
+use myalgorithm::{AmplitudeScale, Calibration, SpectrumScaler};
+use myalgorithm::{AveragingSettings, SpectrumAverager};
+use myalgorithm::{ChannelMode, ChannelSplitter};
+use myalgorithm::{analyze_distortion, DistortionResult, DistortionSettings};
+use myalgorithm::{BandLevels, OctaveFilterBank, OctaveSettings, TimeWeighting};
+use myalgorithm::{FrequencyWeighting, LevelMeter, LevelReading, WeightingCurve};
+use crate::audio::{SourceInfo, SourceStatus, Transport};
+use myalgorithm::{SpectrumEngine, Window, WindowFunction};
 
-#[derive(Copy, Clone)]
-pub enum Resolution {
-    Standard,
-    High,
+// 运行中的音频源超过这个时间没有送来样本就显示为中断
+const STALL_TIMEOUT: Duration = Duration::from_millis(1500);
+
+// 同一帧分析得到的几条曲线：实时、平均、峰值保持、最小值保持，单位相同
+#[derive(Clone, Default)]
+pub struct SpectrumTraces {
+    pub live: Vec<f32>,
+    pub average: Vec<f32>,
+    pub max_hold: Vec<f32>,
+    pub min_hold: Vec<f32>,
+}
+
+impl SpectrumTraces {
+    pub fn new(num_bins: usize, value: f32) -> Self {
+        Self {
+            live: vec![value; num_bins],
+            average: vec![value; num_bins],
+            max_hold: vec![value; num_bins],
+            min_hold: vec![value; num_bins],
+        }
+    }
+
+    // 只有频点数变化时才重新分配
+    pub fn resize(&mut self, num_bins: usize) {
+        for trace in [&mut self.live, &mut self.average, &mut self.max_hold, &mut self.min_hold] {
+            trace.resize(num_bins, 0.0);
+        }
+    }
 }
 
-pub struct BandpassFilter {
-    low: f32,
-    high: f32,
-    y1: f32,
-    y2: f32,
+// 分析线程每算完一帧发布给界面的频谱
+#[derive(Clone)]
+pub struct SpectrumFrame {
+    // 第几路输出，以及当前声道模式下的总路数
+    pub channel: usize,
+    pub channel_count: usize,
+    // 显示用的名称，如 CH1、Mid
+    pub name: String,
+    // 分析线程启动或声道模式变化时加一，帧序号随之从零开始
+    pub session: u64,
+    // 本路的帧序号，界面据此发现丢帧和重复帧
+    pub index: u64,
+    // 帧末尾在输入流中的时刻（秒）
+    pub timestamp: f64,
+    // 产生这一帧的分析参数（含采样率）与对应的频率轴
+    pub config: AnalysisConfig,
+    pub axis: FrequencyAxis,
+    pub traces: SpectrumTraces,
+    // 频点数值的单位与换算参数
+    pub scaler: SpectrumScaler,
+    // 当前窗函数的相干增益与等效噪声带宽（Hz）
+    pub coherent_gain: f32,
+    pub enbw_hz: f32,
+    // 开启失真分析时这一帧的测量结果
+    pub distortion: Option<DistortionResult>,
+    // 开启倍频程分析时各频带的电平，否则为空
+    pub bands: BandLevels,
+    // 频率计权后的宽带电平
+    pub level: LevelReading,
 }
 
-impl BandpassFilter {
-    fn new(low: f32, high: f32) -> Self {
+impl SpectrumFrame {
+    // 还没有分析结果时显示的空白帧
+    pub fn new(config: AnalysisConfig) -> Self {
+        let window = Window::new(WindowFunction::default(), config.fft_size);
+        let scaler = SpectrumScaler::new(AmplitudeScale::default(), Calibration::default(), &window, config.sample_rate);
+        let axis = config.freq_axis();
         Self {
-            low,
-            high,
-            y1: 0.0,
-            y2: 0.0,
+            channel: 0,
+            channel_count: 1,
+            name: ChannelMode::default().name(),
+            session: 0,
+            index: 0,
+            timestamp: 0.0,
+            config,
+            axis,
+            traces: SpectrumTraces::new(axis.num_bins(), scaler.display_range().0),
+            scaler,
+            coherent_gain: window.coherent_gain(),
+            enbw_hz: window.enbw_hz(axis.bin_width()),
+            distortion: None,
+            bands: BandLevels::default(),
+            level: LevelReading::default(),
+        }
+    }
+}
+
+// 界面收帧的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    pub received: u64,
+    // 帧序号出现跳跃，说明队列满时被覆盖
+    pub dropped: u64,
+    // 帧序号没有前进
+    pub duplicated: u64,
+}
+
+// 发布端与接收端共享的两个无锁队列：frames 送出分析结果，recycled 把界面用完的帧还回来复用
+struct FrameQueues {
+    frames: ArrayQueue<SpectrumFrame>,
+    recycled: ArrayQueue<SpectrumFrame>,
+    session: AtomicU64,
+}
+
+// 分析线程一侧，发布时不加锁也不等待界面
+#[derive(Clone)]
+pub struct SpectrumPublisher {
+    queues: Arc<FrameQueues>,
+}
+
+// 界面一侧，每次刷新取走所有新帧
+pub struct SpectrumReceiver {
+    queues: Arc<FrameQueues>,
+    // 每路最后收到的 (session, index)
+    last: Vec<Option<(u64, u64)>>,
+    stats: FrameStats,
+}
+
+// 创建一对发布端与接收端，capacity 为界面来不及取时最多积压的帧数
+pub fn spectrum_channel(capacity: usize) -> (SpectrumPublisher, SpectrumReceiver) {
+    let queues = Arc::new(FrameQueues {
+        frames: ArrayQueue::new(capacity.max(1)),
+        recycled: ArrayQueue::new(capacity.max(1) * 2),
+        session: AtomicU64::new(0),
+    });
+    (
+        SpectrumPublisher { queues: queues.clone() },
+        SpectrumReceiver { queues, last: Vec::new(), stats: FrameStats::default() },
+    )
+}
+
+impl SpectrumPublisher {
+    // 开始新的一组帧序号
+    pub fn next_session(&self) -> u64 {
+        self.queues.session.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    // 取一个回收的帧填写后送出，稳态下不分配内存；队列满时覆盖最旧的一帧
+    pub fn publish(&self, fill: impl FnOnce(&mut SpectrumFrame)) {
+        let mut frame = self
+            .queues
+            .recycled
+            .pop()
+            .unwrap_or_else(|| SpectrumFrame::new(AnalysisConfig::default()));
+        fill(&mut frame);
+        if let Some(oldest) = self.queues.frames.force_push(frame) {
+            let _ = self.queues.recycled.push(oldest);
+        }
+    }
+}
+
+impl SpectrumReceiver {
+    // 取出所有新帧，每一帧先交给 observe（时频图需要每一帧），每路只把最新的一帧放进 display，
+    // 多余的路删掉；有新帧时返回true
+    pub fn receive(&mut self, display: &mut Vec<SpectrumFrame>, mut observe: impl FnMut(&SpectrumFrame)) -> bool {
+        let mut updated = false;
+        while let Some(frame) = self.queues.frames.pop() {
+            self.check_sequence(&frame);
+            observe(&frame);
+            let channel = frame.channel;
+            display.truncate(frame.channel_count);
+            if channel < display.len() {
+                let old = std::mem::replace(&mut display[channel], frame);
+                let _ = self.queues.recycled.push(old);
+            } else {
+                // 新出现的路在前面的路到达之前先用同一帧占位
+                while display.len() < channel {
+                    display.push(frame.clone());
+                }
+                display.push(frame);
+            }
+            updated = true;
         }
+        updated
     }
 
-    fn process(&mut self, x: f32, sample_rate: f32) -> f32 {
-        let dt = 1.0 / sample_rate;
-        let rc_low = 1.0 / (2.0 * PI * self.high);
-        let rc_high = 1.0 / (2.0 * PI * self.low);
-        let alpha_low = dt / (rc_low + dt);
-        let alpha_high = rc_high / (rc_high + dt);
-        
-        self.y1 = alpha_low * x + (1.0 - alpha_low) * self.y1;
-        self.y2 = alpha_high * self.y1 + (1.0 - alpha_high) * self.y2;
-        self.y1 - self.y2
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    fn check_sequence(&mut self, frame: &SpectrumFrame) {
+        self.stats.received += 1;
+        if self.last.len() <= frame.channel {
+            self.last.resize(frame.channel + 1, None);
+        }
+        let last = &mut self.last[frame.channel];
+        match *last {
+            Some((session, index)) if session == frame.session => {
+                if frame.index <= index {
+                    self.stats.duplicated += 1;
+                } else {
+                    self.stats.dropped += frame.index - index - 1;
+                }
+            }
+            _ => {}
+        }
+        *last = Some((frame.session, frame.index));
+    }
+}
+
+// 界面上保留的状态变化条数
+const STATUS_HISTORY: usize = 50;
+
+// 当前输入的信息与状态，只在音频源切换或状态变化时更新
+#[derive(Clone, Default)]
+pub struct InputState {
+    pub info: SourceInfo,
+    // 文件输入的播放控制，实时输入为 None
+    pub transport: Option<Transport>,
+    pub status: SourceStatus,
+    // 最近的状态变化，最新的在最后
+    pub history: VecDeque<(Instant, SourceStatus)>,
+}
+
+impl InputState {
+    // 状态变化时记入历史
+    pub fn set_status(&mut self, status: SourceStatus) {
+        if self.status == status {
+            return;
+        }
+        match &status {
+            SourceStatus::Error(error) => eprintln!("音频源错误: {}", error),
+            SourceStatus::Reconnecting { attempt, target } => eprintln!("正在重新连接 {} (第 {} 次)", target, attempt),
+            _ => {}
+        }
+        if self.history.len() == STATUS_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back((Instant::now(), status.clone()));
+        self.status = status;
+    }
+}
+
+// 界面可以在运行时修改的分析设置，分析线程每帧读取
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnalyzerSettings {
+    pub window: WindowFunction,
+    pub scale: AmplitudeScale,
+    // 按输入名称保存的校准参数，未设置的输入使用默认值
+    pub calibrations: HashMap<String, Calibration>,
+    // 频率计权，作用于频谱曲线、倍频程频带和宽带电平，Z 为不计权
+    pub weighting: FrequencyWeighting,
+    // 宽带电平的时间计权
+    pub level_time_weighting: TimeWeighting,
+    // 相邻分析帧的重叠比例 0..=93.75%
+    pub overlap: f32,
+    pub averaging: AveragingSettings,
+    // 每次加一表示请求清空平均和峰值/最小值保持
+    pub averaging_reset: u32,
+    // 多声道输入的拆分方式
+    pub channel_mode: ChannelMode,
+    // 失真分析的参数，None 时不测量
+    pub distortion: Option<DistortionSettings>,
+    // 分数倍频程分析的参数，None 时不分析
+    pub octave: Option<OctaveSettings>,
+    // 每次加一表示请求重新开始各频带和宽带电平的 Leq
+    pub leq_reset: u32,
+}
+
+impl AnalyzerSettings {
+    pub fn calibration_for(&self, input_name: &str) -> Calibration {
+        self.calibrations.get(input_name).copied().unwrap_or_default()
+    }
+}
+
+// 从文本文件读入自定义计权曲线，以文件名（不含扩展名）为曲线名称
+pub fn load_weighting_curve(path: &Path) -> Result<WeightingCurve, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("无法读取计权曲线 {}: {}", path.display(), e))?;
+    let name = path.file_stem().map_or_else(|| path.display().to_string(), |stem| stem.to_string_lossy().into_owned());
+    WeightingCurve::parse(&name, &text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// 分析流水线的输入端，音频源在回调里把交错的f32样本写进来
+pub struct SampleSink {
+    producer: HeapProducer<f32>,
+    errors: Option<Sender<String>>,
+}
+
+impl SampleSink {
+    // 创建给定容量（样本数）的缓冲，返回写入端和读取端
+    pub fn new(capacity: usize) -> (Self, HeapConsumer<f32>) {
+        let (producer, consumer) = HeapRb::<f32>::new(capacity).split();
+        (Self { producer, errors: None }, consumer)
+    }
+
+    // 音频源运行中的错误（如设备断开）送到这个通道
+    pub fn with_errors(self, errors: Sender<String>) -> Self {
+        Self { errors: Some(errors), ..self }
+    }
+
+    // 给音频源的错误回调使用
+    pub fn error_reporter(&self) -> ErrorReporter {
+        ErrorReporter { errors: self.errors.clone() }
+    }
+
+    // 不加锁也不分配内存，可以在实时回调里调用；缓冲已满时丢弃多出的样本并返回false
+    pub fn push(&mut self, samples: &[f32]) -> bool {
+        self.producer.push_slice(samples) == samples.len()
+    }
+
+    // 缓冲剩余的样本数，离线读取文件时据此控制写入速度而不丢样本
+    pub fn free_len(&self) -> usize {
+        self.producer.free_len()
+    }
+}
+
+// 音频源报告运行错误的句柄，总是打印，接到分析线程时还会更新界面上的状态
+#[derive(Clone)]
+pub struct ErrorReporter {
+    errors: Option<Sender<String>>,
+}
+
+impl ErrorReporter {
+    pub fn report(&self, message: String) {
+        eprintln!("{}", message);
+        if let Some(errors) = &self.errors {
+            let _ = errors.send(message);
+        }
+    }
+}
+
+// 运行中的分析线程，stop 或释放时通知它退出并等待结束
+pub struct AnalysisWorker {
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AnalysisWorker {
+    pub fn stop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for AnalysisWorker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// 每路信号独立的分帧、分析状态和曲线缓冲
+pub(crate) struct ChannelPipeline {
+    pub(crate) stft: StftBuffer,
+    pub(crate) analyzer: SpectrumAnalyzer,
+    pub(crate) traces: SpectrumTraces,
+}
+
+impl ChannelPipeline {
+    pub(crate) fn new(config: AnalysisConfig) -> Self {
+        Self {
+            stft: StftBuffer::new(config.fft_size, config.hop_size),
+            analyzer: SpectrumAnalyzer::new(config),
+            traces: SpectrumTraces::default(),
+        }
+    }
+}
+
+// 分帧、拆分声道、分析并发布频谱，与样本来自哪种音频源无关
+#[derive(Clone)]
+pub struct AnalysisPipeline {
+    publisher: SpectrumPublisher,
+    input: Arc<Mutex<InputState>>,
+    settings: Arc<Mutex<AnalyzerSettings>>,
+    config: AnalysisConfig,
+}
+
+impl AnalysisPipeline {
+    pub fn new(
+        publisher: SpectrumPublisher,
+        input: Arc<Mutex<InputState>>,
+        settings: Arc<Mutex<AnalyzerSettings>>,
+        config: AnalysisConfig,
+    ) -> Self {
+        Self { publisher, input, settings, config }
+    }
+
+    // 音频源启动后发布它的播放控制
+    pub fn set_transport(&self, transport: Option<Transport>) {
+        self.input.lock().transport = transport;
+    }
+
+    pub fn set_status(&self, status: SourceStatus) {
+        self.input.lock().set_status(status);
+    }
+
+    pub fn status(&self) -> SourceStatus {
+        self.input.lock().status.clone()
+    }
+
+    // 按音频源的格式启动分析线程，返回写入样本的入口和线程句柄
+    pub fn spawn(&self, source: &SourceInfo) -> Result<(SampleSink, AnalysisWorker), String> {
+        // 使用音频源实际的采样率
+        let analysis_config = self.config.with_sample_rate(source.sample_rate as f32);
+        let channels = source.channels.max(1);
+        let fft_size = analysis_config.fft_size;
+        // 环形缓冲存放交错样本，容量按声道数放大
+        let ring_capacity = (fft_size * 2).max(8192) * channels;
+        let (errors, error_events) = unbounded();
+        let (sink, mut consumer) = SampleSink::new(ring_capacity);
+        let sink = sink.with_errors(errors);
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let stop = shutdown.clone();
+        let input = self.input.clone();
+        let publisher = self.publisher.clone();
+        let settings = self.settings.clone();
+        let input_name = source.name.clone();
+        {
+            let mut input = self.input.lock();
+            input.info = source.clone();
+            input.transport = None;
+        }
+
+        let thread = std::thread::Builder::new()
+            .name("audio_processing".to_string())
+            .spawn(move || {
+                let mut analysis_config = analysis_config;
+                let mut scratch = vec![0.0; ring_capacity];
+                let mut splitter = ChannelSplitter::new(channels, ChannelMode::default());
+                let mut pipelines = Vec::new();
+                let mut names = Vec::new();
+                // 每路的帧序号与帧末尾对应的样本数
+                let mut clocks: Vec<(u64, u64)> = Vec::new();
+                let mut session = 0;
+                let mut last_data = Instant::now();
+                let mut stalled = false;
+
+                while !stop.load(Ordering::Relaxed) {
+                    // 音频源报告的错误直接显示，恢复出数据后再回到运行状态
+                    if let Ok(error) = error_events.try_recv() {
+                        input.lock().set_status(SourceStatus::Error(error));
+                        stalled = true;
+                    }
+
+                    let count = consumer.pop_slice(&mut scratch);
+                    if count == 0 {
+                        // 暂停或播放完的文件不算中断
+                        if !stalled && last_data.elapsed() > STALL_TIMEOUT {
+                            let mut input = input.lock();
+                            let paused = input.transport.as_ref().is_some_and(|t| !t.is_playing());
+                            if !paused && input.status == SourceStatus::Running {
+                                input.set_status(SourceStatus::Stalled);
+                                stalled = true;
+                            }
+                        }
+                        std::thread::sleep(Duration::from_millis(1));
+                        continue;
+                    }
+                    last_data = Instant::now();
+                    if stalled {
+                        let mut input = input.lock();
+                        if matches!(input.status, SourceStatus::Stalled | SourceStatus::Error(_)) {
+                            input.set_status(SourceStatus::Running);
+                        }
+                        stalled = false;
+                    }
+
+                    let (channel_mode, overlap) = {
+                        let settings = settings.lock();
+                        (settings.channel_mode.resolve(channels), settings.overlap)
+                    };
+
+                    // 声道模式变化时重建每路信号的分帧与分析状态
+                    if channel_mode != splitter.mode() || pipelines.is_empty() {
+                        splitter = ChannelSplitter::new(channels, channel_mode);
+                        pipelines = (0..splitter.output_count())
+                            .map(|_| ChannelPipeline::new(analysis_config))
+                            .collect();
+                        names = channel_mode.output_names(channels);
+                        clocks = vec![(0, 0); pipelines.len()];
+                        session = publisher.next_session();
+                    }
+
+                    // 重叠比例可在运行时调整
+                    let hop_size = analysis_config.with_overlap(overlap).hop_size;
+                    if hop_size != analysis_config.hop_size {
+                        analysis_config.hop_size = hop_size;
+                        for pipeline in &mut pipelines {
+                            pipeline.stft.set_hop_size(hop_size);
+                            pipeline.analyzer.set_hop_size(hop_size);
+                        }
+                    }
+
+                    let outputs = splitter.process(&scratch[..count]);
+                    let channel_count = pipelines.len();
+                    for (index, (pipeline, samples)) in pipelines.iter_mut().zip(outputs).enumerate() {
+                        let ChannelPipeline { stft, analyzer, .. } = pipeline;
+                        let (frame_index, frame_end) = &mut clocks[index];
+                        // 从上一帧的末尾位置起算
+                        let mut position = *frame_end;
+                        let name = &names[index];
+                        // 倍频程滤波器组和电平表逐样本处理，不受分帧影响
+                        analyzer.process_levels(samples);
+                        // 每凑满一个步进就分析一帧，与设备回调的块大小无关
+                        stft.push(samples, |frame| {
+                            analyzer.apply_settings(&settings.lock(), &input_name);
+                            position = if *frame_index == 0 {
+                                analysis_config.fft_size as u64
+                            } else {
+                                position + analysis_config.hop_size as u64
+                            };
+                            publisher.publish(|out| {
+                                analyzer.compute_spectrum(frame, &mut out.traces);
+                                let window = analyzer.window();
+                                out.channel = index;
+                                out.channel_count = channel_count;
+                                out.name.clone_from(name);
+                                out.session = session;
+                                out.index = *frame_index;
+                                out.timestamp = position as f64 / analysis_config.sample_rate as f64;
+                                out.config = analysis_config;
+                                out.axis = analysis_config.freq_axis();
+                                out.scaler = *analyzer.scaler();
+                                out.coherent_gain = window.coherent_gain();
+                                out.enbw_hz = window.enbw_hz(out.axis.bin_width());
+                                out.distortion = analyzer.measure_distortion();
+                                analyzer.read_band_levels(&mut out.bands);
+                                out.level = analyzer.level_reading();
+                            });
+                            *frame_index += 1;
+                        });
+                        *frame_end = position;
+                    }
+                }
+            })
+            .map_err(|e| format!("Failed to spawn audio thread: {}", e))?;
+
+        Ok((sink, AnalysisWorker { shutdown, thread: Some(thread) }))
     }
 }
 
 pub struct SpectrumAnalyzer {
-    filters: Vec<BandpassFilter>,
-    fft_planner: FftPlanner<f32>,
-    resolution: Resolution,
-    sample_rate: f32,
+    config: AnalysisConfig,
+    // 缓存的实数FFT计划、窗系数表与中间缓冲
+    engine: SpectrumEngine,
+    magnitudes: Vec<f32>,
+    // 所选的频率计权及其在每个频点上的增益（dB），不计权时增益表为空
+    weighting: FrequencyWeighting,
+    weighting_gains_db: Vec<f32>,
+    scaler: SpectrumScaler,
+    averager: SpectrumAverager,
+    averaging_reset: u32,
+    distortion: Option<DistortionSettings>,
+    // 开启倍频程分析时的滤波器组
+    octave: Option<OctaveFilterBank>,
+    leq_reset: u32,
+    // 计权后的宽带电平
+    meter: LevelMeter,
 }
 
 impl SpectrumAnalyzer {
-    pub fn new(sample_rate: f32) -> Self {
-        let filters = vec![
-            BandpassFilter::new(0.0, 80.0),     // 低频段
-            BandpassFilter::new(80.0, 1000.0),   // 中低频
-            BandpassFilter::new(1000.0, 6000.0), // 中高频
-            BandpassFilter::new(6000.0, 20000.0),// 高频段
-        ];
+    pub fn new(config: AnalysisConfig) -> Self {
+        let engine = SpectrumEngine::new(config.fft_size, WindowFunction::default());
+        let scaler = SpectrumScaler::new(AmplitudeScale::default(), Calibration::default(), engine.window(), config.sample_rate);
 
         Self {
-            filters,
-            fft_planner: FftPlanner::new(),
-            resolution: Resolution::High,
-            sample_rate,
-        }
-    }
-
-    pub fn compute_spectrum(&mut self, audio_buffer: &[f32]) -> Vec<f32> {
-        //使用FFT库（如rustfft）计划一个正向FFT，长度为BUFFER_SZ
-        let fft = self.fft_planner.plan_fft_forward(BUFFER_SZ);
-        //对输入音频audio_buffer应用窗函数（如汉宁窗），减少频谱泄漏
-        let mut complex_buffer = apply_window(audio_buffer);
-        //执行FFT，结果存储在complex_buffer中（复数形式）
-        fft.process(&mut complex_buffer);
-        
-        // 计算RMS和峰值用于动态范围控制
-        // 计算音频的​​rms（有效值）​​，反映整体能量
-        let rms = (audio_buffer.iter().map(|&x| x * x).sum::<f32>() / audio_buffer.len() as f32).sqrt();
-        // 计算​​峰值​peak​，即音频样本的最大绝对值
-        let peak = audio_buffer.iter().fold(0.0f32, |max, &x| max.max(x.abs()));
-        // 动态范围取rms和峰值peak70%的较大者，用于后续幅度调整
-        let dynamic_range = rms.max(peak * 0.7);
-        
-
-        // 修改频谱计算，使用动态范围
-        let mut spectrum: Vec<f32> = complex_buffer.iter()
-            .take(BUFFER_SZ_HALF)
-            .enumerate()
-            .filter_map(|(i, c)| {
-                // 将fft的结果换算成频率
-                let freq = get_freq(i);
-                // 超过最大频率（采样率的一半）的不予处理
-                if freq > SAMPLE_RATE / 2.0 {
-                    return None;
+            config,
+            magnitudes: vec![0.0; engine.num_bins()],
+            weighting: FrequencyWeighting::default(),
+            weighting_gains_db: Vec::new(),
+            engine,
+            scaler,
+            averager: SpectrumAverager::new(config.freq_axis().num_bins(), AveragingSettings::default()),
+            averaging_reset: 0,
+            distortion: None,
+            octave: None,
+            leq_reset: 0,
+            meter: LevelMeter::new(FrequencyWeighting::default(), TimeWeighting::default(), config.sample_rate),
+        }
+    }
+
+    pub fn window(&self) -> &Window {
+        self.engine.window()
+    }
+
+    pub fn scaler(&self) -> &SpectrumScaler {
+        &self.scaler
+    }
+
+    // 切换窗函数，只在类型或参数变化时重新生成系数表
+    pub fn set_window(&mut self, function: WindowFunction) {
+        if self.engine.window().function() != function {
+            self.engine.set_window(function);
+            self.set_scaling(self.scaler.scale(), *self.scaler.calibration());
+        }
+    }
+
+    // 换算系数依赖窗函数的增益，窗变化后也要重建
+    pub fn set_scaling(&mut self, scale: AmplitudeScale, calibration: Calibration) {
+        self.scaler = SpectrumScaler::new(scale, calibration, self.engine.window(), self.config.sample_rate);
+    }
+
+    // 计权变化时重新计算各频点的增益并重建电平表，Leq 随之重新开始
+    pub fn set_weighting(&mut self, weighting: &FrequencyWeighting, time_weighting: TimeWeighting) {
+        if self.weighting != *weighting {
+            self.weighting = weighting.clone();
+            self.weighting_gains_db = if weighting.is_flat() {
+                Vec::new()
+            } else {
+                self.config.freq_axis().frequencies().map(|freq| weighting.gain_db(freq)).collect()
+            };
+        }
+        if self.meter.weighting() != weighting || self.meter.time_weighting() != time_weighting {
+            self.meter = LevelMeter::new(weighting.clone(), time_weighting, self.config.sample_rate);
+        }
+    }
+
+    // 步进决定平均器的帧间隔
+    pub fn set_hop_size(&mut self, hop_size: usize) {
+        self.config.hop_size = hop_size;
+    }
+
+    pub fn set_averaging(&mut self, settings: AveragingSettings) {
+        self.averager.set_settings(settings);
+    }
+
+    pub fn reset_averaging(&mut self) {
+        self.averager.reset();
+    }
+
+    pub fn apply_settings(&mut self, settings: &AnalyzerSettings, input_name: &str) {
+        self.set_window(settings.window);
+        let calibration = settings.calibration_for(input_name);
+        if self.scaler.scale() != settings.scale || *self.scaler.calibration() != calibration {
+            self.set_scaling(settings.scale, calibration);
+        }
+        self.set_weighting(&settings.weighting, settings.level_time_weighting);
+        self.set_averaging(settings.averaging);
+        if self.averaging_reset != settings.averaging_reset {
+            self.averaging_reset = settings.averaging_reset;
+            self.reset_averaging();
+        }
+        self.distortion = settings.distortion;
+        self.set_octave(settings.octave);
+        if self.leq_reset != settings.leq_reset {
+            self.leq_reset = settings.leq_reset;
+            if let Some(bank) = &mut self.octave {
+                bank.reset_leq();
+            }
+            self.meter.reset_leq();
+        }
+    }
+
+    // 频带划分或时间计权变化时重建滤波器组，Leq 随之重新开始
+    pub fn set_octave(&mut self, settings: Option<OctaveSettings>) {
+        match settings {
+            Some(settings) => {
+                if self.octave.as_ref().map(|bank| *bank.settings()) != Some(settings) {
+                    self.octave = Some(OctaveFilterBank::new(settings, self.config.sample_rate));
                 }
+            }
+            None => self.octave = None,
+        }
+    }
 
-                //ERB调整​​：等效矩形带宽模型，模拟人耳对不同频率的感知带宽
-                let erb = 21.4 * (0.00437 * freq + 1.0).log10();
-                /*幅度计算​​：
-                c.norm()获取复数幅度（即FFT结果的模）。
-                除以BUFFER_SZ_HALF（FFT长度的一半）进行归一化，假设FFT结果对称。
-                乘以dynamic_range调整动态范围，增强或抑制整体幅度*/
-                let magnitude = c.norm() / BUFFER_SZ_HALF as f32 * dynamic_range;
-                /*公式：20 * log10(magnitude)，将幅度转换为分贝（dB）。
-                加1e-10避免对零取对数，确保数值稳定*/
-                let db = 20.0 * (magnitude + 1e-10).log10();
-                //归一化与限制​​
-                let normalized_db = get_normalized_db(db).clamp(0.0, 1.2);
-                //ERB加权​​：增加高频的权重，因ERB随频率增大，调整频谱形状以更符合听觉特性
-                Some(normalized_db * (1.0 + erb * 0.1))
-            })
-            .collect();
-        //填充频谱至BUFFER_SZ长度
-        while spectrum.len() < BUFFER_SZ {
-            spectrum.push(0.0);
-        }
-        //应用平滑处理（如移动平均）减少频谱波动
-        smooth_spectrum(&mut spectrum);
-        spectrum
-    }
-
-    fn compute_band_levels(&mut self, samples: &[f32]) -> Vec<f32> {
-        let mut band_levels = Vec::with_capacity(self.filters.len());
-        
-        for filter in &mut self.filters {
-            let filtered: Vec<f32> = samples.iter()
-                .map(|&x| filter.process(x, self.sample_rate))
-                .collect();
-            
-            let rms = (filtered.iter().map(|x| x * x).sum::<f32>() / filtered.len() as f32).sqrt();
-            let peak = filtered.iter().fold(0.0f32, |a, &b| a.max(b.abs()));
-            
-            // 组合RMS和峰值
-            let level = 0.7 * rms + 0.3 * peak;
-            band_levels.push(level);
-        }
-        
-        band_levels
-    }
-}
-
-fn apply_window(audio_buffer: &[f32]) -> Vec<Complex<f32>> {
-    audio_buffer
-        .iter()
-        .enumerate()
-        .map(|(i, &x)| {
-            let window = 0.5 * (1.0 - (2.0 * PI * i as f32 / 4095.0).cos());
-            Complex::new(x * window, 0.0)
-        })
-        .collect()
-}
-
-fn smooth_spectrum(spectrum: &mut Vec<f32>) {
-    /*平滑处理波谱*/
-    for i in 1..spectrum.len()-1 {
-        spectrum[i] = spectrum[i] * 0.5 + spectrum[i - 1] * 0.25 + spectrum[i + 1] * 0.25;
-    }
-}
-
-// 添加新的滤波器实现
-fn apply_bandpass(samples: &[f32], low: f32, high: f32, sample_rate: f32) -> Vec<f32> {
-    // 简单的IIR滤波器实现
-    let dt = 1.0 / sample_rate;
-    let rc_low = 1.0 / (2.0 * PI * high);
-    let rc_high = 1.0 / (2.0 * PI * low);
-    let alpha_low = dt / (rc_low + dt);
-    let alpha_high = rc_high / (rc_high + dt);
-    
-    let mut output = Vec::with_capacity(samples.len());
-    let mut y1 = 0.0;
-    let mut y2 = 0.0;
-    
-    for &x in samples {
-        y1 = alpha_low * x + (1.0 - alpha_low) * y1;
-        y2 = alpha_high * y1 + (1.0 - alpha_high) * y2;
-        output.push(y1 - y2);
-    }
-    
-    output
+    // 把一段原始样本送入电平表和倍频程滤波器组（开启时）
+    pub fn process_levels(&mut self, samples: &[f32]) {
+        self.meter.process(samples);
+        if let Some(bank) = &mut self.octave {
+            bank.process(samples);
+        }
+    }
+
+    pub fn level_reading(&self) -> LevelReading {
+        self.meter.reading()
+    }
+
+    // 各频带按中心频率加上计权增益；没有开启倍频程分析时清空
+    pub fn read_band_levels(&self, out: &mut BandLevels) {
+        match &self.octave {
+            Some(bank) => {
+                bank.read_levels(out);
+                if !self.weighting.is_flat() {
+                    for ((band, level), leq) in out.bands.iter().zip(&mut out.level).zip(&mut out.leq) {
+                        let gain_db = self.weighting.gain_db(band.center);
+                        *level += gain_db;
+                        *leq += gain_db;
+                    }
+                }
+            }
+            None => {
+                out.bands.clear();
+                out.level.clear();
+                out.leq.clear();
+                out.duration = 0.0;
+            }
+        }
+    }
+
+    // 分析一帧（长度为fft_size），结果写入调用方提供的缓冲，稳态下不分配堆内存
+    pub fn compute_spectrum(&mut self, audio_buffer: &[f32], out: &mut SpectrumTraces) {
+        // 加窗并做实数FFT，只得到直流到奈奎斯特频率的频点
+        self.engine.process(audio_buffer, &mut self.magnitudes);
+        // 平均在线性幅度上进行
+        let frame_interval = self.config.hop_size as f32 / self.config.sample_rate;
+        self.averager.process(&self.magnitudes, frame_interval);
+
+        out.resize(self.magnitudes.len());
+        self.scale_trace(&self.magnitudes, &mut out.live);
+        self.scale_trace(self.averager.average(), &mut out.average);
+        self.scale_trace(self.averager.max_hold(), &mut out.max_hold);
+        self.scale_trace(self.averager.min_hold(), &mut out.min_hold);
+    }
+
+    // 在最近一帧的模值上测量失真，与显示单位和频率计权无关；没有开启时返回 None
+    pub fn measure_distortion(&self) -> Option<DistortionResult> {
+        let settings = self.distortion.as_ref()?;
+        analyze_distortion(&self.magnitudes, self.engine.window(), &self.config.freq_axis(), settings)
+    }
+
+    // 按所选单位换算幅度，窗的相干增益/等效噪声带宽已计入换算系数
+    fn scale_trace(&self, magnitudes: &[f32], out: &mut [f32]) {
+        self.scaler.scale_into(magnitudes, out);
+        if self.weighting_gains_db.is_empty() {
+            return;
+        }
+        let is_db = self.scaler.scale().is_db();
+        for (value, &gain_db) in out.iter_mut().zip(&self.weighting_gains_db) {
+            if is_db {
+                *value += gain_db;
+            } else {
+                *value *= 10f32.powf(gain_db / 20.0);
+            }
+        }
+    }
 }
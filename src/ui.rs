@@ -1,22 +1,169 @@
 use egui::{Align2, Color32, FontId, Pos2, Rect, Ui};
-use myalgorithm::SAMPLE_RATE;
-use myalgorithm::MAX_FREQ;
-use myalgorithm::BUFFER_SZ;
-use myalgorithm::get_freq;
-use myalgorithm::get_normalized_db;
-
-// 绘制频谱
-pub fn draw_spectrum(ui: &mut Ui, spectrum: &[f32]) {
+use myalgorithm::{AnalysisConfig, BandLevels, Peak, SpectrumScaler};
+
+// 纵轴：单位与显示范围
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmplitudeAxis {
+    pub unit: &'static str,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl AmplitudeAxis {
+    pub fn from_scaler(scaler: &SpectrumScaler) -> Self {
+        let (min, max) = scaler.display_range();
+        Self {
+            unit: scaler.scale().unit(),
+            min,
+            max,
+        }
+    }
+
+    // 把读数映射到 0..1 的纵向位置
+    pub fn normalize(&self, value: f32) -> f32 {
+        ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+    }
+
+    // 刻度间隔，保证大约6到15条刻度
+    fn tick_step(&self) -> f32 {
+        let span = self.max - self.min;
+        [0.05, 0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0]
+            .into_iter()
+            .find(|&step| span / step <= 15.0)
+            .unwrap_or(100.0)
+    }
+}
+
+// 曲线的着色方式
+#[derive(Debug, Clone, Copy)]
+pub enum TraceStyle {
+    // 按幅度和频率渐变着色
+    Gradient,
+    Solid(Color32),
+}
+
+// 一条待绘制的频谱曲线
+pub struct Trace<'a> {
+    pub values: &'a [f32],
+    pub style: TraceStyle,
+}
+
+// 绘制频谱，peaks 为要标注的峰（按序号标在曲线上，读数列在右上角）
+pub fn draw_spectrum(
+    ui: &mut Ui,
+    traces: &[Trace],
+    peaks: &[Peak],
+    config: &AnalysisConfig,
+    amplitude: &AmplitudeAxis,
+) {
     let rect = ui.available_rect_before_wrap();
     let painter = ui.painter();
     let _clip_rect = ui.clip_rect();
     let plot_rect = rect.shrink(30.0);
 
     draw_background(painter, &plot_rect);
-    draw_spectrum_lines(painter, &plot_rect, spectrum);
+    for trace in traces {
+        draw_spectrum_lines(painter, &plot_rect, trace, config, amplitude);
+    }
+    draw_axes(painter, &plot_rect);
+    draw_frequency_marks(painter, &plot_rect, config);
+    draw_amplitude_marks(painter, &plot_rect, amplitude);
+    draw_peak_markers(painter, &plot_rect, peaks, config, amplitude);
+}
+
+// 倍频程柱状图（RTA），channels 为每路的频带电平（dBFS）和颜色，多路时同一频带的柱并排；
+// 读数加上 offset_db 换算成纵轴单位，show_leq 时柱高为 Leq，否则为时间计权电平并在柱上用横线标出 Leq
+pub fn draw_band_levels(
+    ui: &mut Ui,
+    channels: &[(&BandLevels, Color32)],
+    show_leq: bool,
+    offset_db: f32,
+    amplitude: &AmplitudeAxis,
+) {
+    let rect = ui.available_rect_before_wrap();
+    let painter = ui.painter();
+    let plot_rect = rect.shrink(30.0);
+    draw_background(painter, &plot_rect);
+
+    let Some((first, _)) = channels.first() else {
+        return;
+    };
+    let bands = &first.bands;
+    if bands.is_empty() {
+        return;
+    }
+    let slot = plot_rect.width() / bands.len() as f32;
+    let bar_width = ((slot - 2.0) / channels.len() as f32).max(1.0);
+    let y = |level: f32| plot_rect.bottom() - amplitude.normalize(level + offset_db) * plot_rect.height();
+
+    for (index, band) in bands.iter().enumerate() {
+        let left = plot_rect.left() + index as f32 * slot + 1.0;
+        for (number, (levels, color)) in channels.iter().enumerate() {
+            // 各路的频带划分相同，切换设置的瞬间可能还不一致
+            let (Some(&level), Some(&leq)) = (levels.level.get(index), levels.leq.get(index)) else {
+                continue;
+            };
+            let x = left + number as f32 * bar_width;
+            let top = y(if show_leq { leq } else { level });
+            painter.rect_filled(Rect::from_min_max(Pos2::new(x, top), Pos2::new(x + bar_width, plot_rect.bottom())), 0.0, *color);
+            if !show_leq {
+                painter.line_segment([Pos2::new(x, y(leq)), Pos2::new(x + bar_width, y(leq))], (2.0, Color32::BLACK));
+            }
+        }
+
+        // 标称频率，频带太密时隔几个标一次
+        let every = (36.0 / slot).ceil().max(1.0) as usize;
+        if index % every == 0 {
+            painter.text(
+                Pos2::new(left + slot / 2.0, plot_rect.bottom() + 8.0),
+                Align2::CENTER_TOP,
+                format_band(band.nominal),
+                FontId::monospace(10.0),
+                Color32::LIGHT_GRAY,
+            );
+        }
+    }
+
     draw_axes(painter, &plot_rect);
-    draw_frequency_marks(painter, &plot_rect);
-    draw_db_marks(painter, &plot_rect);
+    draw_amplitude_marks(painter, &plot_rect, amplitude);
+
+    // 鼠标所在频带的读数
+    let hover = ui.input(|i| i.pointer.hover_pos()).filter(|pos| plot_rect.contains(*pos));
+    if let Some(pos) = hover {
+        let index = (((pos.x - plot_rect.left()) / slot) as usize).min(bands.len() - 1);
+        let band = &bands[index];
+        for (number, (levels, color)) in channels.iter().enumerate() {
+            let (Some(&level), Some(&leq)) = (levels.level.get(index), levels.leq.get(index)) else {
+                continue;
+            };
+            let line = format!(
+                "{} Hz ({:.0}-{:.0})  {:.1} {}  Leq {:.1} {}",
+                format_band(band.nominal),
+                band.lower,
+                band.upper,
+                level + offset_db,
+                amplitude.unit,
+                leq + offset_db,
+                amplitude.unit
+            );
+            painter.text(
+                Pos2::new(plot_rect.right() - 6.0, plot_rect.top() + 6.0 + number as f32 * 13.0),
+                Align2::RIGHT_TOP,
+                line,
+                FontId::monospace(11.0),
+                if channels.len() > 1 { *color } else { Color32::BLACK },
+            );
+        }
+    }
+}
+
+// 频带的标称频率，如 31.5、1k、12.5k
+fn format_band(nominal: f32) -> String {
+    if nominal >= 1000.0 {
+        format!("{}k", nominal / 1000.0)
+    } else {
+        format!("{}", nominal)
+    }
 }
 
 // 绘制背景
@@ -25,31 +172,41 @@ fn draw_background(painter: &egui::Painter, plot_rect: &Rect) {
 }
 
 // 绘制频谱曲线
-fn draw_spectrum_lines(painter: &egui::Painter, plot_rect: &Rect, spectrum: &[f32]) {
+fn draw_spectrum_lines(
+    painter: &egui::Painter,
+    plot_rect: &Rect,
+    trace: &Trace,
+    config: &AnalysisConfig,
+    amplitude: &AmplitudeAxis,
+) {
+    let spectrum = trace.values;
     let mut points = Vec::with_capacity(spectrum.len());
     let mut colors = Vec::with_capacity(spectrum.len());
 
-    // 只处理到20kHz的数据
-    let max_index = BUFFER_SZ;
+    // 只处理直流到奈奎斯特频率的数据
+    let axis = config.freq_axis();
+    let max_index = spectrum.len().min(axis.num_bins());
 
     for (i, &value) in spectrum.iter().take(max_index).enumerate() {
         // 计算当前的频率
-        let freq = get_freq(i) + 1.0;
+        let freq = axis.bin_to_hz(i);
 
         // 统一的频率到坐标的映射函数
-        let x = freq_to_x_coord(freq, plot_rect);
+        let x = freq_to_x_coord(freq, plot_rect, config.max_freq());
 
-        let db = 20.0 * (value + 1e-10).log10();
-        let db_normalized = get_normalized_db(db);
-        let height = db_normalized.clamp(0.0, 1.2) * plot_rect.height() * 0.9; // 缩小高度到90%
+        let db_normalized = amplitude.normalize(value);
+        let height = db_normalized * plot_rect.height();
         let id_f32 = i as f32 / max_index as f32;
 
         // 根据频段选择颜色
-        let color = Color32::from_rgb(
-            (255.0 * db_normalized) as u8,
-            (255.0 * (1.0 - db_normalized)) as u8,
-            (255.0 * id_f32) as u8,
-        );
+        let color = match trace.style {
+            TraceStyle::Gradient => Color32::from_rgb(
+                (255.0 * db_normalized) as u8,
+                (255.0 * (1.0 - db_normalized)) as u8,
+                (255.0 * id_f32) as u8,
+            ),
+            TraceStyle::Solid(color) => color,
+        };
 
         points.push(Pos2::new(x, plot_rect.bottom() - height));
         colors.push(color);
@@ -66,10 +223,11 @@ fn draw_spectrum_lines(painter: &egui::Painter, plot_rect: &Rect, spectrum: &[f3
     }
 }
 
-// 添加统一的频率到坐标的映射函数
-fn freq_to_x_coord(freq: f32, plot_rect: &Rect) -> f32 {
-    let log_x = (freq.log10() - 1.0) / 3.5;  
-    plot_rect.left() + log_x * plot_rect.width() + 60.0
+// 添加统一的频率到坐标的映射函数，对数坐标从10Hz覆盖到奈奎斯特频率，时频图也使用
+pub fn freq_to_x_coord(freq: f32, plot_rect: &Rect, max_freq: f32) -> f32 {
+    let log_span = max_freq.log10() - 1.0;
+    let log_x = (freq.max(10.0).log10() - 1.0) / log_span;
+    plot_rect.left() + log_x * plot_rect.width()
 }
 
 fn get_frequency_band_color(_freq: f32, intensity: f32) -> Color32 {
@@ -128,11 +286,12 @@ fn draw_axes(painter: &egui::Painter, plot_rect: &Rect) {
     );
 }
 
-fn draw_frequency_marks(painter: &egui::Painter, plot_rect: &Rect) {
-    let freq_marks = [20, 50, 100, 200, 500, 1000, 2000, 5000, 10000, 20000];
+fn draw_frequency_marks(painter: &egui::Painter, plot_rect: &Rect, config: &AnalysisConfig) {
+    let freq_marks = [20, 50, 100, 200, 500, 1000, 2000, 5000, 10000, 20000, 50000, 90000];
 
-    for &freq in &freq_marks {
-        let x = freq_to_x_coord(freq as f32, plot_rect);
+    // 只标注奈奎斯特频率以内的刻度
+    for &freq in freq_marks.iter().filter(|&&f| f as f32 <= config.max_freq()) {
+        let x = freq_to_x_coord(freq as f32, plot_rect, config.max_freq());
 
         // 刻度线
         painter.line_segment(
@@ -158,12 +317,69 @@ fn draw_frequency_marks(painter: &egui::Painter, plot_rect: &Rect) {
     }
 }
 
-fn draw_db_marks(painter: &egui::Painter, plot_rect: &Rect) {
-    let db_marks = [-60, -50, -40, -30, -20, -10, 0];
-    let plot_height = plot_rect.height() * 0.9; // 使用90%的高度
+fn draw_peak_markers(
+    painter: &egui::Painter,
+    plot_rect: &Rect,
+    peaks: &[Peak],
+    config: &AnalysisConfig,
+    amplitude: &AmplitudeAxis,
+) {
+    let color = Color32::from_rgb(255, 255, 255);
+    for (number, peak) in peaks.iter().enumerate() {
+        let x = freq_to_x_coord(peak.frequency, plot_rect, config.max_freq());
+        let y = plot_rect.bottom() - amplitude.normalize(peak.level) * plot_rect.height();
+        // 峰顶上方的倒三角和序号
+        let tip = Pos2::new(x, y - 3.0);
+        painter.add(egui::Shape::convex_polygon(
+            vec![tip, Pos2::new(x - 4.0, tip.y - 7.0), Pos2::new(x + 4.0, tip.y - 7.0)],
+            color,
+            egui::Stroke::NONE,
+        ));
+        painter.text(
+            Pos2::new(x, tip.y - 9.0),
+            Align2::CENTER_BOTTOM,
+            format!("{}", number + 1),
+            FontId::monospace(10.0),
+            color,
+        );
+
+        // 右上角的读数表
+        let line = format!("{}  {}  {}", number + 1, format_frequency(peak.frequency), format_level(peak.level, amplitude));
+        painter.text(
+            Pos2::new(plot_rect.right() - 6.0, plot_rect.top() + 6.0 + number as f32 * 13.0),
+            Align2::RIGHT_TOP,
+            line,
+            FontId::monospace(11.0),
+            Color32::BLACK,
+        );
+    }
+}
+
+// 插值后的频率保留到百分之一赫兹
+pub fn format_frequency(freq: f32) -> String {
+    if freq >= 1000.0 {
+        format!("{:.4} kHz", freq / 1000.0)
+    } else {
+        format!("{:.2} Hz", freq)
+    }
+}
 
-    for &db in &db_marks {
-        let y = plot_rect.bottom() - get_normalized_db(db) * plot_height;
+fn format_level(value: f32, amplitude: &AmplitudeAxis) -> String {
+    if amplitude.unit == "FS" {
+        format!("{:.5} {}", value, amplitude.unit)
+    } else {
+        format!("{:.2} {}", value, amplitude.unit)
+    }
+}
+
+fn draw_amplitude_marks(painter: &egui::Painter, plot_rect: &Rect, amplitude: &AmplitudeAxis) {
+    let step = amplitude.tick_step();
+    let first = (amplitude.min / step).ceil() as i32;
+    let last = (amplitude.max / step).floor() as i32;
+
+    for tick in first..=last {
+        let value = tick as f32 * step;
+        let y = plot_rect.bottom() - amplitude.normalize(value) * plot_rect.height();
 
         // 刻度线
         painter.line_segment(
@@ -174,11 +390,16 @@ fn draw_db_marks(painter: &egui::Painter, plot_rect: &Rect) {
             (1.0, Color32::LIGHT_GRAY),
         );
 
-        // 分贝标签
+        // 刻度标签
+        let label = if step < 1.0 {
+            format!("{:.2}{}", value, amplitude.unit)
+        } else {
+            format!("{}{}", value, amplitude.unit)
+        };
         painter.text(
             Pos2::new(plot_rect.left() - 8.0, y),
             Align2::RIGHT_CENTER,
-            format!("{}dB", db),
+            label,
             FontId::monospace(10.0),
             Color32::LIGHT_GRAY,
         );